@@ -0,0 +1,11 @@
+CREATE TABLE channel_settings (
+    channel_id VARCHAR PRIMARY KEY,
+    default_repo VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON channel_settings
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,5 @@
+ALTER TABLE pull_requests DROP COLUMN title;
+ALTER TABLE pull_requests DROP COLUMN html_url;
+ALTER TABLE pull_requests DROP COLUMN additions;
+ALTER TABLE pull_requests DROP COLUMN deletions;
+ALTER TABLE pull_requests DROP COLUMN labels;
@@ -0,0 +1,5 @@
+ALTER TABLE pull_requests ADD COLUMN title VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE pull_requests ADD COLUMN html_url VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE pull_requests ADD COLUMN additions INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE pull_requests ADD COLUMN deletions INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE pull_requests ADD COLUMN labels TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+ALTER TABLE pull_requests DROP COLUMN muted;
+DROP TABLE review_claims;
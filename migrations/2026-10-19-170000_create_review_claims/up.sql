@@ -0,0 +1,16 @@
+CREATE TABLE review_claims (
+    id SERIAL PRIMARY KEY,
+    pull_request_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON review_claims
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX inx_review_claims ON review_claims(pull_request_id, user_id);
+
+ALTER TABLE pull_requests ADD COLUMN muted BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,11 @@
+CREATE TABLE repo_settings (
+    repo VARCHAR PRIMARY KEY,
+    merge_method VARCHAR NOT NULL DEFAULT 'merge',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON repo_settings
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
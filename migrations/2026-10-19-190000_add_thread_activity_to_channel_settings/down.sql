@@ -0,0 +1 @@
+ALTER TABLE channel_settings DROP COLUMN thread_activity;
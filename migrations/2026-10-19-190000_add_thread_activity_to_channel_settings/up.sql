@@ -0,0 +1 @@
+ALTER TABLE channel_settings ADD COLUMN thread_activity BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,16 @@
+CREATE TABLE comment_mirrors (
+    id SERIAL PRIMARY KEY,
+    pull_request_id INTEGER NOT NULL,
+    github_comment_id BIGINT NOT NULL,
+    slack_ts VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON comment_mirrors
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX inx_comment_mirrors_github_comment_id ON comment_mirrors(github_comment_id);
+CREATE INDEX inx_comment_mirrors_slack_ts ON comment_mirrors(slack_ts);
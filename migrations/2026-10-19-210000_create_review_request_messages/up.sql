@@ -0,0 +1,17 @@
+CREATE TABLE review_request_messages (
+    id SERIAL PRIMARY KEY,
+    pull_request_github_id VARCHAR NOT NULL,
+    reviewer_github_id INTEGER NOT NULL,
+    channel VARCHAR NOT NULL,
+    slack_ts VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON review_request_messages
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX inx_review_request_messages_pull_request_reviewer
+ON review_request_messages(pull_request_github_id, reviewer_github_id);
@@ -0,0 +1,11 @@
+CREATE TABLE notification_preferences (
+    user_id INTEGER PRIMARY KEY,
+    review_requests BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON notification_preferences
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
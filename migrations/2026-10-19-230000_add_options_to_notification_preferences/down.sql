@@ -0,0 +1,7 @@
+ALTER TABLE notification_preferences
+    DROP COLUMN reviews,
+    DROP COLUMN merges,
+    DROP COLUMN digest,
+    DROP COLUMN quiet_hours_start,
+    DROP COLUMN quiet_hours_end,
+    DROP COLUMN time_zone;
@@ -0,0 +1,7 @@
+ALTER TABLE notification_preferences
+    ADD COLUMN reviews BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN merges BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN digest VARCHAR NOT NULL DEFAULT 'off',
+    ADD COLUMN quiet_hours_start INTEGER,
+    ADD COLUMN quiet_hours_end INTEGER,
+    ADD COLUMN time_zone VARCHAR NOT NULL DEFAULT 'UTC';
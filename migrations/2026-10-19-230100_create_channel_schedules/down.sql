@@ -0,0 +1,2 @@
+DROP TABLE scheduler_locks;
+DROP TABLE channel_schedules;
@@ -0,0 +1,27 @@
+CREATE TABLE channel_schedules (
+    channel_id VARCHAR PRIMARY KEY,
+    digest_hour INTEGER,
+    nudge_after_hours INTEGER,
+    time_zone VARCHAR NOT NULL DEFAULT 'UTC',
+    last_digest_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON channel_schedules
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE scheduler_locks (
+    name VARCHAR PRIMARY KEY,
+    holder VARCHAR NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON scheduler_locks
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
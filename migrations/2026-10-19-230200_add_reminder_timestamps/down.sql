@@ -0,0 +1,2 @@
+ALTER TABLE notification_preferences DROP COLUMN last_digest_at;
+ALTER TABLE pull_requests DROP COLUMN last_nudged_at;
@@ -0,0 +1,2 @@
+ALTER TABLE pull_requests ADD COLUMN last_nudged_at TIMESTAMP;
+ALTER TABLE notification_preferences ADD COLUMN last_digest_at TIMESTAMP;
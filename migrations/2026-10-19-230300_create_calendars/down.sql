@@ -0,0 +1,3 @@
+ALTER TABLE channel_schedules DROP COLUMN calendar_id;
+DROP TABLE holidays;
+DROP TABLE calendars;
@@ -0,0 +1,32 @@
+CREATE TABLE calendars (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE,
+    time_zone VARCHAR NOT NULL DEFAULT 'UTC',
+    working_days INTEGER[] NOT NULL DEFAULT '{1,2,3,4,5}',
+    start_hour INTEGER NOT NULL DEFAULT 9,
+    end_hour INTEGER NOT NULL DEFAULT 17,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON calendars
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE holidays (
+    id SERIAL PRIMARY KEY,
+    calendar_id INTEGER NOT NULL,
+    date DATE NOT NULL,
+    name VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (calendar_id, date)
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON holidays
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+ALTER TABLE channel_schedules ADD COLUMN calendar_id INTEGER;
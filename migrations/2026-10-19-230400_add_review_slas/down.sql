@@ -0,0 +1,5 @@
+DROP TABLE sla_breaches;
+
+ALTER TABLE repo_settings
+    DROP COLUMN review_sla_hours,
+    DROP COLUMN escalation_mention;
@@ -0,0 +1,18 @@
+ALTER TABLE repo_settings
+    ADD COLUMN review_sla_hours INTEGER,
+    ADD COLUMN escalation_mention VARCHAR;
+
+CREATE TABLE sla_breaches (
+    id SERIAL PRIMARY KEY,
+    pull_request_id INTEGER NOT NULL UNIQUE,
+    repo VARCHAR NOT NULL,
+    sla_hours INTEGER NOT NULL,
+    reviewed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON sla_breaches
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
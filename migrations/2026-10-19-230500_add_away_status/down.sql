@@ -0,0 +1,3 @@
+ALTER TABLE users
+    DROP COLUMN away_until,
+    DROP COLUMN away_from_status;
@@ -0,0 +1,3 @@
+ALTER TABLE users
+    ADD COLUMN away_until TIMESTAMP,
+    ADD COLUMN away_from_status BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+DROP TABLE event_logs;
+DROP TABLE ignore_rules;
@@ -0,0 +1,29 @@
+CREATE TABLE ignore_rules (
+    id SERIAL PRIMARY KEY,
+    kind VARCHAR NOT NULL,
+    pattern VARCHAR NOT NULL,
+    repo VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON ignore_rules
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE event_logs (
+    id SERIAL PRIMARY KEY,
+    event VARCHAR NOT NULL,
+    pull_request_url VARCHAR,
+    message VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON event_logs
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE INDEX event_logs_created_at_idx ON event_logs (created_at);
@@ -0,0 +1,5 @@
+ALTER TABLE pull_requests
+    DROP COLUMN digest_id,
+    DROP COLUMN merged;
+
+DROP TABLE dependency_digests;
@@ -0,0 +1,18 @@
+CREATE TABLE dependency_digests (
+    id SERIAL PRIMARY KEY,
+    repo VARCHAR NOT NULL,
+    channel VARCHAR NOT NULL,
+    slack_message_id VARCHAR NOT NULL,
+    open BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON dependency_digests
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+ALTER TABLE pull_requests
+    ADD COLUMN digest_id INTEGER,
+    ADD COLUMN merged BOOLEAN NOT NULL DEFAULT FALSE;
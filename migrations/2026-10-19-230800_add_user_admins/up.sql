@@ -0,0 +1,5 @@
+ALTER TABLE users
+    ADD COLUMN admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Whoever signed in first set the bot up, so they start as its admin
+UPDATE users SET admin = TRUE WHERE id = (SELECT MIN(id) FROM users);
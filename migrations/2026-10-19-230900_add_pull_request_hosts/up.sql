@@ -0,0 +1,6 @@
+ALTER TABLE pull_requests
+    ADD COLUMN host VARCHAR NOT NULL DEFAULT 'github.com';
+
+UPDATE pull_requests
+    SET host = substring(html_url FROM '^https?://(?:www\.)?([^/]+)/')
+    WHERE html_url ~ '^https?://';
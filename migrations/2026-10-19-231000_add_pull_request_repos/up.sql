@@ -0,0 +1,9 @@
+ALTER TABLE pull_requests
+    ADD COLUMN repo VARCHAR NOT NULL DEFAULT '';
+
+UPDATE pull_requests SET repo = regexp_replace(github_id, '-[0-9]+$', '');
+
+ALTER TABLE pull_requests
+    ALTER COLUMN repo DROP DEFAULT;
+
+CREATE INDEX pull_requests_repo_idx ON pull_requests (lower(repo));
@@ -0,0 +1,15 @@
+CREATE TABLE review_views (
+    id SERIAL PRIMARY KEY,
+    view_id VARCHAR NOT NULL,
+    slack_user_id VARCHAR NOT NULL,
+    pull_request_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_timestamp
+BEFORE UPDATE ON review_views
+FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX inx_review_views_view_id ON review_views(view_id);
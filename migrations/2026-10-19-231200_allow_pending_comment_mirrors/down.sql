@@ -0,0 +1,4 @@
+DELETE FROM comment_mirrors WHERE slack_ts IS NULL;
+
+ALTER TABLE comment_mirrors
+    ALTER COLUMN slack_ts SET NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE comment_mirrors
+    ALTER COLUMN slack_ts DROP NOT NULL;
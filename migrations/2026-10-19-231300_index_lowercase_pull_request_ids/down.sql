@@ -0,0 +1 @@
+DROP INDEX pull_requests_lower_github_id_idx;
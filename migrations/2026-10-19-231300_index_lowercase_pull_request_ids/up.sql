@@ -0,0 +1 @@
+CREATE INDEX pull_requests_lower_github_id_idx ON pull_requests (lower(github_id));
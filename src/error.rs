@@ -24,6 +24,13 @@ pub enum Error {
     #[fail(display = "Github Error: {}", _0)]
     GithubError(String),
 
+    #[fail(display = "{} request failed: {}", api, message)]
+    OutboundError {
+        api: String,
+        status: Option<u16>,
+        message: String,
+    },
+
     #[fail(display = "Something went wrong")]
     ServerError(String),
 
@@ -151,4 +158,13 @@ pub enum UrlParseError {
 
     #[fail(display = "Malformed url")]
     Parse(url::ParseError),
+
+    #[fail(display = "Not a pull request url")]
+    NotPullRequest,
+
+    #[fail(display = "Pull request number is invalid")]
+    InvalidNumber,
+
+    #[fail(display = "No default repository is set for this channel")]
+    NoDefaultRepo,
 }
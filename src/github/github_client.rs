@@ -1,24 +1,53 @@
 use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::error::{Error, Result};
 use crate::models;
+use crate::utils::outbound::{self, RetryPolicy};
 use crate::utils::paginated_resource::{PaginatedResource, PaginationParams};
 
 use super::{
-    add_user_token::AddUserToken, FileResult, NewWebhook, PRResult, Repo, ReviewRequest, User,
-    Webhook,
+    add_user_token::AddUserToken,
+    rate_limit::{self, RateLimiter, TokenQuota},
+    response_cache::{CachedResponse, ResponseCache},
+    Comment, FileResult, MergeMethod, MergeRequest, NewComment, NewReview, NewWebhook, PRResult,
+    Repo, ReviewRequest, Team, User, Webhook, GITHUB_HOST,
 };
 
+/// Longest work done in the background will be queued waiting for a token's
+/// rate limit to reset. Requests made while answering Slack or GitHub aren't
+/// queued, since they only wait seconds for an answer.
+const MAX_QUEUE_WAIT: Duration = Duration::from_secs(5 * 60);
+/// The most files GitHub returns in a page of a pull request's files
+const FILES_PER_PAGE: u32 = 100;
+/// GitHub only lists a pull request's first 3000 files
+const MAX_FILE_PAGES: u32 = 30;
+
 #[derive(Clone)]
 pub struct GithubClient {
     url: String,
     client: reqwest::Client,
+    policy: RetryPolicy,
+    rate_limits: RateLimiter,
+    cache: ResponseCache,
+    max_queue_wait: Duration,
+    /// Tokens for the GitHub Enterprise hosts pull requests can come from, so
+    /// github.com tokens are never sent anywhere else
+    enterprise_tokens: HashMap<String, String>,
 }
 
 impl Default for GithubClient {
     fn default() -> Self {
-        let client = reqwest::Client::builder()
+        Self::new(RetryPolicy::default())
+    }
+}
+
+impl GithubClient {
+    pub fn new(policy: RetryPolicy) -> Self {
+        let client = policy
+            .client_builder()
             .user_agent("code-review-bot")
             .build()
             .unwrap();
@@ -26,27 +55,178 @@ impl Default for GithubClient {
         Self {
             url: "https://api.github.com".to_owned(),
             client,
+            policy,
+            rate_limits: RateLimiter::default(),
+            cache: ResponseCache::default(),
+            max_queue_wait: Duration::from_secs(0),
+            enterprise_tokens: HashMap::new(),
         }
     }
-}
 
-impl GithubClient {
+    /// A client sharing this one's rate limits and cache that waits for a
+    /// token's rate limit to reset, for work done after the request is answered
+    pub fn background(&self) -> Self {
+        Self {
+            max_queue_wait: MAX_QUEUE_WAIT,
+            ..self.clone()
+        }
+    }
+
+    /// Allows pull requests from a GitHub Enterprise host, fetched with its own token
+    pub fn enterprise_host(mut self, host: &str, token: &str) -> Self {
+        self.enterprise_tokens
+            .insert(host.to_lowercase(), token.to_owned());
+        self
+    }
+
+    /// The API root for a host and the token to use there, which is `token`
+    /// only on github.com
+    fn endpoint(&self, host: &str, token: &str) -> Result<(String, String)> {
+        if host == GITHUB_HOST {
+            return Ok((self.url.clone(), token.to_owned()));
+        }
+
+        self.enterprise_tokens
+            .get(host)
+            .map(|enterprise_token| (format!("https://{}/api/v3", host), enterprise_token.clone()))
+            .ok_or_else(|| {
+                Error::GithubError(format!(
+                    "{} isn't a GitHub Enterprise host this bot is set up for",
+                    host
+                ))
+            })
+    }
+
     pub async fn get_pr(&self, pull_request: &ReviewRequest, token: &str) -> Result<PRResult> {
+        let (api_url, token) = self.endpoint(&pull_request.host, token)?;
+        let token = token.as_str();
         let request_url = format!(
             "{url}/repos/{owner}/{repo}/pulls/{id}",
-            url = self.url,
+            url = api_url,
             owner = pull_request.owner,
             repo = pull_request.name,
             id = pull_request.id
         );
 
-        self.get_json(&request_url, token).await
+        self.get_cached_json("pulls.get", &request_url, token).await
     }
 
     pub async fn get_files(&self, pull_request: &PRResult, token: &str) -> Result<Vec<FileResult>> {
-        let request_url = format!("{}/files", pull_request.url);
+        let (api_url, token) = self.endpoint(&pull_request.host(), token)?;
+        let token = token.as_str();
+        // Only follow the url GitHub gave when it's on the host the token is for
+        if !pull_request.url.starts_with(&format!("{}/", api_url)) {
+            return Err(Error::GithubError(format!(
+                "{} isn't on {}",
+                pull_request.url, api_url
+            )));
+        }
+
+        let pages = pull_request
+            .changed_files
+            .div_ceil(FILES_PER_PAGE)
+            .clamp(1, MAX_FILE_PAGES);
+        let mut files = vec![];
+        for page in 1..=pages {
+            let request_url = format!(
+                "{}/files?per_page={}&page={}",
+                pull_request.url, FILES_PER_PAGE, page
+            );
+            let page_files: Vec<FileResult> = self
+                .get_cached_json("pulls.files", &request_url, token)
+                .await?;
+            let last_page = page_files.len() < FILES_PER_PAGE as usize;
+            files.extend(page_files);
+            if last_page {
+                break;
+            }
+        }
+        Ok(files)
+    }
+
+    pub async fn create_review(
+        &self,
+        pull_request: &ReviewRequest,
+        review: &NewReview,
+        token: &str,
+    ) -> Result<()> {
+        let (api_url, token) = self.endpoint(&pull_request.host, token)?;
+        let token = token.as_str();
+        let request_url = format!(
+            "{url}/repos/{owner}/{repo}/pulls/{id}/reviews",
+            url = api_url,
+            owner = pull_request.owner,
+            repo = pull_request.name,
+            id = pull_request.id
+        );
+
+        let body = serde_json::to_string(review)?;
+        self.post_json::<serde_json::Value>("pulls.reviews.create", &request_url, &body, token)
+            .await
+            .map(|_| ())
+    }
 
-        self.get_json(&request_url, token).await
+    /// Comments on the pull request's conversation rather than on its diff
+    pub async fn create_issue_comment(
+        &self,
+        pull_request: &ReviewRequest,
+        comment: &NewComment,
+        token: &str,
+    ) -> Result<Comment> {
+        let (api_url, token) = self.endpoint(&pull_request.host, token)?;
+        let token = token.as_str();
+        let request_url = format!(
+            "{url}/repos/{owner}/{repo}/issues/{id}/comments",
+            url = api_url,
+            owner = pull_request.owner,
+            repo = pull_request.name,
+            id = pull_request.id
+        );
+
+        let body = serde_json::to_string(comment)?;
+        self.post_json("issues.comments.create", &request_url, &body, token)
+            .await
+    }
+
+    pub async fn get_repo(&self, pull_request: &ReviewRequest, token: &str) -> Result<Repo> {
+        let (api_url, token) = self.endpoint(&pull_request.host, token)?;
+        let token = token.as_str();
+        let request_url = format!(
+            "{url}/repos/{owner}/{repo}",
+            url = api_url,
+            owner = pull_request.owner,
+            repo = pull_request.name,
+        );
+
+        self.get_cached_json("repos.get", &request_url, token).await
+    }
+
+    pub async fn merge(
+        &self,
+        pull_request: &ReviewRequest,
+        merge_method: MergeMethod,
+        token: &str,
+    ) -> Result<()> {
+        let (api_url, token) = self.endpoint(&pull_request.host, token)?;
+        let token = token.as_str();
+        let request_url = format!(
+            "{url}/repos/{owner}/{repo}/pulls/{id}/merge",
+            url = api_url,
+            owner = pull_request.owner,
+            repo = pull_request.name,
+            id = pull_request.id
+        );
+
+        let body = serde_json::to_string(&MergeRequest { merge_method })?;
+        let request = self
+            .client
+            .put(&request_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .add_token(token)
+            .body(body);
+
+        let res = self.send("pulls.merge", request, token, false).await?;
+        error_with_message("pulls.merge", res).await.map(|_| ())
     }
 
     pub async fn create_webhook(
@@ -55,14 +235,16 @@ impl GithubClient {
         webhook_url: &str,
         token: &str,
     ) -> Result<Webhook> {
+        let (api_url, token) = self.endpoint(&pull_request.host, token)?;
+        let token = token.as_str();
         let request_url = format!(
             "{url}/repos/{owner}/{repo}/hooks",
-            url = self.url,
+            url = api_url,
             owner = pull_request.owner,
             repo = pull_request.name,
         );
 
-        let hooks: Vec<Webhook> = self.get_json(&request_url, token).await?;
+        let hooks: Vec<Webhook> = self.get_json("hooks.list", &request_url, token).await?;
 
         if let Some(hook) = hooks
             .iter()
@@ -72,7 +254,8 @@ impl GithubClient {
             Ok(hook)
         } else {
             let body = serde_json::to_string(&NewWebhook::new(webhook_url)).unwrap();
-            self.post_json(&request_url, &body, token).await
+            self.post_json("hooks.create", &request_url, &body, token)
+                .await
         }
     }
 
@@ -85,13 +268,40 @@ impl GithubClient {
             hook_id = hook.hook_id,
         );
 
-        self.delete(&request_url, token).await.map(|_| ())
+        self.delete("hooks.delete", &request_url, token)
+            .await
+            .map(|_| ())
     }
 
     pub async fn get_user(&self, access_token: &str) -> Result<User> {
         let request_url = format!("{url}/user", url = self.url,);
 
-        self.get_json(&request_url, access_token).await
+        self.get_json("user.get", &request_url, access_token).await
+    }
+
+    pub async fn get_user_by_login(&self, login: &str, access_token: &str) -> Result<User> {
+        let request_url = format!("{url}/users/{login}", url = self.url, login = login);
+
+        self.get_cached_json("users.get", &request_url, access_token)
+            .await
+    }
+
+    /// Members of a team in the organization that owns the repository
+    pub async fn get_team_members(
+        &self,
+        pull_request: &PRResult,
+        team: &Team,
+        access_token: &str,
+    ) -> Result<Vec<User>> {
+        let request_url = format!(
+            "{url}/orgs/{org}/teams/{slug}/members",
+            url = self.url,
+            org = pull_request.base.repo.owner.login,
+            slug = team.slug
+        );
+
+        self.get_cached_json("teams.members", &request_url, access_token)
+            .await
     }
 
     pub async fn get_repos(
@@ -106,68 +316,236 @@ impl GithubClient {
             page = params.page.as_ref().unwrap_or(&"1".to_owned()),
         );
 
-        let res = self.get(&request_url, access_token).await?;
-
-        let link_header = res.headers().get(reqwest::header::LINK).cloned();
-        let resources: Vec<Repo> = res.json().await?;
+        let res = self
+            .get_cached("user.repos", &request_url, access_token)
+            .await?;
+        let resources: Vec<Repo> = serde_json::from_str(&res.body)?;
 
-        if let Some(link_str) = link_header {
-            let link = hyperx::header::Link::from_str(
-                link_str
-                    .to_str()
-                    .map_err(|_| Error::ServerError("header error".to_string()))?,
-            )?;
+        if let Some(link_str) = res.link {
+            let link = hyperx::header::Link::from_str(&link_str)?;
             PaginatedResource::new(resources, link.values())
         } else {
             PaginatedResource::new(resources, &[])
         }
     }
 
-    async fn get_json<T>(&self, url: &str, token: &str) -> Result<T>
+    pub fn quotas(&self) -> Vec<TokenQuota> {
+        self.rate_limits.quotas()
+    }
+
+    async fn get_json<T>(&self, api: &str, url: &str, token: &str) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        self.get(url, token)
+        self.get(api, url, token)
             .await?
             .json()
             .await
             .map_err(|e| e.into())
     }
 
-    async fn get(&self, url: &str, token: &str) -> Result<reqwest::Response> {
-        self.client
-            .get(url)
-            .add_token(token)
-            .send()
-            .await?
-            .error_for_status()
-            .map_err(|e| e.into())
+    async fn get_cached_json<T>(&self, api: &str, url: &str, token: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let res = self.get_cached(api, url, token).await?;
+
+        serde_json::from_str(&res.body).map_err(|e| e.into())
     }
 
-    async fn delete(&self, url: &str, token: &str) -> Result<reqwest::Response> {
-        self.client
-            .delete(url)
-            .add_token(token)
-            .send()
-            .await?
-            .error_for_status()
-            .map_err(|e| e.into())
+    /// Sends a conditional request when the response is cached, reusing the
+    /// cached body when GitHub reports it unchanged
+    async fn get_cached(&self, api: &str, url: &str, token: &str) -> Result<CachedResponse> {
+        let cached = self.cache.get(token, url);
+        let mut request = self.client.get(url).add_token(token);
+        if let Some(etag) = cached.as_ref().and_then(|c| c.etag.as_ref()) {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag.as_str());
+        }
+
+        let mut res = self.send(api, request, token, true).await?;
+        if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+            match cached {
+                Some(cached) => return Ok(cached),
+                // There's no body to reuse, so ask again unconditionally
+                None => {
+                    let request = self
+                        .client
+                        .get(url)
+                        .add_token(token)
+                        .header(reqwest::header::CACHE_CONTROL, "no-cache");
+                    res = self.send(api, request, token, true).await?;
+                }
+            }
+        }
+
+        let etag = header_string(&res, reqwest::header::ETAG);
+        let link = header_string(&res, reqwest::header::LINK);
+        let response = CachedResponse {
+            etag,
+            link,
+            body: outbound::error_for_status(api, res)?.text().await?,
+        };
+        self.cache.insert(token, url, &response);
+
+        Ok(response)
+    }
+
+    async fn get(&self, api: &str, url: &str, token: &str) -> Result<reqwest::Response> {
+        let res = self
+            .send(api, self.client.get(url).add_token(token), token, true)
+            .await?;
+
+        outbound::error_for_status(api, res)
+    }
+
+    async fn delete(&self, api: &str, url: &str, token: &str) -> Result<reqwest::Response> {
+        let res = self
+            .send(api, self.client.delete(url).add_token(token), token, true)
+            .await?;
+
+        outbound::error_for_status(api, res)
     }
 
-    async fn post_json<T>(&self, url: &str, body: &str, token: &str) -> Result<T>
+    /// Sends a request once the token's quota allows it, retrying once if GitHub
+    /// rejects it for exceeding a rate limit and the wait is short enough
+    async fn send(
+        &self,
+        api: &str,
+        request: reqwest::RequestBuilder,
+        token: &str,
+        idempotent: bool,
+    ) -> Result<reqwest::Response> {
+        let mut retried = false;
+
+        loop {
+            if let Some(wait) = self.rate_limits.wait_time(token) {
+                if wait > self.max_queue_wait {
+                    return Err(Error::GithubError(format!(
+                        "Rate limit exceeded, retry in {} seconds",
+                        wait.as_secs()
+                    )));
+                }
+                actix_rt::time::delay_for(wait).await;
+            }
+
+            let attempt = request
+                .try_clone()
+                .ok_or_else(|| Error::ServerError("Request can't be retried".to_string()))?;
+            let res = self.policy.send(api, attempt, idempotent).await?;
+
+            if rate_limit::is_rate_limited(res.status(), res.headers()) {
+                let until = self.rate_limits.block(token, res.headers());
+                log::warn!("Github rate limit hit, blocked until {}", until);
+
+                let wait = self.rate_limits.wait_time(token).unwrap_or_default();
+                if !retried && wait <= self.max_queue_wait {
+                    retried = true;
+                    continue;
+                }
+            } else {
+                self.rate_limits.update(token, res.headers());
+            }
+
+            return Ok(res);
+        }
+    }
+
+    async fn post_json<T>(&self, api: &str, url: &str, body: &str, token: &str) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        self.client
+        let request = self
+            .client
             .post(url)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .add_token(token)
-            .body(body.to_owned())
-            .send()
+            .body(body.to_owned());
+
+        let res = self.send(api, request, token, false).await?;
+
+        error_with_message(api, res)
             .await?
-            .error_for_status()?
             .json()
             .await
             .map_err(|e| e.into())
     }
 }
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+/// Like `outbound::error_for_status`, but keeps the message GitHub explains a
+/// rejected write with, e.g. why a review or merge wasn't allowed
+async fn error_with_message(api: &str, res: reqwest::Response) -> Result<reqwest::Response> {
+    let status = res.status();
+    if !status.is_client_error() {
+        return outbound::error_for_status(api, res);
+    }
+
+    let message = res
+        .json::<ErrorMessage>()
+        .await
+        .map(|error| error.message)
+        .unwrap_or_else(|_| {
+            status
+                .canonical_reason()
+                .unwrap_or("Request failed")
+                .to_owned()
+        });
+
+    Err(Error::OutboundError {
+        api: api.to_owned(),
+        status: Some(status.as_u16()),
+        message,
+    })
+}
+
+fn header_string(res: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    res.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        let client = GithubClient::default().enterprise_host("Git.Example.com", "ghe-token");
+
+        assert_eq!(
+            client.endpoint(GITHUB_HOST, "user-token").unwrap(),
+            (
+                "https://api.github.com".to_string(),
+                "user-token".to_string()
+            )
+        );
+        assert_eq!(
+            client.endpoint("git.example.com", "user-token").unwrap(),
+            (
+                "https://git.example.com/api/v3".to_string(),
+                "ghe-token".to_string()
+            )
+        );
+        assert!(client.endpoint("evil.example", "user-token").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_send_fails_fast_when_rate_limited() {
+        let client = GithubClient::default();
+        client
+            .rate_limits
+            .block("token", &reqwest::header::HeaderMap::new());
+
+        let request = client.client.get("https://api.github.com/rate_limit");
+        match client.send("rate_limit", request, "token", true).await {
+            Err(Error::GithubError(message)) => assert!(message.starts_with("Rate limit exceeded")),
+            other => panic!("Expected a rate limit error, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(client.background().max_queue_wait, MAX_QUEUE_WAIT);
+    }
+}
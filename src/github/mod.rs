@@ -1,19 +1,45 @@
 use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
 
 mod add_user_token;
 mod github_client;
 mod github_oauth_client;
+mod rate_limit;
+mod response_cache;
 mod review_request;
 pub use github_client::GithubClient;
 pub use github_oauth_client::GithubOauthClient;
-pub use review_request::ReviewRequest;
+pub use rate_limit::TokenQuota;
+pub use review_request::{ReviewRequest, GITHUB_HOST};
+
+/// Hidden in the body of comments posted from Slack so they aren't mirrored
+/// back into the thread they came from
+pub const SLACK_COMMENT_MARKER: &str = "<!-- code-review-bot:slack -->";
+
+/// The key tracked pull requests are stored under
+pub fn github_id(repo: &str, number: u32) -> String {
+    format!("{}-{}", repo, number)
+}
 
 #[derive(Deserialize, Debug)]
 pub struct PullRequestEvent {
     pub number: u32,
     pub action: PRAction,
     pub pull_request: PRResult,
+    #[serde(default)]
+    pub sender: Option<User>,
+    /// Set on review request events, for a person or a team
+    #[serde(default)]
+    pub requested_reviewer: Option<User>,
+    #[serde(default)]
+    pub requested_team: Option<Team>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Team {
+    pub slug: String,
+    pub name: String,
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -24,7 +50,7 @@ pub enum PRAction {
     ReviewRequested,
     ReviewRequestRemoved,
     Labeled,
-    Unlabled,
+    Unlabeled,
     Opened,
     ReadyForReview,
     Reopened,
@@ -38,6 +64,8 @@ pub struct ReviewEvent {
     pub action: ReviewAction,
     pub pull_request: ReviewPR,
     pub review: PRReview,
+    #[serde(default)]
+    pub sender: Option<User>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -48,10 +76,60 @@ pub enum ReviewAction {
     Dismissed,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ReviewCommentEvent {
+    pub action: CommentAction,
+    pub comment: Comment,
+    pub pull_request: ReviewPR,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IssueCommentEvent {
+    pub action: CommentAction,
+    pub comment: Comment,
+    pub issue: Issue,
+    pub repository: Repo,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentAction {
+    Created,
+    Edited,
+    Deleted,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Comment {
+    pub id: i64,
+    pub body: String,
+    pub html_url: String,
+    pub user: User,
+    /// The file a review comment is on, issue comments have none
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// Pull requests are issues too, and issue comment events only say which is which
+/// by whether `pull_request` is present
+#[derive(Deserialize, Debug)]
+pub struct Issue {
+    pub number: u32,
+    #[serde(default)]
+    pub pull_request: Option<IssuePullRequest>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IssuePullRequest {}
+
 #[derive(Deserialize, Debug)]
 pub struct PRReview {
     pub state: PRReviewState,
     pub user: User,
+    #[serde(default)]
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -60,6 +138,7 @@ pub enum PRReviewState {
     ChangesRequested,
     Approved,
     Commented,
+    Dismissed,
 }
 
 impl fmt::Display for PRReviewState {
@@ -74,6 +153,90 @@ impl fmt::Display for PRReviewState {
     }
 }
 
+/// The outcome of a review submitted through the API
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewVerdict {
+    Approve,
+    RequestChanges,
+    Comment,
+}
+
+impl fmt::Display for ReviewVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_json::to_string(self)
+                .map_err(|_| fmt::Error)?
+                .replace("\"", "")
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewReview {
+    pub event: ReviewVerdict,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewComment {
+    pub body: String,
+}
+
+impl NewComment {
+    /// A comment relayed from Slack, marked so it isn't mirrored back
+    pub fn from_slack(text: &str) -> Self {
+        Self {
+            body: format!("{}\n\n{}", text, SLACK_COMMENT_MARKER),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMethod {
+    Merge,
+    Squash,
+    Rebase,
+}
+
+impl Default for MergeMethod {
+    fn default() -> Self {
+        MergeMethod::Merge
+    }
+}
+
+impl fmt::Display for MergeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeMethod::Merge => write!(f, "merge"),
+            MergeMethod::Squash => write!(f, "squash"),
+            MergeMethod::Rebase => write!(f, "rebase"),
+        }
+    }
+}
+
+impl FromStr for MergeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(MergeMethod::Merge),
+            "squash" => Ok(MergeMethod::Squash),
+            "rebase" => Ok(MergeMethod::Rebase),
+            _ => Err(format!("`{}` isn't a merge method", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MergeRequest {
+    merge_method: MergeMethod,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ReviewPR {
     pub url: String,
@@ -95,6 +258,12 @@ pub struct User {
     pub login: String,
     pub avatar_url: String,
     pub html_url: String,
+    /// Only the users endpoint includes the public email, webhooks leave it out
+    #[serde(default)]
+    pub email: Option<String>,
+    /// `User`, `Bot` or `Organization`
+    #[serde(rename = "type", default)]
+    pub account_type: String,
 }
 
 #[derive(Clone, Deserialize, Debug, Serialize)]
@@ -103,6 +272,7 @@ pub struct Repo {
     pub owner: User,
     pub name: String,
     pub full_name: String,
+    pub private: bool,
     #[serde(default)]
     pub permissions: RepoPermissions,
 }
@@ -117,6 +287,20 @@ pub struct RepoPermissions {
 #[derive(Clone, Deserialize, Debug)]
 pub struct Base {
     pub repo: Repo,
+    /// The branch name
+    #[serde(rename = "ref", default)]
+    pub ref_name: String,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Head {
+    #[serde(rename = "ref", default)]
+    pub ref_name: String,
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct Label {
+    pub name: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -132,10 +316,15 @@ pub struct PRResult {
     pub deletions: u32,
     pub number: u32,
     #[serde(default)]
+    pub changed_files: u32,
+    #[serde(default)]
     pub draft: bool,
+    #[serde(default)]
+    pub labels: Vec<Label>,
 
     pub user: User,
     pub base: Base,
+    pub head: Head,
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -182,6 +371,15 @@ impl fmt::Display for PRResult {
 }
 
 impl PRResult {
+    /// The host the pull request lives on, taken from its url
+    pub fn host(&self) -> String {
+        url::Url::parse(&self.html_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+            .map(|host| host.trim_start_matches("www.").to_owned())
+            .unwrap_or_else(|| GITHUB_HOST.to_owned())
+    }
+
     pub fn image_path(&self) -> String {
         if let PRState::Open = self.state {
             "/public/images/open.png".to_string()
@@ -192,6 +390,10 @@ impl PRResult {
         }
     }
 
+    pub fn label_names(&self) -> Vec<String> {
+        self.labels.iter().map(|label| label.name.clone()).collect()
+    }
+
     pub fn open(&self) -> bool {
         if let PRState::Open = self.state {
             true
@@ -234,6 +436,8 @@ impl NewWebhook {
             events: vec![
                 "pull_request".to_string(),
                 "pull_request_review".to_string(),
+                "pull_request_review_comment".to_string(),
+                "issue_comment".to_string(),
             ],
             config: WebhookConfig {
                 url: webhook_url.to_string(),
@@ -0,0 +1,295 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Seconds to wait on a secondary rate limit when GitHub doesn't send `Retry-After`
+const DEFAULT_BACKOFF_SECS: i64 = 60;
+
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: DateTime<Utc>,
+    pub blocked_until: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    fn available_at(&self) -> Option<DateTime<Utc>> {
+        let exhausted_until = if self.remaining == 0 {
+            Some(self.reset)
+        } else {
+            None
+        };
+
+        match (exhausted_until, self.blocked_until) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// The quota for a single token, with the token masked for display
+#[derive(Clone, Debug)]
+pub struct TokenQuota {
+    pub token: String,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: DateTime<Utc>,
+    pub blocked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+}
+
+impl RateLimiter {
+    /// How long a request with this token has to wait before it can be sent
+    pub fn wait_time(&self, token: &str) -> Option<std::time::Duration> {
+        let limits = self.limits.lock().expect("Rate limits can't be accessed");
+        let now = Utc::now();
+
+        limits
+            .get(token)
+            .and_then(RateLimit::available_at)
+            .filter(|available_at| *available_at > now)
+            .and_then(|available_at| (available_at - now).to_std().ok())
+    }
+
+    pub fn update(&self, token: &str, headers: &HeaderMap) {
+        let remaining = header_value(headers, REMAINING_HEADER);
+        let limit = header_value(headers, LIMIT_HEADER);
+        let reset = header_value(headers, RESET_HEADER).and_then(timestamp);
+
+        if let (Some(remaining), Some(limit), Some(reset)) = (remaining, limit, reset) {
+            let mut limits = self.limits.lock().expect("Rate limits can't be accessed");
+            let blocked_until = limits
+                .get(token)
+                .and_then(|rate_limit| rate_limit.blocked_until)
+                .filter(|blocked_until| *blocked_until > Utc::now());
+
+            limits.insert(
+                token.to_owned(),
+                RateLimit {
+                    limit: limit as u32,
+                    remaining: remaining as u32,
+                    reset,
+                    blocked_until,
+                },
+            );
+        }
+    }
+
+    /// Blocks a token after GitHub rejected a request for exceeding a rate limit,
+    /// returning when requests can be sent again
+    pub fn block(&self, token: &str, headers: &HeaderMap) -> DateTime<Utc> {
+        let now = Utc::now();
+        let until = header_value(headers, RETRY_AFTER.as_str())
+            .map(|secs| now + Duration::seconds(secs))
+            .or_else(|| {
+                header_value(headers, RESET_HEADER)
+                    .filter(|_| header_value(headers, REMAINING_HEADER) == Some(0))
+                    .and_then(timestamp)
+            })
+            .unwrap_or_else(|| now + Duration::seconds(DEFAULT_BACKOFF_SECS));
+
+        let mut limits = self.limits.lock().expect("Rate limits can't be accessed");
+        let rate_limit = limits.entry(token.to_owned()).or_insert(RateLimit {
+            limit: 0,
+            remaining: 0,
+            reset: until,
+            blocked_until: None,
+        });
+        rate_limit.blocked_until.replace(until);
+
+        until
+    }
+
+    pub fn quotas(&self) -> Vec<TokenQuota> {
+        let limits = self.limits.lock().expect("Rate limits can't be accessed");
+        let mut quotas: Vec<TokenQuota> = limits
+            .iter()
+            .map(|(token, rate_limit)| TokenQuota {
+                token: mask(token),
+                limit: rate_limit.limit,
+                remaining: rate_limit.remaining,
+                reset: rate_limit.reset,
+                blocked_until: rate_limit.blocked_until,
+            })
+            .collect();
+        quotas.sort_by_key(|quota| quota.remaining);
+
+        quotas
+    }
+}
+
+/// Whether a response is GitHub refusing the request because of a primary or
+/// secondary rate limit
+pub fn is_rate_limited(status: reqwest::StatusCode, headers: &HeaderMap) -> bool {
+    match status {
+        reqwest::StatusCode::TOO_MANY_REQUESTS => true,
+        reqwest::StatusCode::FORBIDDEN => {
+            headers.contains_key(RETRY_AFTER) || header_value(headers, REMAINING_HEADER) == Some(0)
+        }
+        _ => false,
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// None when the header's out of range, which is treated as no reset known
+fn timestamp(secs: i64) -> Option<DateTime<Utc>> {
+    NaiveDateTime::from_timestamp_opt(secs, 0).map(|time| DateTime::from_utc(time, Utc))
+}
+
+fn mask(token: &str) -> String {
+    let visible: String = token
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<char>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("****{}", visible)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn quota_headers(remaining: u32, reset: DateTime<Utc>) -> HeaderMap {
+        headers(&[
+            (LIMIT_HEADER, "5000".to_string()),
+            (REMAINING_HEADER, remaining.to_string()),
+            (RESET_HEADER, reset.timestamp().to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_wait_time() {
+        let limiter = RateLimiter::default();
+        let reset = Utc::now() + Duration::minutes(10);
+
+        assert_eq!(limiter.wait_time("token"), None);
+
+        limiter.update("token", &quota_headers(10, reset));
+        assert_eq!(limiter.wait_time("token"), None);
+
+        limiter.update("token", &quota_headers(0, reset));
+        let wait = limiter
+            .wait_time("token")
+            .expect("Token should be exhausted");
+        assert!(wait.as_secs() > 9 * 60 && wait.as_secs() <= 10 * 60);
+        assert_eq!(limiter.wait_time("other"), None);
+
+        // A reset that's passed doesn't hold anything up
+        limiter.update(
+            "token",
+            &quota_headers(0, Utc::now() - Duration::minutes(1)),
+        );
+        assert_eq!(limiter.wait_time("token"), None);
+    }
+
+    #[test]
+    fn test_update_ignores_partial_headers() {
+        let limiter = RateLimiter::default();
+        limiter.update("token", &headers(&[(REMAINING_HEADER, "0".to_string())]));
+        assert!(limiter.quotas().is_empty());
+
+        let out_of_range = headers(&[
+            (LIMIT_HEADER, "5000".to_string()),
+            (REMAINING_HEADER, "0".to_string()),
+            (RESET_HEADER, i64::MAX.to_string()),
+        ]);
+        limiter.update("token", &out_of_range);
+        assert!(limiter.quotas().is_empty());
+    }
+
+    #[test]
+    fn test_block() {
+        let limiter = RateLimiter::default();
+        let now = Utc::now();
+
+        let until = limiter.block("token", &headers(&[("retry-after", "30".to_string())]));
+        assert!(until >= now + Duration::seconds(30));
+        assert!(limiter.wait_time("token").is_some());
+
+        // The block outlasts a quota update that has requests left
+        limiter.update("token", &quota_headers(100, now + Duration::minutes(10)));
+        assert!(limiter.wait_time("token").is_some());
+
+        let until = limiter.block("other", &HeaderMap::new());
+        assert!(until >= now + Duration::seconds(DEFAULT_BACKOFF_SECS));
+
+        let reset = now + Duration::minutes(5);
+        let until = limiter.block("exhausted", &quota_headers(0, reset));
+        assert_eq!(until.timestamp(), reset.timestamp());
+
+        let out_of_range = headers(&[
+            (REMAINING_HEADER, "0".to_string()),
+            (RESET_HEADER, i64::MAX.to_string()),
+        ]);
+        let until = limiter.block("unknown reset", &out_of_range);
+        assert!(until >= now + Duration::seconds(DEFAULT_BACKOFF_SECS));
+    }
+
+    #[test]
+    fn test_quotas() {
+        let limiter = RateLimiter::default();
+        let reset = Utc::now() + Duration::minutes(10);
+        limiter.update("token-aaaa", &quota_headers(4000, reset));
+        limiter.update("token-bbbb", &quota_headers(12, reset));
+
+        let quotas = limiter.quotas();
+        assert_eq!(quotas[0].token, "****bbbb");
+        assert_eq!(quotas[0].remaining, 12);
+        assert_eq!(quotas[1].token, "****aaaa");
+        assert_eq!(quotas[1].limit, 5000);
+    }
+
+    #[test]
+    fn test_is_rate_limited() {
+        let reset = Utc::now();
+
+        assert!(is_rate_limited(
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+            &HeaderMap::new()
+        ));
+        assert!(is_rate_limited(
+            reqwest::StatusCode::FORBIDDEN,
+            &quota_headers(0, reset)
+        ));
+        assert!(is_rate_limited(
+            reqwest::StatusCode::FORBIDDEN,
+            &headers(&[("retry-after", "60".to_string())])
+        ));
+        assert!(!is_rate_limited(
+            reqwest::StatusCode::FORBIDDEN,
+            &quota_headers(10, reset)
+        ));
+        assert!(!is_rate_limited(
+            reqwest::StatusCode::OK,
+            &quota_headers(0, reset)
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Cached entries are dropped wholesale once the cache grows past this size
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub link: Option<String>,
+    pub body: String,
+}
+
+/// Responses keyed by token and url, so they can be revalidated with `If-None-Match`
+#[derive(Clone, Default)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<HashMap<(String, String), CachedResponse>>>,
+}
+
+impl ResponseCache {
+    pub fn get(&self, token: &str, url: &str) -> Option<CachedResponse> {
+        let entries = self
+            .entries
+            .lock()
+            .expect("Response cache can't be accessed");
+
+        entries.get(&(token.to_owned(), url.to_owned())).cloned()
+    }
+
+    pub fn insert(&self, token: &str, url: &str, response: &CachedResponse) {
+        if response.etag.is_none() {
+            return;
+        }
+
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("Response cache can't be accessed");
+        if entries.len() >= MAX_ENTRIES {
+            entries.clear();
+        }
+        entries.insert((token.to_owned(), url.to_owned()), response.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn response(etag: Option<&str>, body: &str) -> CachedResponse {
+        CachedResponse {
+            etag: etag.map(|etag| etag.to_string()),
+            link: None,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_keyed_by_token_and_url() {
+        let cache = ResponseCache::default();
+        cache.insert("token", "/repos", &response(Some("\"abc\""), "[]"));
+
+        let cached = cache
+            .get("token", "/repos")
+            .expect("Response should be cached");
+        assert_eq!(cached.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(cached.body, "[]");
+        assert!(cache.get("other", "/repos").is_none());
+        assert!(cache.get("token", "/user").is_none());
+    }
+
+    #[test]
+    fn test_skips_responses_without_etag() {
+        let cache = ResponseCache::default();
+        cache.insert("token", "/repos", &response(None, "[]"));
+
+        assert!(cache.get("token", "/repos").is_none());
+    }
+
+    #[test]
+    fn test_clears_when_full() {
+        let cache = ResponseCache::default();
+        for i in 0..MAX_ENTRIES {
+            cache.insert("token", &format!("/repos/{}", i), &response(Some("e"), ""));
+        }
+        assert!(cache.get("token", "/repos/0").is_some());
+
+        cache.insert("token", "/repos/new", &response(Some("e"), ""));
+        assert!(cache.get("token", "/repos/0").is_none());
+        assert!(cache.get("token", "/repos/new").is_some());
+    }
+}
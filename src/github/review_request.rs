@@ -3,37 +3,141 @@ use url::{self, Url};
 
 use crate::error::UrlParseError;
 
+pub const GITHUB_HOST: &str = "github.com";
+
 #[derive(Debug)]
 pub struct ReviewRequest {
+    pub host: String,
     pub owner: String,
     pub name: String,
     pub id: String,
 }
 
-impl FromStr for ReviewRequest {
-    type Err = UrlParseError;
+impl ReviewRequest {
+    pub fn new(owner: &str, name: &str, id: &str) -> Self {
+        Self {
+            host: GITHUB_HOST.to_owned(),
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+            id: id.to_owned(),
+        }
+    }
 
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    /// Parses a pull request url, an `owner/repo#123` shorthand, or a bare `#123`
+    /// resolved against `default_repo`
+    pub fn parse(s: &str, default_repo: Option<&str>) -> Result<Self, UrlParseError> {
+        let s = strip_slack_link(s);
+
+        if s.starts_with('#') {
+            let repo = default_repo.ok_or(UrlParseError::NoDefaultRepo)?;
+            return Self::parse_shorthand(&format!("{}{}", repo, s));
+        }
+
+        if s.contains("://") {
+            Self::parse_url(s)
+        } else {
+            Self::parse_shorthand(s)
+        }
+    }
+
+    /// Parses every pull request in a whitespace or comma separated list, keeping
+    /// the original text alongside each result for error messages
+    pub fn parse_all(
+        text: &str,
+        default_repo: Option<&str>,
+    ) -> Vec<(String, Result<Self, UrlParseError>)> {
+        text.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(|token| (token.to_owned(), Self::parse(token, default_repo)))
+            .collect()
+    }
+
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.owner, self.name)
+    }
+
+    /// The key the pull request is tracked under
+    pub fn github_id(&self) -> String {
+        super::github_id(&self.full_name(), self.id.parse().unwrap_or_default())
+    }
+
+    /// The same pull request on another host
+    pub fn on_host(mut self, host: &str) -> Self {
+        self.host = host.to_owned();
+        self
+    }
+
+    fn parse_url(s: &str) -> Result<Self, UrlParseError> {
         let repository_url = Url::parse(s).map_err(UrlParseError::Parse)?;
+        let host = repository_url
+            .host_str()
+            .ok_or(UrlParseError::MissingSegment)?
+            .to_lowercase();
         let mut path = repository_url
             .path_segments()
-            .ok_or(UrlParseError::MissingSegment)?;
+            .ok_or(UrlParseError::MissingSegment)?
+            .filter(|segment| !segment.is_empty());
+
+        let owner = path.next().ok_or(UrlParseError::MissingSegment)?;
+        let name = path.next().ok_or(UrlParseError::MissingSegment)?;
+        match path.next() {
+            Some("pull") | Some("pulls") => (),
+            Some(_) => return Err(UrlParseError::NotPullRequest),
+            None => return Err(UrlParseError::MissingSegment),
+        }
+        let id = path.next().ok_or(UrlParseError::MissingSegment)?;
 
-        Ok(ReviewRequest {
-            owner: path
-                .next()
-                .ok_or(UrlParseError::MissingSegment)?
-                .to_string(),
-            name: path
-                .next()
-                .ok_or(UrlParseError::MissingSegment)?
-                .to_string(),
-            id: path
-                .nth(1)
-                .ok_or(UrlParseError::MissingSegment)?
-                .to_string(),
+        Ok(Self {
+            host: host.trim_start_matches("www.").to_owned(),
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+            id: parse_number(id)?,
         })
     }
+
+    fn parse_shorthand(s: &str) -> Result<Self, UrlParseError> {
+        let mut parts = s.splitn(2, '#');
+        let repo = parts.next().ok_or(UrlParseError::MissingSegment)?;
+        let id = parts.next().ok_or(UrlParseError::MissingSegment)?;
+
+        let mut repo_parts = repo.splitn(2, '/');
+        let owner = repo_parts
+            .next()
+            .filter(|owner| !owner.is_empty())
+            .ok_or(UrlParseError::MissingSegment)?;
+        let name = repo_parts
+            .next()
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .ok_or(UrlParseError::MissingSegment)?;
+
+        Ok(Self::new(owner, name, &parse_number(id)?))
+    }
+}
+
+impl FromStr for ReviewRequest {
+    type Err = UrlParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::parse(s, None)
+    }
+}
+
+/// Slack sends links as `<url>` or `<url|label>` when link escaping is enabled
+fn strip_slack_link(s: &str) -> &str {
+    let s = s.trim();
+
+    if s.starts_with('<') && s.ends_with('>') {
+        let inner = &s[1..s.len() - 1];
+        inner.split('|').next().unwrap_or(inner)
+    } else {
+        s
+    }
+}
+
+fn parse_number(s: &str) -> Result<String, UrlParseError> {
+    s.parse::<u32>()
+        .map(|number| number.to_string())
+        .map_err(|_| UrlParseError::InvalidNumber)
 }
 
 #[cfg(test)]
@@ -55,4 +159,66 @@ mod test {
         let repo = "totally invalid url".parse::<ReviewRequest>();
         assert!(repo.is_err(), "Should not parse")
     }
+
+    #[test]
+    fn test_parse_url_with_files_and_fragment() {
+        let repo: ReviewRequest = "https://github.com/Facebook/React/pull/1234/files#diff-abc"
+            .parse()
+            .expect("Can't parse url");
+        assert_eq!(repo.id, "1234");
+        assert_eq!(repo.owner, "Facebook");
+        assert_eq!(repo.name, "React");
+        assert_eq!(repo.host, GITHUB_HOST);
+    }
+
+    #[test]
+    fn test_parse_slack_escaped_url() {
+        let repo: ReviewRequest = "<https://github.com/facebook/react/pull/12|react#12>"
+            .parse()
+            .expect("Can't parse url");
+        assert_eq!(repo.id, "12");
+    }
+
+    #[test]
+    fn test_parse_enterprise_url() {
+        let repo: ReviewRequest = "https://git.example.com/team/app/pull/7"
+            .parse()
+            .expect("Can't parse url");
+        assert_eq!(repo.host, "git.example.com");
+        assert_eq!(repo.full_name(), "team/app");
+    }
+
+    #[test]
+    fn test_parse_non_pull_request_url() {
+        let repo = "https://github.com/facebook/react/issues/12".parse::<ReviewRequest>();
+        assert!(repo.is_err(), "Should not parse issues");
+    }
+
+    #[test]
+    fn test_parse_shorthand() {
+        let repo: ReviewRequest = "facebook/react#42".parse().expect("Can't parse shorthand");
+        assert_eq!(repo.full_name(), "facebook/react");
+        assert_eq!(repo.id, "42");
+    }
+
+    #[test]
+    fn test_parse_bare_number_with_default_repo() {
+        let repo = ReviewRequest::parse("#42", Some("facebook/react")).expect("Can't parse");
+        assert_eq!(repo.full_name(), "facebook/react");
+        assert_eq!(repo.id, "42");
+
+        assert!(ReviewRequest::parse("#42", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let results = ReviewRequest::parse_all(
+            "facebook/react#1, https://github.com/rust-lang/rust/pull/2 nope",
+            None,
+        );
+        assert_eq!(results.len(), 3);
+        assert!(results[0].1.is_ok());
+        assert!(results[1].1.is_ok());
+        assert!(results[2].1.is_err());
+    }
 }
@@ -20,6 +20,8 @@ pub use crate::models::Config;
 pub use crate::utils::{
     app_config::{AppConfig, AppData},
     db,
+    outbound::RetryPolicy,
+    scheduler::start as start_scheduler,
 };
 
 use actix_files as fs;
@@ -31,6 +33,8 @@ use listenfd::ListenFd;
 const LOG_FORMAT: &str =
     "%a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T \"%{X-GitHub-Event}i\"";
 
+const ICS_FORM_LIMIT: usize = 1024 * 1024;
+
 pub fn configure_app(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/")
@@ -38,6 +42,45 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
             .route(web::get().to(routes::web::root)),
     )
     .route("/logout", web::get().to(routes::web::logout))
+    .service(
+        web::resource("/preferences")
+            .route(web::get().to(routes::web::preferences))
+            .route(web::post().to(routes::web::update_preferences)),
+    )
+    .service(
+        web::scope("/admin")
+            .route("/rate_limits", web::get().to(routes::admin::rate_limits))
+            .route("/github_users", web::get().to(routes::admin::github_users))
+            .route(
+                "/github_users/match",
+                web::post().to(routes::admin::match_github_users),
+            )
+            .route(
+                "/github_users/{id}",
+                web::post().to(routes::admin::link_github_user),
+            )
+            .service(
+                web::resource("/calendars")
+                    .route(web::get().to(routes::admin::calendars))
+                    .route(web::post().to(routes::admin::create_calendar)),
+            )
+            .service(
+                web::resource("/calendars/{id}/holidays")
+                    // Holiday calendars are bigger than the default form limit
+                    .app_data(web::FormConfig::default().limit(ICS_FORM_LIMIT))
+                    .route(web::post().to(routes::admin::import_holidays)),
+            )
+            .service(
+                web::resource("/ignore_rules")
+                    .route(web::get().to(routes::admin::ignore_rules))
+                    .route(web::post().to(routes::admin::create_ignore_rule)),
+            )
+            .route(
+                "/ignore_rules/{id}",
+                web::post().to(routes::admin::delete_ignore_rule),
+            )
+            .route("/events", web::get().to(routes::admin::events)),
+    )
     .service(
         web::scope("/github")
             .route("/repos", web::get().to(routes::github::repos))
@@ -67,17 +110,44 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
                     .guard(guard::Header("X-GitHub-Event", "pull_request_review"))
                     .to(routes::github_webhook::review),
             )
+            .route(
+                web::post()
+                    .guard(guard::Header(
+                        "X-GitHub-Event",
+                        "pull_request_review_comment",
+                    ))
+                    .to(routes::github_webhook::review_comment),
+            )
+            .route(
+                web::post()
+                    .guard(guard::Header("X-GitHub-Event", "issue_comment"))
+                    .to(routes::github_webhook::issue_comment),
+            )
             .route(
                 web::post()
                     .guard(guard::Header("X-GitHub-Event", "ping"))
                     .to(routes::github_webhook::ping),
             ),
     )
-    .route("/review", web::post().to(routes::slack_webhook::review))
-    .route("/reviews", web::post().to(routes::slack_webhook::reviews))
-    .route(
-        "/slack_event",
-        web::post().to(routes::slack_webhook::message),
+    .service(
+        web::resource("/review")
+            .wrap(middlewares::VerifySlackSignature)
+            .route(web::post().to(routes::slack_webhook::command)),
+    )
+    .service(
+        web::resource("/reviews")
+            .wrap(middlewares::VerifySlackSignature)
+            .route(web::post().to(routes::slack_webhook::reviews)),
+    )
+    .service(
+        web::resource("/slack_event")
+            .wrap(middlewares::VerifySlackSignature)
+            .route(web::post().to(routes::slack_webhook::message)),
+    )
+    .service(
+        web::resource("/slack_interaction")
+            .wrap(middlewares::VerifySlackSignature)
+            .route(web::post().to(routes::slack_interaction::interaction)),
     )
     .service(
         web::resource("/setup")
@@ -1,7 +1,9 @@
 use rand::Rng;
 use std::collections::HashMap;
 
-use code_review_bot::{db, start_dev_server, start_server, AppConfig, AppData, Config};
+use code_review_bot::{
+    db, start_dev_server, start_scheduler, start_server, AppConfig, AppData, Config, RetryPolicy,
+};
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use dotenv::dotenv;
@@ -57,7 +59,18 @@ async fn main() -> std::io::Result<()> {
         .map(|config| (config.key.as_ref(), config.value.as_ref()))
         .collect();
 
-    let mut builder = AppData::new();
+    let mut http_policy = RetryPolicy::default();
+    if let Some(timeout) = configs
+        .get("http_timeout_secs")
+        .and_then(|t| t.parse().ok())
+    {
+        http_policy = http_policy.timeout_secs(timeout);
+    }
+    if let Some(retries) = configs.get("http_max_retries").and_then(|r| r.parse().ok()) {
+        http_policy = http_policy.max_retries(retries);
+    }
+
+    let mut builder = AppData::new().http_policy(http_policy);
 
     if configs.contains_key("github_client_id") && configs.contains_key("github_client_secret") {
         builder = builder.github(
@@ -66,14 +79,24 @@ async fn main() -> std::io::Result<()> {
         );
     }
 
+    // Pull requests from GitHub Enterprise are only fetched from hosts given a
+    // token as `github_enterprise_token:<host>`
+    for (key, token) in configs.iter() {
+        if let Some(host) = key.strip_prefix("github_enterprise_token:") {
+            builder = builder.github_enterprise(host, token);
+        }
+    }
+
     if configs.contains_key("slack_client_id")
         && configs.contains_key("slack_client_secret")
+        && configs.contains_key("slack_signing_secret")
         && configs.contains_key("slack_channel")
         && configs.contains_key("slack_token")
     {
         builder = builder.slack(
             configs.get("slack_client_id").unwrap(),
             configs.get("slack_client_secret").unwrap(),
+            configs.get("slack_signing_secret").unwrap(),
             configs.get("slack_channel").unwrap(),
             configs.get("slack_token").unwrap(),
         );
@@ -85,6 +108,7 @@ async fn main() -> std::io::Result<()> {
 
     // Create AppConfig
     let app_config = AppConfig::new(builder.clone(), builder.build());
+    start_scheduler(app_config.clone(), db.clone());
 
     if opt.dev {
         start_dev_server(opt.port, app_config, app_secret, db).await
@@ -1,11 +1,23 @@
 use actix_service::{Service, Transform};
-use actix_web::{dev::ServiceRequest, dev::ServiceResponse, http, Error, HttpResponse};
+use actix_web::error::PayloadError;
+use actix_web::web::{Bytes, BytesMut, Data};
+use actix_web::{
+    dev::Payload, dev::ServiceRequest, dev::ServiceResponse, http, Error, HttpMessage, HttpResponse,
+};
 use futures::future::{ok, Either, Future, Ready};
+use futures::stream::{self, StreamExt};
+use std::cell::RefCell;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 
+use crate::slack::signature::{self, SIGNATURE_HEADER, TIMESTAMP_HEADER};
 use crate::utils::app_config::AppConfig;
 
+/// Slack's payloads are far smaller, anything bigger is refused before it's
+/// verified so unsigned requests can't make the server hold large bodies
+const MAX_SLACK_BODY_BYTES: usize = 1024 * 1024;
+
 pub struct SetupRedirect;
 
 type PinBox<T> = Pin<Box<T>>;
@@ -76,3 +88,187 @@ where
         Box::pin(fut)
     }
 }
+
+/// Rejects requests to the Slack endpoints that weren't signed with the app's
+/// signing secret, before anything in them is trusted
+pub struct VerifySlackSignature;
+
+impl<S, B> Transform<S> for VerifySlackSignature
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = VerifySlackSignatureMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(VerifySlackSignatureMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct VerifySlackSignatureMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for VerifySlackSignatureMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = PinBox<dyn Future<Output = Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let signing_secret = req.app_data::<Data<AppConfig>>().and_then(|config| {
+            let app_data = config.data.lock().expect("Data can't be accessed");
+            app_data
+                .as_ref()
+                .map(|app_data| app_data.slack.signing_secret.clone())
+        });
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let timestamp = header(TIMESTAMP_HEADER);
+        let signature = header(SIGNATURE_HEADER);
+        let mut payload = req.take_payload();
+
+        Box::pin(async move {
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                if body.len() + chunk.len() > MAX_SLACK_BODY_BYTES {
+                    return Ok(
+                        req.into_response(HttpResponse::PayloadTooLarge().finish().into_body())
+                    );
+                }
+                body.extend_from_slice(&chunk);
+            }
+            let body = body.freeze();
+
+            let now = chrono::Utc::now().timestamp();
+            let verified = signing_secret
+                .filter(|secret| !secret.is_empty())
+                .map(|secret| signature::verify(&secret, &timestamp, &body, &signature, now))
+                .unwrap_or(false);
+            if !verified {
+                log::warn!(
+                    "Rejected a request to {} without a valid Slack signature",
+                    req.path()
+                );
+                return Ok(req.into_response(HttpResponse::Unauthorized().finish().into_body()));
+            }
+
+            // Hand the body on to the route's extractors, which haven't read it yet
+            let replay = stream::once(async move { Ok::<Bytes, PayloadError>(body) });
+            req.set_payload(Payload::Stream(Box::pin(replay)));
+            let res = service.borrow_mut().call(req);
+            res.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::{test, web, App};
+    use hmac::{Hmac, Mac, NewMac};
+    use sha2::Sha256;
+
+    use crate::utils::app_config::AppData;
+
+    const SIGNING_SECRET: &str = "signing-secret";
+
+    fn app_config() -> Data<AppConfig> {
+        let builder = AppData::new()
+            .slack("client", "secret", SIGNING_SECRET, "C1", "xoxb-token")
+            .github("client", "secret")
+            .app_url("https://crbot.example.com");
+        Data::new(AppConfig::new(builder.clone(), builder.build()))
+    }
+
+    fn sign(timestamp: i64, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(SIGNING_SECRET.as_bytes()).unwrap();
+        mac.update(format!("v0:{}:{}", timestamp, body).as_bytes());
+        let signature: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("v0={}", signature)
+    }
+
+    async fn echo(body: Bytes) -> HttpResponse {
+        HttpResponse::Ok().body(body)
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_slack_signature() {
+        let mut app = test::init_service(
+            App::new().app_data(app_config()).service(
+                web::resource("/slack_event")
+                    .wrap(VerifySlackSignature)
+                    .route(web::post().to(echo)),
+            ),
+        )
+        .await;
+        let body = "payload=%7B%7D";
+        let now = chrono::Utc::now().timestamp();
+
+        let req = test::TestRequest::post()
+            .uri("/slack_event")
+            .header(TIMESTAMP_HEADER, now.to_string())
+            .header(SIGNATURE_HEADER, sign(now, body))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(test::read_body(res).await, Bytes::from(body));
+
+        let req = test::TestRequest::post()
+            .uri("/slack_event")
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+        let old = now - 10 * 60;
+        let req = test::TestRequest::post()
+            .uri("/slack_event")
+            .header(TIMESTAMP_HEADER, old.to_string())
+            .header(SIGNATURE_HEADER, sign(old, body))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+        let large = "a".repeat(MAX_SLACK_BODY_BYTES + 1);
+        let req = test::TestRequest::post()
+            .uri("/slack_event")
+            .header(TIMESTAMP_HEADER, now.to_string())
+            .header(SIGNATURE_HEADER, sign(now, &large))
+            .set_payload(large)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,11 +1,18 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text, Timestamp};
+use std::collections::HashMap;
 
 use crate::db::DBExecutor;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::github;
 use crate::schema::*;
 
+// GitHub owner and repository names are case insensitive, so names typed in
+// Slack are compared lowercased
+sql_function!(fn lower(x: Text) -> Text);
+
 #[derive(Clone, Debug, Queryable, Insertable)]
 #[table_name = "configs"]
 pub struct Config {
@@ -32,202 +39,1707 @@ impl Config {
         use crate::schema::configs::dsl::*;
         let conn = db.0.get()?;
 
-        diesel::insert_into(configs)
-            .values(new_configs)
-            .get_results(&conn)
+        diesel::insert_into(configs)
+            .values(new_configs)
+            .get_results(&conn)
+            .map_err(|e| e.into())
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[table_name = "channel_settings"]
+#[primary_key(channel_id)]
+pub struct ChannelSetting {
+    pub channel_id: String,
+    pub default_repo: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub thread_activity: bool,
+}
+
+impl ChannelSetting {
+    pub fn set_default_repo(
+        channel: &str,
+        repo: Option<&str>,
+        db: &DBExecutor,
+    ) -> Result<ChannelSetting> {
+        use crate::schema::channel_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(channel_settings)
+            .values((channel_id.eq(channel), default_repo.eq(repo)))
+            .on_conflict(channel_id)
+            .do_update()
+            .set(default_repo.eq(repo))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_thread_activity(
+        channel: &str,
+        enabled: bool,
+        db: &DBExecutor,
+    ) -> Result<ChannelSetting> {
+        use crate::schema::channel_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(channel_settings)
+            .values((channel_id.eq(channel), thread_activity.eq(enabled)))
+            .on_conflict(channel_id)
+            .do_update()
+            .set(thread_activity.eq(enabled))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn find(channel: &str, db: &DBExecutor) -> Result<Option<ChannelSetting>> {
+        use crate::schema::channel_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        channel_settings
+            .find(channel)
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn default_repo(channel: &str, db: &DBExecutor) -> Result<Option<String>> {
+        Ok(Self::find(channel, db)?.and_then(|setting| setting.default_repo))
+    }
+
+    /// Whether pull request activity is posted in message threads, which it
+    /// is until a channel turns it off
+    pub fn thread_activity(channel: &str, db: &DBExecutor) -> Result<bool> {
+        Ok(Self::find(channel, db)?
+            .map(|setting| setting.thread_activity)
+            .unwrap_or(true))
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "calendars"]
+pub struct NewCalendar {
+    pub name: String,
+    pub time_zone: String,
+    pub working_days: Vec<i32>,
+    pub start_hour: i32,
+    pub end_hour: i32,
+}
+
+/// A team's working week, which reminders and review times are counted in
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct Calendar {
+    pub id: i32,
+    pub name: String,
+    /// An IANA time zone name like `Europe/London`
+    pub time_zone: String,
+    /// ISO weekday numbers, Monday is 1
+    pub working_days: Vec<i32>,
+    pub start_hour: i32,
+    pub end_hour: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Calendar {
+    pub fn create(new_calendar: &NewCalendar, db: &DBExecutor) -> Result<Calendar> {
+        use crate::schema::calendars::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(calendars)
+            .values(new_calendar)
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn find(calendar_id: i32, db: &DBExecutor) -> Result<Option<Calendar>> {
+        use crate::schema::calendars::dsl::*;
+        let conn = db.0.get()?;
+
+        calendars
+            .find(calendar_id)
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn find_by_name(calendar_name: &str, db: &DBExecutor) -> Result<Option<Calendar>> {
+        use crate::schema::calendars::dsl::*;
+        let conn = db.0.get()?;
+
+        calendars
+            .filter(name.eq(calendar_name))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn all(db: &DBExecutor) -> Result<Vec<Calendar>> {
+        use crate::schema::calendars::dsl::*;
+        let conn = db.0.get()?;
+
+        calendars
+            .order(name.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(Calendar)]
+pub struct Holiday {
+    pub id: i32,
+    pub calendar_id: i32,
+    pub date: NaiveDate,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Holidays inserted per query when importing a calendar
+const HOLIDAY_IMPORT_CHUNK: usize = 1000;
+
+impl Holiday {
+    pub fn for_calendar(calendar: i32, db: &DBExecutor) -> Result<Vec<Holiday>> {
+        use crate::schema::holidays::dsl::*;
+        let conn = db.0.get()?;
+
+        holidays
+            .filter(calendar_id.eq(calendar))
+            .order(date.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Adds the dates to the calendar, skipping any it already has. Returns how
+    /// many were added.
+    pub fn import(calendar: i32, dates: &[(NaiveDate, String)], db: &DBExecutor) -> Result<usize> {
+        use crate::schema::holidays::dsl::*;
+        let conn = db.0.get()?;
+
+        // Each row takes three of the 65535 parameters Postgres allows a query
+        conn.transaction(|| {
+            let mut added = 0;
+            for chunk in dates.chunks(HOLIDAY_IMPORT_CHUNK) {
+                let rows: Vec<_> = chunk
+                    .iter()
+                    .map(|(holiday_date, holiday_name)| {
+                        (
+                            calendar_id.eq(calendar),
+                            date.eq(*holiday_date),
+                            name.eq(holiday_name),
+                        )
+                    })
+                    .collect();
+
+                added += diesel::insert_into(holidays)
+                    .values(rows)
+                    .on_conflict((calendar_id, date))
+                    .do_nothing()
+                    .execute(&conn)?;
+            }
+            Ok(added)
+        })
+    }
+}
+
+/// One message listing a repository's dependency update pull requests. It's
+/// open until every pull request in it is closed, then the next update starts
+/// a new one.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct DependencyDigest {
+    pub id: i32,
+    pub repo: String,
+    pub channel: String,
+    pub slack_message_id: String,
+    pub open: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl DependencyDigest {
+    pub fn create(
+        full_name: &str,
+        digest_channel: &str,
+        ts: &str,
+        db: &DBExecutor,
+    ) -> Result<DependencyDigest> {
+        use crate::schema::dependency_digests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(dependency_digests)
+            .values((
+                repo.eq(full_name),
+                channel.eq(digest_channel),
+                slack_message_id.eq(ts),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn find(digest_id: i32, db: &DBExecutor) -> Result<Option<DependencyDigest>> {
+        use crate::schema::dependency_digests::dsl::*;
+        let conn = db.0.get()?;
+
+        dependency_digests
+            .find(digest_id)
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn open_for_repo(full_name: &str, db: &DBExecutor) -> Result<Option<DependencyDigest>> {
+        use crate::schema::dependency_digests::dsl::*;
+        let conn = db.0.get()?;
+
+        dependency_digests
+            .filter(repo.eq(full_name))
+            .filter(open.eq(true))
+            .order(created_at.desc())
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn close(&self, db: &DBExecutor) -> Result<DependencyDigest> {
+        use crate::schema::dependency_digests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(dependency_digests.find(self.id))
+            .set(open.eq(false))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "ignore_rules"]
+pub struct NewIgnoreRule {
+    pub kind: String,
+    pub pattern: String,
+    pub repo: Option<String>,
+}
+
+/// Pull requests matching a rule aren't posted. Rules without a repository
+/// apply to every one.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct IgnoreRule {
+    pub id: i32,
+    pub kind: String,
+    pub pattern: String,
+    pub repo: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl IgnoreRule {
+    pub fn create(new_rule: &NewIgnoreRule, db: &DBExecutor) -> Result<IgnoreRule> {
+        use crate::schema::ignore_rules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(ignore_rules)
+            .values(new_rule)
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn all(db: &DBExecutor) -> Result<Vec<IgnoreRule>> {
+        use crate::schema::ignore_rules::dsl::*;
+        let conn = db.0.get()?;
+
+        ignore_rules
+            .order((repo.asc(), kind.asc()))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// The rules for the repository and those for every repository
+    pub fn for_repo(full_name: &str, db: &DBExecutor) -> Result<Vec<IgnoreRule>> {
+        use crate::schema::ignore_rules::dsl::*;
+        let conn = db.0.get()?;
+
+        ignore_rules
+            .filter(repo.is_null().or(repo.eq(full_name)))
+            .order(id.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn delete(rule_id: i32, db: &DBExecutor) -> Result<()> {
+        use crate::schema::ignore_rules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::delete(ignore_rules.find(rule_id))
+            .execute(&conn)
+            .map(|_| ())
+            .map_err(|e| e.into())
+    }
+}
+
+/// Something the bot did, or chose not to do, for admins to look back on
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct EventLog {
+    pub id: i32,
+    pub event: String,
+    pub pull_request_url: Option<String>,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl EventLog {
+    pub fn record(
+        event_name: &str,
+        url: Option<&str>,
+        text: &str,
+        db: &DBExecutor,
+    ) -> Result<EventLog> {
+        use crate::schema::event_logs::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(event_logs)
+            .values((
+                event.eq(event_name),
+                pull_request_url.eq(url),
+                message.eq(text),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// The latest `limit` events, newest first
+    pub fn recent(limit: i64, db: &DBExecutor) -> Result<Vec<EventLog>> {
+        use crate::schema::event_logs::dsl::*;
+        let conn = db.0.get()?;
+
+        event_logs
+            .order(created_at.desc())
+            .limit(limit)
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+}
+
+/// When the scheduler posts a channel's digest and nudges its pull requests,
+/// in the channel's time zone
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[table_name = "channel_schedules"]
+#[primary_key(channel_id)]
+pub struct ChannelSchedule {
+    pub channel_id: String,
+    pub digest_hour: Option<i32>,
+    pub nudge_after_hours: Option<i32>,
+    /// An IANA time zone name like `Europe/London`
+    pub time_zone: String,
+    pub last_digest_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub calendar_id: Option<i32>,
+}
+
+impl ChannelSchedule {
+    pub fn find(channel: &str, db: &DBExecutor) -> Result<Option<ChannelSchedule>> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        channel_schedules
+            .find(channel)
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn all(db: &DBExecutor) -> Result<Vec<ChannelSchedule>> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        channel_schedules.load(&conn).map_err(|e| e.into())
+    }
+
+    pub fn set_digest_hour(
+        channel: &str,
+        hour: Option<i32>,
+        db: &DBExecutor,
+    ) -> Result<ChannelSchedule> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(channel_schedules)
+            .values((channel_id.eq(channel), digest_hour.eq(hour)))
+            .on_conflict(channel_id)
+            .do_update()
+            .set(digest_hour.eq(hour))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_nudge_after_hours(
+        channel: &str,
+        hours: Option<i32>,
+        db: &DBExecutor,
+    ) -> Result<ChannelSchedule> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(channel_schedules)
+            .values((channel_id.eq(channel), nudge_after_hours.eq(hours)))
+            .on_conflict(channel_id)
+            .do_update()
+            .set(nudge_after_hours.eq(hours))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_time_zone(channel: &str, zone: Tz, db: &DBExecutor) -> Result<ChannelSchedule> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(channel_schedules)
+            .values((channel_id.eq(channel), time_zone.eq(zone.name())))
+            .on_conflict(channel_id)
+            .do_update()
+            .set(time_zone.eq(zone.name()))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_calendar(
+        channel: &str,
+        calendar: Option<i32>,
+        db: &DBExecutor,
+    ) -> Result<ChannelSchedule> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(channel_schedules)
+            .values((channel_id.eq(channel), calendar_id.eq(calendar)))
+            .on_conflict(channel_id)
+            .do_update()
+            .set(calendar_id.eq(calendar))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn digest_sent(&self, at: NaiveDateTime, db: &DBExecutor) -> Result<ChannelSchedule> {
+        use crate::schema::channel_schedules::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(channel_schedules.find(&self.channel_id))
+            .set(last_digest_at.eq(at))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[table_name = "notification_preferences"]
+#[primary_key(user_id)]
+pub struct NotificationPreference {
+    pub user_id: i32,
+    pub review_requests: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub reviews: bool,
+    pub merges: bool,
+    pub digest: String,
+    pub quiet_hours_start: Option<i32>,
+    pub quiet_hours_end: Option<i32>,
+    pub time_zone: String,
+    pub last_digest_at: Option<NaiveDateTime>,
+}
+
+/// The direct messages a user wants, with quiet hours counted in their own
+/// time zone
+#[derive(Clone, Debug, Insertable, AsChangeset)]
+#[table_name = "notification_preferences"]
+#[primary_key(user_id)]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NotificationSettings {
+    pub user_id: i32,
+    pub review_requests: bool,
+    pub reviews: bool,
+    pub merges: bool,
+    pub digest: String,
+    pub quiet_hours_start: Option<i32>,
+    pub quiet_hours_end: Option<i32>,
+    /// An IANA time zone name like `Europe/London`
+    pub time_zone: String,
+}
+
+impl NotificationSettings {
+    /// Everything on except the digest, until the user says otherwise
+    pub fn new(u_id: i32) -> Self {
+        Self {
+            user_id: u_id,
+            review_requests: true,
+            reviews: true,
+            merges: true,
+            digest: "off".to_string(),
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            time_zone: Tz::UTC.name().to_string(),
+        }
+    }
+
+    /// The user's time zone, UTC if it's no longer one chrono-tz knows
+    pub fn tz(&self) -> Tz {
+        self.time_zone.parse().unwrap_or(Tz::UTC)
+    }
+}
+
+impl From<NotificationPreference> for NotificationSettings {
+    fn from(preference: NotificationPreference) -> Self {
+        Self {
+            user_id: preference.user_id,
+            review_requests: preference.review_requests,
+            reviews: preference.reviews,
+            merges: preference.merges,
+            digest: preference.digest,
+            quiet_hours_start: preference.quiet_hours_start,
+            quiet_hours_end: preference.quiet_hours_end,
+            time_zone: preference.time_zone,
+        }
+    }
+}
+
+impl NotificationPreference {
+    pub fn find(u_id: i32, db: &DBExecutor) -> Result<Option<NotificationPreference>> {
+        use crate::schema::notification_preferences::dsl::*;
+        let conn = db.0.get()?;
+
+        notification_preferences
+            .find(u_id)
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn save(
+        settings: &NotificationSettings,
+        db: &DBExecutor,
+    ) -> Result<NotificationPreference> {
+        use crate::schema::notification_preferences::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(notification_preferences)
+            .values(settings)
+            .on_conflict(user_id)
+            .do_update()
+            .set(settings)
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Users who've asked for a digest of their review queue
+    pub fn with_digest(db: &DBExecutor) -> Result<Vec<NotificationPreference>> {
+        use crate::schema::notification_preferences::dsl::*;
+        let conn = db.0.get()?;
+
+        notification_preferences
+            .filter(digest.ne("off"))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn digest_sent(
+        &self,
+        at: NaiveDateTime,
+        db: &DBExecutor,
+    ) -> Result<NotificationPreference> {
+        use crate::schema::notification_preferences::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(notification_preferences.find(self.user_id))
+            .set(last_digest_at.eq(at))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// The user's settings, or the defaults when they've never changed them
+    pub fn settings(u_id: i32, db: &DBExecutor) -> Result<NotificationSettings> {
+        Ok(Self::find(u_id, db)?
+            .map(NotificationSettings::from)
+            .unwrap_or_else(|| NotificationSettings::new(u_id)))
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[table_name = "repo_settings"]
+#[primary_key(repo)]
+pub struct RepoSetting {
+    pub repo: String,
+    pub merge_method: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// Working hours a pull request can wait for its first review
+    pub review_sla_hours: Option<i32>,
+    /// Who's mentioned when the SLA is breached, as Slack mention markup
+    pub escalation_mention: Option<String>,
+}
+
+impl RepoSetting {
+    pub fn find(full_name: &str, db: &DBExecutor) -> Result<Option<RepoSetting>> {
+        use crate::schema::repo_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        repo_settings
+            .find(full_name)
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_merge_method(
+        full_name: &str,
+        method: github::MergeMethod,
+        db: &DBExecutor,
+    ) -> Result<RepoSetting> {
+        use crate::schema::repo_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(repo_settings)
+            .values((repo.eq(full_name), merge_method.eq(method.to_string())))
+            .on_conflict(repo)
+            .do_update()
+            .set(merge_method.eq(method.to_string()))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_review_sla(
+        full_name: &str,
+        hours: Option<i32>,
+        mention: Option<&str>,
+        db: &DBExecutor,
+    ) -> Result<RepoSetting> {
+        use crate::schema::repo_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(repo_settings)
+            .values((
+                repo.eq(full_name),
+                review_sla_hours.eq(hours),
+                escalation_mention.eq(mention),
+            ))
+            .on_conflict(repo)
+            .do_update()
+            .set((review_sla_hours.eq(hours), escalation_mention.eq(mention)))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Repositories with a review SLA
+    pub fn with_review_sla(db: &DBExecutor) -> Result<Vec<RepoSetting>> {
+        use crate::schema::repo_settings::dsl::*;
+        let conn = db.0.get()?;
+
+        repo_settings
+            .filter(review_sla_hours.is_not_null())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// How pull requests in the repository are merged from Slack, a plain merge
+    /// commit unless it's been changed
+    pub fn merge_method(full_name: &str, db: &DBExecutor) -> Result<github::MergeMethod> {
+        Ok(Self::find(full_name, db)?
+            .and_then(|setting| setting.merge_method.parse().ok())
+            .unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "pull_requests"]
+pub struct NewPullRequest {
+    pub github_id: String,
+    pub state: String,
+    pub slack_message_id: String,
+    pub channel: String,
+    pub display_text: String,
+    pub github_user_id: i32,
+    pub title: String,
+    pub html_url: String,
+    pub additions: i32,
+    pub deletions: i32,
+    pub labels: Vec<String>,
+    pub digest_id: Option<i32>,
+    pub host: String,
+    pub repo: String,
+}
+
+impl NewPullRequest {
+    pub fn new(
+        pull_request: &github::PRResult,
+        slack_message_id: String,
+        channel: String,
+        github_user_id: i32,
+    ) -> Self {
+        Self {
+            github_id: github::github_id(&pull_request.base.repo.full_name, pull_request.number),
+            state: if pull_request.open() {
+                "open"
+            } else {
+                "closed"
+            }
+            .to_string(),
+            slack_message_id,
+            channel,
+            display_text: format!("{}", pull_request),
+            github_user_id,
+            title: pull_request.title.clone(),
+            html_url: pull_request.html_url.clone(),
+            additions: pull_request.additions as i32,
+            deletions: pull_request.deletions as i32,
+            labels: pull_request.label_names(),
+            digest_id: None,
+            host: pull_request.host(),
+            repo: pull_request.base.repo.full_name.clone(),
+        }
+    }
+}
+
+/// Narrows the review queue, an empty query matches every waiting pull request
+#[derive(Debug, Default)]
+pub struct QueueQuery {
+    pub channel: Option<String>,
+    /// Pull requests in any of these repositories, in any case
+    pub repos: Vec<String>,
+    pub authors: Option<Vec<i32>>,
+    pub labels: Vec<String>,
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[table_name = "pull_requests"]
+#[belongs_to(GithubUser)]
+pub struct PullRequest {
+    pub id: i32,
+    pub github_id: String,
+    pub state: String,
+    pub slack_message_id: String,
+    pub channel: String,
+    pub display_text: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub github_user_id: i32,
+    pub snoozed_until: Option<NaiveDateTime>,
+    pub title: String,
+    pub html_url: String,
+    pub additions: i32,
+    pub deletions: i32,
+    pub labels: Vec<String>,
+    pub muted: bool,
+    pub last_nudged_at: Option<NaiveDateTime>,
+    /// The dependency digest listing the pull request, which it shares a
+    /// message with
+    pub digest_id: Option<i32>,
+    pub merged: bool,
+    /// Where the pull request lives, github.com or a GitHub Enterprise host
+    pub host: String,
+    /// The `owner/name` of the repository
+    pub repo: String,
+}
+
+impl PullRequest {
+    pub fn create(pr: &NewPullRequest, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(pull_requests)
+            .values(pr)
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn find(gh_id: &str, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        pull_requests
+            .filter(lower(github_id).eq(gh_id.to_lowercase()))
+            .first(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Whether any pull request from the repository has been posted
+    pub fn repo_tracked(full_name: &str, db: &DBExecutor) -> Result<bool> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::select(diesel::dsl::exists(
+            pull_requests.filter(repo.eq(full_name)),
+        ))
+        .get_result(&conn)
+        .map_err(|e| e.into())
+    }
+
+    pub fn lookup(gh_id: &str, db: &DBExecutor) -> Result<Option<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        pull_requests
+            .filter(lower(github_id).eq(gh_id.to_lowercase()))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    /// The pull request a Slack message was posted for
+    pub fn find_by_message(
+        message_channel: &str,
+        message_ts: &str,
+        db: &DBExecutor,
+    ) -> Result<Option<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        pull_requests
+            .filter(channel.eq(message_channel))
+            .filter(slack_message_id.eq(message_ts))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn by_state(query_state: &str, db: &DBExecutor) -> Result<Vec<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        pull_requests
+            .filter(state.eq(query_state))
+            .load::<PullRequest>(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Pull requests tracked in the last `days` days
+    pub fn created_since(days: i64, db: &DBExecutor) -> Result<Vec<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+        let since = Utc::now().naive_utc() - Duration::days(days);
+
+        pull_requests
+            .filter(created_at.gt(since))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Open pull requests that aren't snoozed or muted, oldest first
+    pub fn waiting(query: &QueueQuery, db: &DBExecutor) -> Result<Vec<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        let mut sql = pull_requests
+            .filter(state.eq("open"))
+            .filter(muted.eq(false))
+            .filter(
+                snoozed_until
+                    .is_null()
+                    .or(snoozed_until.lt(diesel::dsl::now.nullable())),
+            )
+            .order(created_at.asc())
+            .into_boxed();
+
+        if let Some(query_channel) = &query.channel {
+            sql = sql.filter(channel.eq(query_channel.clone()));
+        }
+        if !query.repos.is_empty() {
+            let repos: Vec<String> = query.repos.iter().map(|name| name.to_lowercase()).collect();
+            sql = sql.filter(lower(repo).eq_any(repos));
+        }
+        if let Some(authors) = &query.authors {
+            sql = sql.filter(github_user_id.eq_any(authors.clone()));
+        }
+        if !query.labels.is_empty() {
+            sql = sql.filter(labels.contains(query.labels.clone()));
+        }
+
+        sql.load(&conn).map_err(|e| e.into())
+    }
+
+    /// Pull requests by any of `authors` that haven't been closed, oldest first
+    pub fn open_by_authors(authors: &[i32], db: &DBExecutor) -> Result<Vec<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        pull_requests
+            .filter(state.ne("closed"))
+            .filter(github_user_id.eq_any(authors.to_vec()))
+            .order(created_at.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn update_details(
+        &self,
+        pull_request: &github::PRResult,
+        db: &DBExecutor,
+    ) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(pull_requests.find(self.id))
+            .set((
+                display_text.eq(format!("{}", pull_request)),
+                title.eq(&pull_request.title),
+                additions.eq(pull_request.additions as i32),
+                deletions.eq(pull_request.deletions as i32),
+                labels.eq(pull_request.label_names()),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// The pull request to fetch from Github, rebuilt from the tracking key
+    pub fn review_request(&self) -> Option<github::ReviewRequest> {
+        let (_, number) = self.github_id.rsplit_once('-')?;
+
+        github::ReviewRequest::parse(&format!("{}#{}", self.repo, number), None)
+            .ok()
+            .map(|review_request| review_request.on_host(&self.host))
+    }
+
+    pub fn nudged(&self, at: NaiveDateTime, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(pull_requests.find(self.id))
+            .set(last_nudged_at.eq(at))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_muted(&self, mute: bool, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(pull_requests.find(self.id))
+            .set(muted.eq(mute))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn snooze(&self, hours: i64, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+        let until = Utc::now()
+            .naive_utc()
+            .checked_add_signed(Duration::hours(hours))
+            .ok_or(Error::GuardError("Snoozed for too long"))?;
+
+        diesel::update(pull_requests.find(self.id))
+            .set(snoozed_until.eq(until))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn set_merged(&self, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(pull_requests.find(self.id))
+            .set(merged.eq(true))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// The pull requests in a dependency digest, oldest first
+    pub fn in_digest(digest: i32, db: &DBExecutor) -> Result<Vec<PullRequest>> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        pull_requests
+            .filter(digest_id.eq(digest))
+            .order(created_at.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn update(&self, new_state: &str, db: &DBExecutor) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(pull_requests.find(self.id))
+            .set(state.eq(new_state))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn move_message(
+        &self,
+        ts: &str,
+        new_channel: &str,
+        db: &DBExecutor,
+    ) -> Result<PullRequest> {
+        use crate::schema::pull_requests::dsl::*;
+        let conn = db.0.get()?;
+
+        // A pull request posted on its own leaves its dependency digest
+        diesel::update(pull_requests.find(self.id))
+            .set((
+                slack_message_id.eq(ts),
+                channel.eq(new_channel),
+                digest_id.eq(None::<i32>),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn user(&self, db: &DBExecutor) -> Result<Option<User>> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        let gh_user = github_users
+            .filter(github_id.eq(self.github_user_id))
+            .first::<GithubUser>(&conn)?;
+
+        gh_user.user(db)
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[table_name = "github_users"]
+#[belongs_to(User)]
+pub struct GithubUser {
+    pub id: i32,
+    pub login: String,
+    pub avatar_url: String,
+    pub github_id: i32,
+    pub user_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl GithubUser {
+    pub fn find_or_create(
+        user: &github::User,
+        db: &DBExecutor,
+        u_id: Option<i32>,
+    ) -> Result<GithubUser> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+        let res = github_users
+            .filter(github_id.eq(user.id))
+            .first::<GithubUser>(&conn);
+
+        let gh_user: Option<GithubUser> = match res {
+            Ok(user) => Ok(Some(user)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        }?;
+
+        match gh_user {
+            Some(user) => Ok(user),
+            None => match u_id {
+                Some(u_id) => diesel::insert_into(github_users)
+                    .values(vec![(
+                        login.eq(&user.login),
+                        avatar_url.eq(&user.avatar_url),
+                        github_id.eq(user.id),
+                        user_id.eq(u_id),
+                    )])
+                    .get_result(&conn)
+                    .map_err(|e| e.into()),
+                None => diesel::insert_into(github_users)
+                    .values(vec![(
+                        login.eq(&user.login),
+                        avatar_url.eq(&user.avatar_url),
+                        github_id.eq(user.id),
+                    )])
+                    .get_result(&conn)
+                    .map_err(|e| e.into()),
+            },
+        }
+    }
+
+    pub fn find(find_id: i32, db: &DBExecutor) -> Result<GithubUser> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        github_users
+            .find(find_id)
+            .first(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Github accounts nobody has connected to a Slack user yet
+    pub fn unlinked(db: &DBExecutor) -> Result<Vec<GithubUser>> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        github_users
+            .filter(user_id.is_null())
+            .order(login.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn link(&self, u_id: i32, db: &DBExecutor) -> Result<GithubUser> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(github_users.find(self.id))
+            .set(user_id.eq(u_id))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Slack user ids for whichever of the Github accounts, by Github id, are
+    /// linked to one
+    pub fn slack_user_ids(ids: &[i32], db: &DBExecutor) -> Result<HashMap<i32, String>> {
+        use crate::schema::github_users::dsl::*;
+        use crate::schema::users;
+        let conn = db.0.get()?;
+
+        github_users
+            .inner_join(users::table)
+            .filter(github_id.eq_any(ids.to_vec()))
+            .select((github_id, users::slack_user_id))
+            .load::<(i32, String)>(&conn)
+            .map(|pairs| pairs.into_iter().collect())
+            .map_err(|e| e.into())
+    }
+
+    pub fn slack_user_id(gh_id: i32, db: &DBExecutor) -> Result<Option<String>> {
+        Ok(Self::slack_user_ids(&[gh_id], db)?.remove(&gh_id))
+    }
+
+    pub fn find_by_login(gh_login: &str, db: &DBExecutor) -> Result<Option<GithubUser>> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        github_users
+            .filter(login.eq(gh_login))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn by_github_ids(ids: &[i32], db: &DBExecutor) -> Result<Vec<GithubUser>> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        github_users
+            .filter(github_id.eq_any(ids.to_vec()))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn for_user(u_id: i32, db: &DBExecutor) -> Result<Vec<GithubUser>> {
+        use crate::schema::github_users::dsl::*;
+        let conn = db.0.get()?;
+
+        github_users
+            .filter(user_id.eq(u_id))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn user(&self, db: &DBExecutor) -> Result<Option<User>> {
+        use crate::schema::users::dsl::*;
+        let conn = db.0.get()?;
+
+        if let Some(u_id) = self.user_id {
+            return users
+                .find(u_id)
+                .get_result::<User>(&conn)
+                .map(Some)
+                .map_err(|e| e.into());
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(GithubUser)]
+#[belongs_to(PullRequest)]
+pub struct Review {
+    pub id: i32,
+    pub pull_request_id: i32,
+    pub github_user_id: i32,
+    pub state: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Review {
+    pub fn for_pull_requests(ids: &[i32], db: &DBExecutor) -> Result<Vec<Review>> {
+        use crate::schema::reviews::dsl::*;
+        let conn = db.0.get()?;
+
+        reviews
+            .filter(pull_request_id.eq_any(ids.to_vec()))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn create_or_update(
+        reviewer: &GithubUser,
+        pull_request: &PullRequest,
+        review_state: &str,
+        db: &DBExecutor,
+    ) -> Result<Review> {
+        use crate::schema::reviews::dsl::*;
+        let conn = db.0.get()?;
+
+        let review: Result<Review> = reviews
+            .filter(github_user_id.eq(&reviewer.id))
+            .filter(pull_request_id.eq(&pull_request.id))
+            .first(&conn)
+            .map_err(|e| e.into());
+
+        match review {
+            Ok(review) => diesel::update(reviews.find(review.id))
+                .set(state.eq(review_state))
+                .get_result(&conn),
+            Err(_) => diesel::insert_into(reviews)
+                .values(vec![(
+                    github_user_id.eq(reviewer.id),
+                    pull_request_id.eq(pull_request.id),
+                    state.eq(review_state),
+                )])
+                .get_result(&conn),
+        }
+        .map_err(|e| e.into())
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(PullRequest)]
+#[belongs_to(User)]
+pub struct ReviewClaim {
+    pub id: i32,
+    pub pull_request_id: i32,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl ReviewClaim {
+    /// Claims the pull request for the user, or drops their claim if they
+    /// already had one. Returns whether the user is now reviewing it.
+    pub fn toggle(pull_request: &PullRequest, user: &User, db: &DBExecutor) -> Result<bool> {
+        use crate::schema::review_claims::dsl::*;
+        let conn = db.0.get()?;
+
+        let existing = review_claims
+            .filter(pull_request_id.eq(pull_request.id))
+            .filter(user_id.eq(user.id));
+        let removed = diesel::delete(existing).execute(&conn)?;
+
+        if removed == 0 {
+            diesel::insert_into(review_claims)
+                .values((pull_request_id.eq(pull_request.id), user_id.eq(user.id)))
+                .execute(&conn)?;
+        }
+
+        Ok(removed == 0)
+    }
+
+    /// Slack ids of everyone reviewing the pull request, in the order they claimed it
+    /// Ids of the pull requests the user has said they're reviewing
+    pub fn pull_request_ids(user: &User, db: &DBExecutor) -> Result<Vec<i32>> {
+        use crate::schema::review_claims::dsl::*;
+        let conn = db.0.get()?;
+
+        review_claims
+            .filter(user_id.eq(user.id))
+            .select(pull_request_id)
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Everyone who's claimed the pull request, first claim first
+    pub fn users(pull_request: &PullRequest, db: &DBExecutor) -> Result<Vec<User>> {
+        use crate::schema::review_claims::dsl::*;
+        use crate::schema::users;
+        let conn = db.0.get()?;
+
+        review_claims
+            .inner_join(users::table)
+            .filter(pull_request_id.eq(pull_request.id))
+            .order(created_at.asc())
+            .select(users::all_columns)
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn slack_user_ids(pull_request: &PullRequest, db: &DBExecutor) -> Result<Vec<String>> {
+        use crate::schema::review_claims::dsl::*;
+        use crate::schema::users::dsl::{slack_user_id, users};
+        let conn = db.0.get()?;
+
+        review_claims
+            .inner_join(users)
+            .filter(pull_request_id.eq(pull_request.id))
+            .order(created_at.asc())
+            .select(slack_user_id)
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+}
+
+/// A Github comment that's been posted in a pull request's Slack thread. It's
+/// pending, without a `slack_ts`, while the Slack message is being posted.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(PullRequest)]
+pub struct CommentMirror {
+    pub id: i32,
+    pub pull_request_id: i32,
+    pub github_comment_id: i64,
+    pub slack_ts: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl CommentMirror {
+    pub fn create(
+        pull_request: &PullRequest,
+        comment_id: i64,
+        ts: &str,
+        db: &DBExecutor,
+    ) -> Result<CommentMirror> {
+        use crate::schema::comment_mirrors::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(comment_mirrors)
+            .values((
+                pull_request_id.eq(pull_request.id),
+                github_comment_id.eq(comment_id),
+                slack_ts.eq(ts),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Records the comment before it's posted, so the Slack message event for
+    /// it can't arrive before the mirror does
+    pub fn pending(
+        pull_request: &PullRequest,
+        comment_id: i64,
+        db: &DBExecutor,
+    ) -> Result<CommentMirror> {
+        use crate::schema::comment_mirrors::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::insert_into(comment_mirrors)
+            .values((
+                pull_request_id.eq(pull_request.id),
+                github_comment_id.eq(comment_id),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn posted(&self, ts: &str, db: &DBExecutor) -> Result<CommentMirror> {
+        use crate::schema::comment_mirrors::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(self)
+            .set(slack_ts.eq(ts))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn delete(&self, db: &DBExecutor) -> Result<()> {
+        let conn = db.0.get()?;
+
+        diesel::delete(self).execute(&conn)?;
+        Ok(())
+    }
+
+    /// Whether a comment is still being posted in the pull request's thread
+    pub fn any_pending(pull_request: &PullRequest, db: &DBExecutor) -> Result<bool> {
+        use crate::schema::comment_mirrors::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::select(diesel::dsl::exists(
+            comment_mirrors
+                .filter(pull_request_id.eq(pull_request.id))
+                .filter(slack_ts.is_null()),
+        ))
+        .get_result(&conn)
+        .map_err(|e| e.into())
+    }
+
+    pub fn find_by_comment(comment_id: i64, db: &DBExecutor) -> Result<Option<CommentMirror>> {
+        use crate::schema::comment_mirrors::dsl::*;
+        let conn = db.0.get()?;
+
+        comment_mirrors
+            .filter(github_comment_id.eq(comment_id))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn find_by_slack_ts(
+        pull_request: &PullRequest,
+        ts: &str,
+        db: &DBExecutor,
+    ) -> Result<Option<CommentMirror>> {
+        use crate::schema::comment_mirrors::dsl::*;
+        let conn = db.0.get()?;
+
+        comment_mirrors
+            .filter(pull_request_id.eq(pull_request.id))
+            .filter(slack_ts.eq(ts))
+            .first(&conn)
+            .optional()
             .map_err(|e| e.into())
     }
 }
 
-#[derive(Debug, Insertable)]
-#[table_name = "pull_requests"]
-pub struct NewPullRequest {
-    pub github_id: String,
-    pub state: String,
-    pub slack_message_id: String,
-    pub channel: String,
-    pub display_text: String,
-    pub github_user_id: i32,
-}
-
+/// A review modal the bot opened, so submissions can be matched to the user
+/// and pull request it was opened for
 #[derive(Clone, Debug, Queryable, Identifiable, Associations)]
-#[table_name = "pull_requests"]
-#[belongs_to(GithubUser)]
-pub struct PullRequest {
+#[belongs_to(PullRequest)]
+pub struct ReviewView {
     pub id: i32,
-    pub github_id: String,
-    pub state: String,
-    pub slack_message_id: String,
-    pub channel: String,
-    pub display_text: String,
+    pub view_id: String,
+    pub slack_user_id: String,
+    pub pull_request_id: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
-    pub github_user_id: i32,
 }
 
-impl PullRequest {
-    pub fn create(pr: &NewPullRequest, db: &DBExecutor) -> Result<PullRequest> {
-        use crate::schema::pull_requests::dsl::*;
+impl ReviewView {
+    pub fn create(
+        opened_view_id: &str,
+        user_id: &str,
+        pull_request: &PullRequest,
+        db: &DBExecutor,
+    ) -> Result<ReviewView> {
+        use crate::schema::review_views::dsl::*;
         let conn = db.0.get()?;
 
-        diesel::insert_into(pull_requests)
-            .values(pr)
+        diesel::insert_into(review_views)
+            .values((
+                view_id.eq(opened_view_id),
+                slack_user_id.eq(user_id),
+                pull_request_id.eq(pull_request.id),
+            ))
             .get_result(&conn)
             .map_err(|e| e.into())
     }
 
-    pub fn find(gh_id: &str, db: &DBExecutor) -> Result<PullRequest> {
-        use crate::schema::pull_requests::dsl::*;
+    /// The view with `submitted_view_id`, when it was opened for `user_id`
+    pub fn find_for_user(
+        submitted_view_id: &str,
+        user_id: &str,
+        db: &DBExecutor,
+    ) -> Result<Option<ReviewView>> {
+        use crate::schema::review_views::dsl::*;
         let conn = db.0.get()?;
 
-        pull_requests
-            .filter(github_id.eq(gh_id))
+        review_views
+            .filter(view_id.eq(submitted_view_id))
+            .filter(slack_user_id.eq(user_id))
             .first(&conn)
+            .optional()
             .map_err(|e| e.into())
     }
 
-    pub fn by_state(query_state: &str, db: &DBExecutor) -> Result<Vec<PullRequest>> {
-        use crate::schema::pull_requests::dsl::*;
+    pub fn delete(&self, db: &DBExecutor) -> Result<()> {
         let conn = db.0.get()?;
 
-        pull_requests
-            .filter(state.eq(query_state))
-            .load::<PullRequest>(&conn)
-            .map_err(|e| e.into())
+        diesel::delete(self).execute(&conn)?;
+        Ok(())
     }
+}
 
-    pub fn update(&self, new_state: &str, db: &DBExecutor) -> Result<PullRequest> {
-        use crate::schema::pull_requests::dsl::*;
+/// The direct message sent to a reviewer when their review was requested.
+/// Pull requests are referred to by their tracking key since a review can be
+/// requested before the pull request is posted.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct ReviewRequestMessage {
+    pub id: i32,
+    pub pull_request_github_id: String,
+    pub reviewer_github_id: i32,
+    pub channel: String,
+    pub slack_ts: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl ReviewRequestMessage {
+    pub fn create(
+        pr_github_id: &str,
+        reviewer: i32,
+        message_channel: &str,
+        ts: &str,
+        db: &DBExecutor,
+    ) -> Result<ReviewRequestMessage> {
+        use crate::schema::review_request_messages::dsl::*;
         let conn = db.0.get()?;
 
-        diesel::update(pull_requests.find(self.id))
-            .set(state.eq(new_state))
+        diesel::insert_into(review_request_messages)
+            .values((
+                pull_request_github_id.eq(pr_github_id),
+                reviewer_github_id.eq(reviewer),
+                channel.eq(message_channel),
+                slack_ts.eq(ts),
+            ))
             .get_result(&conn)
             .map_err(|e| e.into())
     }
 
-    pub fn user(&self, db: &DBExecutor) -> Result<Option<User>> {
-        use crate::schema::github_users::dsl::*;
+    /// The messages sent to any of the Github accounts, by Github id
+    pub fn for_reviewers(ids: &[i32], db: &DBExecutor) -> Result<Vec<ReviewRequestMessage>> {
+        use crate::schema::review_request_messages::dsl::*;
         let conn = db.0.get()?;
 
-        let gh_user = github_users
-            .filter(github_id.eq(self.github_user_id))
-            .first::<GithubUser>(&conn)?;
+        review_request_messages
+            .filter(reviewer_github_id.eq_any(ids.to_vec()))
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
 
-        gh_user.user(db)
+    pub fn find(
+        pr_github_id: &str,
+        reviewer: i32,
+        db: &DBExecutor,
+    ) -> Result<Option<ReviewRequestMessage>> {
+        use crate::schema::review_request_messages::dsl::*;
+        let conn = db.0.get()?;
+
+        review_request_messages
+            .filter(pull_request_github_id.eq(pr_github_id))
+            .filter(reviewer_github_id.eq(reviewer))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    pub fn delete(&self, db: &DBExecutor) -> Result<()> {
+        use crate::schema::review_request_messages::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::delete(review_request_messages.find(self.id)).execute(&conn)?;
+        Ok(())
     }
 }
 
-#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
-#[table_name = "github_users"]
-#[belongs_to(User)]
-pub struct GithubUser {
+/// A pull request that went longer than its repository's review SLA without
+/// a review
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[table_name = "sla_breaches"]
+pub struct SlaBreach {
     pub id: i32,
-    pub login: String,
-    pub avatar_url: String,
-    pub github_id: i32,
-    pub user_id: Option<i32>,
+    pub pull_request_id: i32,
+    pub repo: String,
+    pub sla_hours: i32,
+    pub reviewed_at: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
-impl GithubUser {
-    pub fn find_or_create(
-        user: &github::User,
-        db: &DBExecutor,
-        u_id: Option<i32>,
-    ) -> Result<GithubUser> {
-        use crate::schema::github_users::dsl::*;
+impl SlaBreach {
+    pub fn create(pull_request: &PullRequest, hours: i32, db: &DBExecutor) -> Result<SlaBreach> {
+        use crate::schema::sla_breaches::dsl::*;
         let conn = db.0.get()?;
-        let res = github_users
-            .filter(github_id.eq(user.id))
-            .first::<GithubUser>(&conn);
 
-        let gh_user: Option<GithubUser> = match res {
-            Ok(user) => Ok(Some(user)),
-            Err(diesel::result::Error::NotFound) => Ok(None),
-            Err(err) => Err(err),
-        }?;
+        diesel::insert_into(sla_breaches)
+            .values((
+                pull_request_id.eq(pull_request.id),
+                repo.eq(&pull_request.repo),
+                sla_hours.eq(hours),
+            ))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
 
-        match gh_user {
-            Some(user) => Ok(user),
-            None => match u_id {
-                Some(u_id) => diesel::insert_into(github_users)
-                    .values(vec![(
-                        login.eq(&user.login),
-                        avatar_url.eq(&user.avatar_url),
-                        github_id.eq(user.id),
-                        user_id.eq(u_id),
-                    )])
-                    .get_result(&conn)
-                    .map_err(|e| e.into()),
-                None => diesel::insert_into(github_users)
-                    .values(vec![(
-                        login.eq(&user.login),
-                        avatar_url.eq(&user.avatar_url),
-                        github_id.eq(user.id),
-                    )])
-                    .get_result(&conn)
-                    .map_err(|e| e.into()),
-            },
-        }
+    /// Which of the pull requests have already breached their SLA
+    pub fn pull_request_ids(ids: &[i32], db: &DBExecutor) -> Result<Vec<i32>> {
+        use crate::schema::sla_breaches::dsl::*;
+        let conn = db.0.get()?;
+
+        sla_breaches
+            .select(pull_request_id)
+            .filter(pull_request_id.eq_any(ids.to_vec()))
+            .load(&conn)
+            .map_err(|e| e.into())
     }
 
-    pub fn user(&self, db: &DBExecutor) -> Result<Option<User>> {
-        use crate::schema::users::dsl::*;
+    /// Records when a breached pull request finally got its first review
+    pub fn reviewed(pull_request: &PullRequest, at: NaiveDateTime, db: &DBExecutor) -> Result<()> {
+        use crate::schema::sla_breaches::dsl::*;
         let conn = db.0.get()?;
 
-        if let Some(u_id) = self.user_id {
-            return users
-                .find(u_id)
-                .get_result::<User>(&conn)
-                .map(Some)
-                .map_err(|e| e.into());
-        }
-        Ok(None)
+        diesel::update(
+            sla_breaches
+                .filter(pull_request_id.eq(pull_request.id))
+                .filter(reviewed_at.is_null()),
+        )
+        .set(reviewed_at.eq(at))
+        .execute(&conn)
+        .map(|_| ())
+        .map_err(|e| e.into())
     }
-}
 
-#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
-#[belongs_to(GithubUser)]
-#[belongs_to(PullRequest)]
-pub struct Review {
-    pub id: i32,
-    pub github_user_id: i32,
-    pub pull_request_id: i32,
-    pub state: String,
-    pub created_at: NaiveDateTime,
-    pub updated_at: NaiveDateTime,
+    pub fn since(days: i64, db: &DBExecutor) -> Result<Vec<SlaBreach>> {
+        use crate::schema::sla_breaches::dsl::*;
+        let conn = db.0.get()?;
+        let since = Utc::now().naive_utc() - Duration::days(days);
+
+        sla_breaches
+            .filter(created_at.gt(since))
+            .order(repo.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
 }
 
-impl Review {
-    pub fn create_or_update(
-        reviewer: &GithubUser,
-        pull_request: &PullRequest,
-        review_state: &str,
+/// Only the instance holding the lock sends scheduled messages. It's held
+/// until it expires, so another instance takes over if the holder goes away.
+pub struct SchedulerLock;
+
+impl SchedulerLock {
+    /// Takes the lock for `ttl` if it's free or already `lock_holder`'s,
+    /// returning whether it was taken
+    pub fn acquire(
+        lock_name: &str,
+        lock_holder: &str,
+        ttl: Duration,
         db: &DBExecutor,
-    ) -> Result<Review> {
-        use crate::schema::reviews::dsl::*;
+    ) -> Result<bool> {
         let conn = db.0.get()?;
+        let now = Utc::now().naive_utc();
 
-        let review: Result<Review> = reviews
-            .filter(github_user_id.eq(&reviewer.id))
-            .filter(pull_request_id.eq(&pull_request.id))
-            .first(&conn)
-            .map_err(|e| e.into());
+        diesel::sql_query(
+            "INSERT INTO scheduler_locks (name, holder, expires_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (name) DO UPDATE \
+             SET holder = EXCLUDED.holder, expires_at = EXCLUDED.expires_at \
+             WHERE scheduler_locks.holder = EXCLUDED.holder OR scheduler_locks.expires_at < $4",
+        )
+        .bind::<Text, _>(lock_name)
+        .bind::<Text, _>(lock_holder)
+        .bind::<Timestamp, _>(now + ttl)
+        .bind::<Timestamp, _>(now)
+        .execute(&conn)
+        .map(|rows| rows == 1)
+        .map_err(|e| e.into())
+    }
+}
 
-        match review {
-            Ok(review) => diesel::update(reviews.find(review.id))
-                .set(state.eq(review_state))
-                .get_result(&conn),
-            Err(_) => diesel::insert_into(reviews)
-                .values(vec![(
-                    github_user_id.eq(reviewer.id),
-                    pull_request_id.eq(pull_request.id),
-                    state.eq(review_state),
-                )])
-                .get_result(&conn),
-        }
+#[derive(Debug, QueryableByName)]
+pub struct ReviewerCount {
+    #[sql_type = "Text"]
+    pub login: String,
+    #[sql_type = "BigInt"]
+    pub reviews: i64,
+}
+
+impl ReviewerCount {
+    pub fn since(days: i64, db: &DBExecutor) -> Result<Vec<ReviewerCount>> {
+        let conn = db.0.get()?;
+
+        diesel::sql_query(
+            "SELECT github_users.login, COUNT(*) AS reviews FROM reviews \
+             INNER JOIN github_users ON github_users.id = reviews.github_user_id \
+             WHERE reviews.updated_at > NOW() - $1 * INTERVAL '1 day' \
+             GROUP BY github_users.login ORDER BY reviews DESC",
+        )
+        .bind::<BigInt, _>(days)
+        .load(&conn)
         .map_err(|e| e.into())
     }
 }
@@ -248,6 +1760,12 @@ pub struct User {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub github_access_token: Option<String>,
+    pub away_until: Option<NaiveDateTime>,
+    /// Whether the user is away because of their Slack status, which clears
+    /// it when the status changes
+    pub away_from_status: bool,
+    /// Whether the user can use the admin pages
+    pub admin: bool,
 }
 
 #[derive(AsChangeset)]
@@ -257,6 +1775,32 @@ struct RemoveGithubToken<'a> {
     github_access_token: Option<&'a str>,
 }
 
+/// When a user is out of office. Away from their Slack status with no
+/// expiration lasts until the status changes.
+#[derive(Clone, Debug, PartialEq, AsChangeset)]
+#[table_name = "users"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct AwayStatus {
+    pub away_until: Option<NaiveDateTime>,
+    pub away_from_status: bool,
+}
+
+impl AwayStatus {
+    pub fn back() -> Self {
+        Self {
+            away_until: None,
+            away_from_status: false,
+        }
+    }
+
+    pub fn is_away(&self, now: NaiveDateTime) -> bool {
+        match self.away_until {
+            Some(until) => until > now,
+            None => self.away_from_status,
+        }
+    }
+}
+
 impl User {
     pub fn create_or_udpate(new_user: &NewUser, db: &DBExecutor) -> Result<User> {
         use crate::schema::users::dsl::*;
@@ -288,6 +1832,36 @@ impl User {
             .optional()
             .map_err(|e| e.into())
     }
+    /// Whether anyone can use the admin pages yet
+    pub fn any_admin(db: &DBExecutor) -> Result<bool> {
+        use crate::schema::users::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::select(diesel::dsl::exists(users.filter(admin.eq(true))))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn make_admin(&self, db: &DBExecutor) -> Result<User> {
+        use crate::schema::users::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(users.find(self.id))
+            .set(admin.eq(true))
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
+
+    pub fn all(db: &DBExecutor) -> Result<Vec<User>> {
+        use crate::schema::users::dsl::*;
+        let conn = db.0.get()?;
+
+        users
+            .order(username.asc())
+            .load(&conn)
+            .map_err(|e| e.into())
+    }
+
     pub fn find_by_slack_id(slack_id: &str, db: &DBExecutor) -> Result<Option<User>> {
         use crate::schema::users::dsl::*;
         let conn = db.0.get()?;
@@ -329,6 +1903,27 @@ impl User {
     pub fn is_gh_authed(&self) -> bool {
         self.github_access_token.is_some()
     }
+
+    pub fn away_status(&self) -> AwayStatus {
+        AwayStatus {
+            away_until: self.away_until,
+            away_from_status: self.away_from_status,
+        }
+    }
+
+    pub fn is_away(&self, now: NaiveDateTime) -> bool {
+        self.away_status().is_away(now)
+    }
+
+    pub fn set_away(&self, status: &AwayStatus, db: &DBExecutor) -> Result<User> {
+        use crate::schema::users::dsl::*;
+        let conn = db.0.get()?;
+
+        diesel::update(users.find(self.id))
+            .set(status)
+            .get_result(&conn)
+            .map_err(|e| e.into())
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -372,6 +1967,22 @@ impl Webhook {
         .map_err(|e| e.into())
     }
 
+    pub fn find_by_repo(
+        repo_owner: &str,
+        repo_name: &str,
+        db: &DBExecutor,
+    ) -> Result<Option<Webhook>> {
+        use crate::schema::webhooks::dsl::*;
+        let conn = db.0.get()?;
+
+        webhooks
+            .filter(owner.eq(repo_owner))
+            .filter(name.eq(repo_name))
+            .first(&conn)
+            .optional()
+            .map_err(|e| e.into())
+    }
+
     pub fn find(find_id: i32, db: &DBExecutor) -> Result<Webhook> {
         use crate::schema::webhooks::dsl::*;
         let conn = db.0.get()?;
@@ -0,0 +1,412 @@
+use actix_session::Session;
+use actix_web::{
+    web::{Data, Form, Path},
+    HttpResponse,
+};
+use actix_web_flash::{FlashMessage, FlashResponse};
+use askama::Template;
+
+use crate::db::DBExecutor;
+use crate::error::{Error, Result};
+use crate::github::TokenQuota;
+use crate::models::{
+    Calendar, EventLog, GithubUser, Holiday, IgnoreRule, NewCalendar, NewIgnoreRule, User,
+};
+use crate::routes::web::{build_response, Flash, Info};
+use crate::utils::helpers::{match_github_users_by_email, require_admin};
+use crate::utils::ical;
+use crate::utils::ignore_rules::{IgnoreRuleKind, IGNORE_RULE_KINDS};
+use crate::utils::notifications::parse_time_zone;
+use crate::AppData;
+
+#[derive(Template)]
+#[template(path = "admin/rate_limits.html")]
+struct RateLimitsTemplate<'a> {
+    info: &'a Info<'a>,
+    quotas: &'a [TokenQuota],
+}
+
+#[derive(Template)]
+#[template(path = "admin/github_users.html")]
+struct GithubUsersTemplate<'a> {
+    info: &'a Info<'a>,
+    flash: &'a Option<Flash>,
+    github_users: &'a [GithubUser],
+    users: &'a [User],
+}
+
+#[derive(Template)]
+#[template(path = "admin/calendars.html")]
+struct CalendarsTemplate<'a> {
+    info: &'a Info<'a>,
+    flash: &'a Option<Flash>,
+    calendars: &'a [CalendarSummary],
+}
+
+struct CalendarSummary {
+    calendar: Calendar,
+    working_days: String,
+    upcoming_holidays: Vec<Holiday>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/ignore_rules.html")]
+struct IgnoreRulesTemplate<'a> {
+    info: &'a Info<'a>,
+    flash: &'a Option<Flash>,
+    rules: &'a [IgnoreRule],
+    kinds: &'a [IgnoreRuleKind],
+}
+
+#[derive(Template)]
+#[template(path = "admin/events.html")]
+struct EventsTemplate<'a> {
+    info: &'a Info<'a>,
+    events: &'a [EventLog],
+}
+
+const WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+/// How many of a calendar's coming holidays are listed
+const UPCOMING_HOLIDAYS: usize = 5;
+/// How many of the latest events the event log shows
+const EVENT_LOG_LENGTH: i64 = 200;
+
+pub async fn rate_limits(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+) -> Result<HttpResponse> {
+    let current_user = Some(require_admin(&db, &session)?);
+
+    let info = Info {
+        client_id: &state.slack.client_id,
+        gh_client_id: &state.github_oauth.client_id,
+        current_user: &current_user,
+    };
+    let quotas = state.github.quotas();
+
+    Ok(build_response(
+        RateLimitsTemplate {
+            info: &info,
+            quotas: &quotas,
+        }
+        .render()?,
+    ))
+}
+
+pub async fn github_users(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+    flash_message: Option<FlashMessage<Flash>>,
+) -> Result<HttpResponse> {
+    let flash = flash_message.map(|flash| flash.into_inner());
+    let current_user = Some(require_admin(&db, &session)?);
+
+    let info = Info {
+        client_id: &state.slack.client_id,
+        gh_client_id: &state.github_oauth.client_id,
+        current_user: &current_user,
+    };
+    let github_users = GithubUser::unlinked(&db)?;
+    let users = User::all(&db)?;
+
+    Ok(build_response(
+        GithubUsersTemplate {
+            info: &info,
+            flash: &flash,
+            github_users: &github_users,
+            users: &users,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct LinkParams {
+    user_id: i32,
+}
+
+pub async fn link_github_user(
+    form: Form<LinkParams>,
+    db: Data<DBExecutor>,
+    session: Session,
+    path: Path<i32>,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    require_admin(&db, &session)?;
+
+    let user = User::find(form.user_id, &db)?.ok_or(Error::NotFoundError)?;
+    let github_user = GithubUser::find(path.0, &db)?.link(user.id, &db)?;
+
+    Ok(FlashResponse::with_redirect(
+        Flash::info(&format!(
+            "Linked {} to {}",
+            github_user.login, user.username
+        )),
+        "/admin/github_users",
+    ))
+}
+
+pub async fn match_github_users(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    let access_token = require_admin(&db, &session)?
+        .github_access_token
+        .ok_or(Error::NotAuthedError)?;
+
+    let flash = match match_github_users_by_email(&state, &db, &access_token).await {
+        Ok(linked) => Flash::info(&format!("Linked {} Github users by email", linked)),
+        Err(err) => Flash::err(&format!("Couldn't match by email: {}", err)),
+    };
+
+    Ok(FlashResponse::with_redirect(flash, "/admin/github_users"))
+}
+
+pub async fn calendars(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+    flash_message: Option<FlashMessage<Flash>>,
+) -> Result<HttpResponse> {
+    let flash = flash_message.map(|flash| flash.into_inner());
+    let current_user = Some(require_admin(&db, &session)?);
+
+    let info = Info {
+        client_id: &state.slack.client_id,
+        gh_client_id: &state.github_oauth.client_id,
+        current_user: &current_user,
+    };
+    let today = chrono::Utc::now().naive_utc().date();
+    let calendars = Calendar::all(&db)?
+        .into_iter()
+        .map(|calendar| {
+            let upcoming_holidays = Holiday::for_calendar(calendar.id, &db)?
+                .into_iter()
+                .filter(|holiday| holiday.date >= today)
+                .take(UPCOMING_HOLIDAYS)
+                .collect();
+            let working_days: Vec<&str> = calendar
+                .working_days
+                .iter()
+                .filter_map(|day| WEEKDAY_NAMES.get((*day - 1) as usize).copied())
+                .collect();
+
+            Ok(CalendarSummary {
+                working_days: working_days.join(", "),
+                upcoming_holidays,
+                calendar,
+            })
+        })
+        .collect::<Result<Vec<CalendarSummary>>>()?;
+
+    Ok(build_response(
+        CalendarsTemplate {
+            info: &info,
+            flash: &flash,
+            calendars: &calendars,
+        }
+        .render()?,
+    ))
+}
+
+/// Unchecked weekdays aren't submitted, so each is its own optional field
+#[derive(Deserialize)]
+pub struct CalendarParams {
+    name: String,
+    time_zone: String,
+    start_hour: i32,
+    end_hour: i32,
+    mon: Option<String>,
+    tue: Option<String>,
+    wed: Option<String>,
+    thu: Option<String>,
+    fri: Option<String>,
+    sat: Option<String>,
+    sun: Option<String>,
+}
+
+pub async fn create_calendar(
+    form: Form<CalendarParams>,
+    db: Data<DBExecutor>,
+    session: Session,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    require_admin(&db, &session)?;
+
+    let name = form.name.trim();
+    let time_zone = parse_time_zone(&form.time_zone);
+    let days = [
+        &form.mon, &form.tue, &form.wed, &form.thu, &form.fri, &form.sat, &form.sun,
+    ];
+    let working_days: Vec<i32> = (1..)
+        .zip(days.iter())
+        .filter(|(_, checked)| checked.is_some())
+        .map(|(day, _)| day)
+        .collect();
+
+    let flash = if name.is_empty() {
+        Flash::err("Give the calendar a name")
+    } else if !(0 <= form.start_hour && form.start_hour < form.end_hour && form.end_hour <= 24) {
+        Flash::err("Working hours must start before they end, between 0 and 24")
+    } else if let Some(time_zone) = time_zone {
+        let result = Calendar::create(
+            &NewCalendar {
+                name: name.to_string(),
+                time_zone: time_zone.name().to_string(),
+                working_days,
+                start_hour: form.start_hour,
+                end_hour: form.end_hour,
+            },
+            &db,
+        );
+        match result {
+            Ok(calendar) => Flash::info(&format!("Created {}", calendar.name)),
+            Err(err) => Flash::err(&format!("Couldn't create {}: {}", name, err)),
+        }
+    } else {
+        Flash::err(&format!(
+            "`{}` isn't a time zone like America/New_York",
+            form.time_zone
+        ))
+    };
+
+    Ok(FlashResponse::with_redirect(flash, "/admin/calendars"))
+}
+
+#[derive(Deserialize)]
+pub struct HolidayImportParams {
+    ics: String,
+}
+
+pub async fn import_holidays(
+    form: Form<HolidayImportParams>,
+    db: Data<DBExecutor>,
+    session: Session,
+    path: Path<i32>,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    require_admin(&db, &session)?;
+
+    let calendar = Calendar::find(path.0, &db)?.ok_or(Error::NotFoundError)?;
+    let holidays = ical::holidays(&form.ics);
+    let flash = if holidays.is_empty() {
+        Flash::err("No events found in that iCalendar file")
+    } else {
+        let imported = Holiday::import(calendar.id, &holidays, &db)?;
+        Flash::info(&format!(
+            "Imported {} holidays into {}",
+            imported, calendar.name
+        ))
+    };
+
+    Ok(FlashResponse::with_redirect(flash, "/admin/calendars"))
+}
+
+pub async fn ignore_rules(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+    flash_message: Option<FlashMessage<Flash>>,
+) -> Result<HttpResponse> {
+    let flash = flash_message.map(|flash| flash.into_inner());
+    let current_user = Some(require_admin(&db, &session)?);
+
+    let info = Info {
+        client_id: &state.slack.client_id,
+        gh_client_id: &state.github_oauth.client_id,
+        current_user: &current_user,
+    };
+    let rules = IgnoreRule::all(&db)?;
+
+    Ok(build_response(
+        IgnoreRulesTemplate {
+            info: &info,
+            flash: &flash,
+            rules: &rules,
+            kinds: &IGNORE_RULE_KINDS,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct IgnoreRuleParams {
+    kind: String,
+    pattern: String,
+    repo: String,
+}
+
+pub async fn create_ignore_rule(
+    form: Form<IgnoreRuleParams>,
+    db: Data<DBExecutor>,
+    session: Session,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    require_admin(&db, &session)?;
+
+    let pattern = form.pattern.trim();
+    let repo = Some(form.repo.trim()).filter(|repo| !repo.is_empty());
+
+    let flash = match form.kind.parse::<IgnoreRuleKind>() {
+        Err(err) => Flash::err(&err),
+        Ok(IgnoreRuleKind::Title) if regex::Regex::new(pattern).is_err() => {
+            Flash::err(&format!("`{}` isn't a valid regex", pattern))
+        }
+        Ok(kind) if pattern.is_empty() && kind != IgnoreRuleKind::Bot => {
+            Flash::err("Give the rule a pattern")
+        }
+        Ok(_) if repo.map(|repo| !repo.contains('/')).unwrap_or(false) => {
+            Flash::err("Give the repository as owner/repo")
+        }
+        Ok(kind) => {
+            IgnoreRule::create(
+                &NewIgnoreRule {
+                    kind: kind.to_string(),
+                    pattern: pattern.to_string(),
+                    repo: repo.map(|repo| repo.to_string()),
+                },
+                &db,
+            )?;
+            Flash::info(&format!("Added the {} rule", kind))
+        }
+    };
+
+    Ok(FlashResponse::with_redirect(flash, "/admin/ignore_rules"))
+}
+
+pub async fn delete_ignore_rule(
+    db: Data<DBExecutor>,
+    session: Session,
+    path: Path<i32>,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    require_admin(&db, &session)?;
+
+    IgnoreRule::delete(path.0, &db)?;
+
+    Ok(FlashResponse::with_redirect(
+        Flash::info("Deleted the rule"),
+        "/admin/ignore_rules",
+    ))
+}
+
+pub async fn events(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+) -> Result<HttpResponse> {
+    let current_user = Some(require_admin(&db, &session)?);
+
+    let info = Info {
+        client_id: &state.slack.client_id,
+        gh_client_id: &state.github_oauth.client_id,
+        current_user: &current_user,
+    };
+    let events = EventLog::recent(EVENT_LOG_LENGTH, &db)?;
+
+    Ok(build_response(
+        EventsTemplate {
+            info: &info,
+            events: &events,
+        }
+        .render()?,
+    ))
+}
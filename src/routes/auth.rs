@@ -31,6 +31,10 @@ pub async fn slack(
         },
         &db,
     )?;
+    // The first person to sign in to a new install set it up
+    if !User::any_admin(&db)? {
+        user.make_admin(&db)?;
+    }
     session.set("id", user.id)?;
 
     Ok(redirect_to("/"))
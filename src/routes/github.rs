@@ -74,11 +74,7 @@ pub async fn create_webhook(
     let result = state
         .github
         .create_webhook(
-            &github::ReviewRequest {
-                owner: form.owner.clone(),
-                name: form.name.clone(),
-                id: "".to_owned(),
-            },
+            &github::ReviewRequest::new(&form.owner, &form.name, ""),
             &state.webhook_url(),
             &access_token,
         )
@@ -2,14 +2,28 @@ use actix_web::{
     web::{Data, Json},
     HttpRequest, HttpResponse,
 };
+use chrono::Utc;
 
 use crate::db::DBExecutor;
 use crate::error::{Error, Result};
 use crate::github::{
-    PRAction, PRFiles, PRReviewState, PullRequestEvent, ReviewAction, ReviewEvent,
+    github_id, Comment, CommentAction, IssueCommentEvent, PRAction, PRFiles, PRReview,
+    PRReviewState, PullRequestEvent, ReviewAction, ReviewCommentEvent, ReviewEvent,
+    User as GithubAccount, SLACK_COMMENT_MARKER,
 };
-use crate::models::{GithubUser, IconMapping, NewPullRequest, PullRequest, Review, User};
-use crate::slack::Reaction;
+use crate::models::{
+    CommentMirror, EventLog, GithubUser, IconMapping, IgnoreRule, NewPullRequest, PullRequest,
+    Review, ReviewRequestMessage, SlaBreach, User,
+};
+use crate::slack::{mention, mrkdwn, MessageContext, Reaction};
+use crate::utils::away::flag_pull_request;
+use crate::utils::dependency_digest::{is_dependency_update, post_dependency_update};
+use crate::utils::helpers::{
+    message_context, post_thread_activity, refresh_pull_request_message,
+    update_pull_request_message,
+};
+use crate::utils::ignore_rules::ignore_reason;
+use crate::utils::notifications::{notify_author_of_review, wants, Notification};
 use crate::utils::prepare_response;
 use crate::AppData;
 
@@ -22,6 +36,17 @@ async fn handle_pull_request_opened(
         return Err(Error::GuardError("Ignoring Draft PR"));
     }
 
+    let tracked = PullRequest::lookup(
+        &github_id(
+            &json.pull_request.base.repo.full_name,
+            json.pull_request.number,
+        ),
+        &db,
+    )?;
+    if tracked.is_some() {
+        return Err(Error::GuardError("Pull request already tracked"));
+    }
+
     let requester = GithubUser::find_or_create(&json.pull_request.user, &db, None)?;
     let user = requester
         .user_id
@@ -34,7 +59,36 @@ async fn handle_pull_request_opened(
         user.clone().and_then(|u| u.github_access_token),
     )
     .await;
+
+    let rules = IgnoreRule::for_repo(&json.pull_request.base.repo.full_name, &db)?;
+    if let Some(reason) = ignore_reason(&rules, &json.pull_request, &pr_files.filenames) {
+        let message = format!(
+            "Didn't post {}#{} \"{}\": {}",
+            json.pull_request.base.repo.full_name,
+            json.pull_request.number,
+            json.pull_request.title,
+            reason
+        );
+        log::info!("{}", message);
+        EventLog::record(
+            "pull_request_ignored",
+            Some(&json.pull_request.html_url),
+            &message,
+            &db,
+        )?;
+        return Ok(prepare_response(""));
+    }
+
+    if is_dependency_update(&json.pull_request) {
+        post_dependency_update(&state, &db, &json.pull_request, requester.github_id).await?;
+        return Ok(prepare_response(""));
+    }
+
     let mappings = IconMapping::from(pr_files.filenames, pr_files.extensions, &db)?;
+    let context = MessageContext {
+        author: user.as_ref().map(|user| user.slack_user_id.clone()),
+        ..MessageContext::default()
+    };
 
     let result = state
         .slack
@@ -43,22 +97,18 @@ async fn handle_pull_request_opened(
             mappings,
             &state.slack.channel,
             &state.app_url,
+            &context,
             user,
         )
         .await?;
 
     PullRequest::create(
-        &NewPullRequest {
-            github_id: github_id(
-                &json.pull_request.base.repo.full_name,
-                json.pull_request.number,
-            ),
-            state: "open".to_string(),
-            slack_message_id: result.ts.unwrap_or_else(|| "".to_string()),
-            channel: result.channel.unwrap_or_else(|| "".to_string()),
-            display_text: format!("{}", json.pull_request),
-            github_user_id: requester.github_id,
-        },
+        &NewPullRequest::new(
+            &json.pull_request,
+            result.ts.unwrap_or_else(|| "".to_string()),
+            result.channel.unwrap_or_else(|| "".to_string()),
+            requester.github_id,
+        ),
         &db,
     )?;
 
@@ -70,7 +120,7 @@ async fn handle_pull_request_closed(
     db: Data<DBExecutor>,
     json: PullRequestEvent,
 ) -> Result<HttpResponse> {
-    let db_pr = PullRequest::find(
+    let mut db_pr = PullRequest::find(
         &github_id(
             &json.pull_request.base.repo.full_name,
             json.pull_request.number,
@@ -78,27 +128,70 @@ async fn handle_pull_request_closed(
         &db,
     )?
     .update("closed", &db)?;
-    let user = db_pr.user(&db)?;
-
-    let pr_files = PRFiles::new(
-        &json.pull_request,
-        &state.github,
-        user.clone().and_then(|u| u.github_access_token),
-    )
-    .await;
-    let mappings = IconMapping::from(pr_files.filenames, pr_files.extensions, &db)?;
+    if json.pull_request.merged {
+        db_pr = db_pr.set_merged(&db)?;
+    }
 
-    state
-        .slack
-        .update_message(
-            &json.pull_request,
-            mappings,
-            &db_pr.slack_message_id,
-            &db_pr.channel,
-            &state.app_url,
-            user,
+    update_pull_request_message(&state, &db, &db_pr, &json.pull_request).await?;
+    if json.pull_request.merged {
+        let merger = actor(&json.sender, &db)?;
+        post_thread_activity(
+            &state,
+            &db,
+            &db_pr,
+            &format!(":tada: Merged by {}", merger),
+            None,
         )
         .await?;
+
+        let merged_by_author = json
+            .sender
+            .as_ref()
+            .map(|sender| sender.id == db_pr.github_user_id)
+            .unwrap_or(false);
+        if let Some(author) = db_pr.user(&db)? {
+            if !merged_by_author && wants(&author, Notification::Merge, &db)? {
+                let text = format!(
+                    ":tada: Your pull request <{}|{}> was merged by {}",
+                    json.pull_request.html_url, json.pull_request.title, merger
+                );
+                state.slack.post_text(&author.slack_user_id, &text).await?;
+            }
+        }
+    }
+    Ok(prepare_response(""))
+}
+
+async fn handle_pull_request_updated(
+    state: AppData,
+    db: Data<DBExecutor>,
+    json: PullRequestEvent,
+) -> Result<HttpResponse> {
+    let tracked = PullRequest::lookup(
+        &github_id(
+            &json.pull_request.base.repo.full_name,
+            json.pull_request.number,
+        ),
+        &db,
+    )?;
+
+    if let Some(db_pr) = tracked {
+        db_pr.update_details(&json.pull_request, &db)?;
+
+        if let PRAction::Synchronize = json.action {
+            post_thread_activity(
+                &state,
+                &db,
+                &db_pr,
+                &format!(
+                    ":arrow_up: {} pushed new commits",
+                    actor(&json.sender, &db)?
+                ),
+                None,
+            )
+            .await?;
+        }
+    }
     Ok(prepare_response(""))
 }
 
@@ -112,6 +205,11 @@ pub async fn pull_request(
             handle_pull_request_opened(state, db, json.0).await
         }
         PRAction::Closed => handle_pull_request_closed(state, db, json.0).await,
+        PRAction::Edited | PRAction::Labeled | PRAction::Unlabeled | PRAction::Synchronize => {
+            handle_pull_request_updated(state, db, json.0).await
+        }
+        PRAction::ReviewRequested => handle_review_requested(state, db, json.0).await,
+        PRAction::ReviewRequestRemoved => handle_review_request_removed(state, db, json.0).await,
         _ => Err(Error::GithubError(format!(
             "Unhandled PR Action: {:?}",
             json.action
@@ -119,6 +217,139 @@ pub async fn pull_request(
     }
 }
 
+/// Sends each requested reviewer who's signed in and hasn't opted out a direct
+/// message with the pull request
+async fn handle_review_requested(
+    state: AppData,
+    db: Data<DBExecutor>,
+    json: PullRequestEvent,
+) -> Result<HttpResponse> {
+    let pull_request = &json.pull_request;
+    let key = github_id(&pull_request.base.repo.full_name, pull_request.number);
+    let reviewers = requested_reviewers(&state, &db, &json).await?;
+
+    let author = GithubUser::find_or_create(&pull_request.user, &db, None)?.user(&db)?;
+    let pr_files = PRFiles::new(
+        pull_request,
+        &state.github,
+        author.clone().and_then(|u| u.github_access_token),
+    )
+    .await;
+    let mappings = IconMapping::from(pr_files.filenames, pr_files.extensions, &db)?;
+    let context = match PullRequest::lookup(&key, &db)? {
+        Some(tracked) => message_context(&tracked, &db)?,
+        None => MessageContext {
+            author: author.map(|author| author.slack_user_id),
+            ..MessageContext::default()
+        },
+    };
+
+    let requester = actor(&json.sender, &db)?;
+    let text = match &json.requested_team {
+        Some(team) => format!(
+            ":wave: {} requested a review from {} on <{}|{}>",
+            requester, team.name, pull_request.html_url, pull_request.title
+        ),
+        None => format!(
+            ":wave: {} requested your review on <{}|{}>",
+            requester, pull_request.html_url, pull_request.title
+        ),
+    };
+
+    for reviewer in reviewers {
+        let user = match GithubUser::find_or_create(&reviewer, &db, None)?.user(&db)? {
+            Some(user) => user,
+            None => continue,
+        };
+        if user.is_away(Utc::now().naive_utc()) {
+            if let Some(tracked) = PullRequest::lookup(&key, &db)? {
+                flag_pull_request(&state, &user, &tracked, &db).await?;
+            }
+            continue;
+        }
+        if !wants(&user, Notification::ReviewRequest, &db)?
+            || ReviewRequestMessage::find(&key, reviewer.id, &db)?.is_some()
+        {
+            continue;
+        }
+
+        let result = state
+            .slack
+            .post_direct_message(
+                &user.slack_user_id,
+                &text,
+                pull_request,
+                mappings.clone(),
+                &state.app_url,
+                &context,
+            )
+            .await?;
+        ReviewRequestMessage::create(
+            &key,
+            reviewer.id,
+            &result.channel.unwrap_or_default(),
+            &result.ts.unwrap_or_default(),
+            &db,
+        )?;
+    }
+
+    Ok(prepare_response(""))
+}
+
+/// Takes back the direct messages sent for a review request
+async fn handle_review_request_removed(
+    state: AppData,
+    db: Data<DBExecutor>,
+    json: PullRequestEvent,
+) -> Result<HttpResponse> {
+    let key = github_id(
+        &json.pull_request.base.repo.full_name,
+        json.pull_request.number,
+    );
+
+    for reviewer in requested_reviewers(&state, &db, &json).await? {
+        if let Some(message) = ReviewRequestMessage::find(&key, reviewer.id, &db)? {
+            state
+                .slack
+                .delete_message(&message.channel, &message.slack_ts)
+                .await?;
+            message.delete(&db)?;
+        }
+    }
+
+    Ok(prepare_response(""))
+}
+
+/// The requested reviewer, or everyone on the requested team. Teams are
+/// expanded with the author's token, so they're skipped when the author hasn't
+/// connected Github.
+async fn requested_reviewers(
+    state: &AppData,
+    db: &DBExecutor,
+    json: &PullRequestEvent,
+) -> Result<Vec<GithubAccount>> {
+    if let Some(reviewer) = &json.requested_reviewer {
+        return Ok(vec![reviewer.clone()]);
+    }
+    let team = match &json.requested_team {
+        Some(team) => team,
+        None => return Ok(vec![]),
+    };
+
+    let token = GithubUser::find_or_create(&json.pull_request.user, db, None)?
+        .user(db)?
+        .and_then(|author| author.github_access_token);
+    match token {
+        Some(token) => {
+            state
+                .github
+                .get_team_members(&json.pull_request, team, &token)
+                .await
+        }
+        None => Ok(vec![]),
+    }
+}
+
 pub async fn ping(_: HttpRequest) -> Result<HttpResponse> {
     Ok(prepare_response(""))
 }
@@ -130,6 +361,7 @@ pub async fn review(
 ) -> Result<HttpResponse> {
     match json.action {
         ReviewAction::Submitted => handle_review_submitted(state, db, json.0).await,
+        ReviewAction::Dismissed => handle_review_dismissed(state, db, json.0).await,
         _ => Ok(prepare_response("")),
     }
 }
@@ -158,24 +390,230 @@ async fn handle_review_submitted(
         ),
         &db,
     )?;
+    let previous_state = db_pr.state.clone();
     db_pr = db_pr.update(&next_state(&db_pr.state, approved), &db)?;
     Review::create_or_update(&reviewer, &db_pr, &json.review.state.to_string(), &db)?;
+    SlaBreach::reviewed(&db_pr, Utc::now().naive_utc(), &db)?;
 
-    state
-        .slack
-        .add_reaction(
-            &reaction,
-            &db_pr.slack_message_id,
-            &db_pr.channel,
-            reviewer_user,
-        )
-        .await?;
+    // A digest's message is shared, so its statuses show the reviews instead
+    if db_pr.digest_id.is_none() {
+        state
+            .slack
+            .add_reaction(
+                &reaction,
+                &db_pr.slack_message_id,
+                &db_pr.channel,
+                reviewer_user.clone(),
+            )
+            .await?;
+    }
+
+    // Approval adds the merge button, which needs the full pull request to re-render
+    if db_pr.state != previous_state {
+        let token = reviewer_user.and_then(|user| user.github_access_token);
+        refresh_pull_request_message(&state, &db, &db_pr, token).await?;
+    }
+
+    let text = review_activity(&json.review, &display_name(&json.review.user, &db)?);
+    post_thread_activity(&state, &db, &db_pr, &text, None).await?;
+
+    // Authors replying to their own review threads don't need telling
+    let author = match db_pr.user(&db)? {
+        Some(author) if json.review.user.id != db_pr.github_user_id => Some(author),
+        _ => None,
+    };
+    let author = match author {
+        Some(author) if wants(&author, Notification::Review, &db)? => Some(author),
+        _ => None,
+    };
+    if let Some(author) = author {
+        let summary = match json
+            .review
+            .body
+            .as_deref()
+            .filter(|body| !body.trim().is_empty())
+        {
+            Some(body) => format!(
+                "{}\n{}",
+                text,
+                mrkdwn::quote(&mrkdwn::excerpt(body, COMMENT_EXCERPT_LENGTH))
+            ),
+            None => text,
+        };
+        notify_author_of_review(&state, &author, &db_pr, summary);
+    }
+
+    // The reviewer's request is done with, so its direct message says so
+    if let Some(message) = ReviewRequestMessage::find(&db_pr.github_id, json.review.user.id, &db)? {
+        let text = format!(
+            ":white_check_mark: You reviewed <{}|{}>",
+            json.pull_request.html_url, json.pull_request.title
+        );
+        state
+            .slack
+            .update_text(&message.channel, &message.slack_ts, &text)
+            .await?;
+        message.delete(&db)?;
+    }
+
+    Ok(prepare_response(""))
+}
+
+async fn handle_review_dismissed(
+    state: AppData,
+    db: Data<DBExecutor>,
+    json: ReviewEvent,
+) -> Result<HttpResponse> {
+    let db_pr = PullRequest::lookup(
+        &github_id(
+            &json.pull_request.base.repo.full_name,
+            json.pull_request.number,
+        ),
+        &db,
+    )?
+    .ok_or(Error::GuardError("Pull request not tracked"))?;
+
+    let reviewer = GithubUser::find_or_create(&json.review.user, &db, None)?;
+    Review::create_or_update(&reviewer, &db_pr, &json.review.state.to_string(), &db)?;
+
+    // Dismissing the last approval takes the merge button away again
+    let still_approved = Review::for_pull_requests(&[db_pr.id], &db)?
+        .iter()
+        .any(|review| review.state == PRReviewState::Approved.to_string());
+    if db_pr.state == "approved" && !still_approved {
+        let db_pr = db_pr.update("open", &db)?;
+        refresh_pull_request_message(&state, &db, &db_pr, None).await?;
+    }
+
+    post_thread_activity(
+        &state,
+        &db,
+        &db_pr,
+        &format!(
+            ":no_entry_sign: {}'s review was dismissed by {}",
+            display_name(&json.review.user, &db)?,
+            actor(&json.sender, &db)?
+        ),
+        None,
+    )
+    .await?;
+
+    Ok(prepare_response(""))
+}
+
+pub async fn review_comment(
+    json: Json<ReviewCommentEvent>,
+    state: AppData,
+    db: Data<DBExecutor>,
+) -> Result<HttpResponse> {
+    match json.action {
+        CommentAction::Created => {
+            let id = github_id(
+                &json.pull_request.base.repo.full_name,
+                json.pull_request.number,
+            );
+            mirror_comment(state, db, &id, &json.comment).await
+        }
+        _ => Ok(prepare_response("")),
+    }
+}
+
+pub async fn issue_comment(
+    json: Json<IssueCommentEvent>,
+    state: AppData,
+    db: Data<DBExecutor>,
+) -> Result<HttpResponse> {
+    if json.issue.pull_request.is_none() {
+        return Err(Error::GuardError("Comment isn't on a pull request"));
+    }
+
+    match json.action {
+        CommentAction::Created => {
+            let id = github_id(&json.repository.full_name, json.issue.number);
+            mirror_comment(state, db, &id, &json.comment).await
+        }
+        _ => Ok(prepare_response("")),
+    }
+}
+
+/// Posts a comment in the pull request's thread, as the commenter when their
+/// Github account is linked. Redelivered webhooks are only mirrored once.
+async fn mirror_comment(
+    state: AppData,
+    db: Data<DBExecutor>,
+    pull_request_id: &str,
+    comment: &Comment,
+) -> Result<HttpResponse> {
+    if comment.body.contains(SLACK_COMMENT_MARKER) {
+        return Err(Error::GuardError("Comment was posted from Slack"));
+    }
+    let db_pr = PullRequest::lookup(pull_request_id, &db)?
+        .ok_or(Error::GuardError("Pull request not tracked"))?;
+    if CommentMirror::find_by_comment(comment.id, &db)?.is_some() {
+        return Err(Error::GuardError("Comment already mirrored"));
+    }
+
+    let commenter = GithubUser::find_or_create(&comment.user, &db, None)?;
+    let text = comment_activity(comment, &display_name(&comment.user, &db)?);
+    let mirror = CommentMirror::pending(&db_pr, comment.id, &db)?;
+    match post_thread_activity(&state, &db, &db_pr, &text, commenter.user(&db)?).await {
+        Ok(Some(ts)) => {
+            mirror.posted(&ts, &db)?;
+        }
+        Ok(None) => mirror.delete(&db)?,
+        Err(err) => {
+            mirror.delete(&db)?;
+            return Err(err);
+        }
+    }
 
     Ok(prepare_response(""))
 }
 
-fn github_id(repo: &str, number: u32) -> String {
-    format!("{}-{}", repo, number)
+fn review_activity(review: &PRReview, reviewer: &str) -> String {
+    let (emoji, verb) = match review.state {
+        PRReviewState::Approved => (":white_check_mark:", "approved"),
+        PRReviewState::ChangesRequested => (":x:", "requested changes"),
+        PRReviewState::Commented => (":speech_balloon:", "commented"),
+        PRReviewState::Dismissed => (":no_entry_sign:", "had their review dismissed"),
+    };
+
+    match &review.html_url {
+        Some(url) => format!("{} {} <{}|{}>", emoji, reviewer, url, verb),
+        None => format!("{} {} {}", emoji, reviewer, verb),
+    }
+}
+
+const COMMENT_EXCERPT_LENGTH: usize = 500;
+
+fn comment_activity(comment: &Comment, commenter: &str) -> String {
+    let verb = match &comment.path {
+        Some(path) => format!("commented on `{}`", path),
+        None => "commented".to_string(),
+    };
+
+    format!(
+        ":speech_balloon: {} <{}|{}>\n{}",
+        commenter,
+        comment.html_url,
+        verb,
+        mrkdwn::quote(&mrkdwn::excerpt(&comment.body, COMMENT_EXCERPT_LENGTH))
+    )
+}
+
+fn actor(sender: &Option<GithubAccount>, db: &DBExecutor) -> Result<String> {
+    match sender {
+        Some(sender) => display_name(sender, db),
+        None => Ok("someone".to_string()),
+    }
+}
+
+/// Mentions the person in Slack when their Github account is linked
+fn display_name(account: &GithubAccount, db: &DBExecutor) -> Result<String> {
+    Ok(mention(
+        GithubUser::slack_user_id(account.id, db)?.as_deref(),
+        &account.login,
+    ))
 }
 
 fn next_state(state: &str, approved: bool) -> String {
@@ -1,5 +1,7 @@
+pub mod admin;
 pub mod auth;
 pub mod github;
 pub mod github_webhook;
+pub mod slack_interaction;
 pub mod slack_webhook;
 pub mod web;
@@ -0,0 +1,417 @@
+use actix_web::{
+    web::{Data, Form},
+    HttpResponse,
+};
+use std::collections::HashMap;
+
+use crate::db::DBExecutor;
+use crate::error::{Error, Result};
+use crate::github::{NewReview, ReviewRequest, ReviewVerdict};
+use crate::models::{GithubUser, PullRequest, RepoSetting, ReviewClaim, ReviewView, User};
+use crate::routes::slack_webhook::post_pull_request;
+use crate::slack::views::{
+    View, ViewErrors, REVIEW_CALLBACK, REVIEW_COMMENT_BLOCK, REVIEW_VERDICT_BLOCK,
+};
+use crate::slack::{
+    APPROVE_ALL_ACTION, CLAIM_REVIEW_ACTION, MERGE_ACTION, MUTE_ACTION, NUDGE_REVIEWERS_ACTION,
+    REVIEW_ACTION, TRACK_ACTION,
+};
+use crate::utils::dependency_digest;
+use crate::utils::helpers::refresh_pull_request_message;
+use crate::utils::prepare_response;
+use crate::AppData;
+
+/// Slack posts interactions as a form with the JSON in a single `payload` field
+#[derive(Deserialize, Debug)]
+pub struct InteractionRequest {
+    payload: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum Interaction {
+    BlockActions {
+        user: InteractionUser,
+        trigger_id: String,
+        response_url: String,
+        channel: Option<InteractionChannel>,
+        actions: Vec<BlockAction>,
+    },
+    ViewSubmission {
+        user: InteractionUser,
+        view: SubmittedView,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InteractionUser {
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InteractionChannel {
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BlockAction {
+    pub action_id: String,
+    pub value: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubmittedView {
+    pub id: String,
+    pub callback_id: String,
+    pub private_metadata: String,
+    pub state: ViewState,
+}
+
+/// Submitted input values, keyed by block id and then action id
+#[derive(Deserialize, Debug)]
+pub struct ViewState {
+    pub values: HashMap<String, HashMap<String, InputValue>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InputValue {
+    pub value: Option<String>,
+    pub selected_option: Option<SelectedOption>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SelectedOption {
+    pub value: String,
+}
+
+impl ViewState {
+    /// The text entered or option chosen in a block, if any
+    fn value(&self, block_id: &str) -> Option<String> {
+        let input = self.values.get(block_id)?.values().next()?;
+
+        input
+            .selected_option
+            .as_ref()
+            .map(|option| option.value.clone())
+            .or_else(|| input.value.clone())
+    }
+}
+
+pub async fn interaction(
+    form: Form<InteractionRequest>,
+    state: AppData,
+    db: Data<DBExecutor>,
+) -> Result<HttpResponse> {
+    match serde_json::from_str(&form.payload)? {
+        Interaction::BlockActions {
+            user,
+            trigger_id,
+            response_url,
+            channel,
+            actions,
+        } => {
+            for action in actions {
+                let notice = if action.action_id == TRACK_ACTION {
+                    track(&action, &user.id, channel.as_ref(), &state, &db).await?
+                } else if action.action_id == APPROVE_ALL_ACTION {
+                    approve_all(&action, &user.id, &state, &db).await?
+                } else {
+                    block_action(&action, &user.id, &trigger_id, &state, &db).await?
+                };
+
+                if let Some(notice) = notice {
+                    state.slack.respond(&response_url, &notice).await?;
+                }
+            }
+
+            Ok(prepare_response(""))
+        }
+        Interaction::ViewSubmission { user, view } if view.callback_id == REVIEW_CALLBACK => {
+            submit_review(&view, &user.id, &state, &db).await
+        }
+        Interaction::ViewSubmission { .. } | Interaction::Unsupported => {
+            Err(Error::GuardError("Unsupported interaction"))
+        }
+    }
+}
+
+/// Handles a button on a pull request message, returning a notice for the user
+/// who clicked it when there's something they need to know
+async fn block_action(
+    action: &BlockAction,
+    slack_user_id: &str,
+    trigger_id: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<Option<String>> {
+    let db_pr = match &action.value {
+        Some(value) => PullRequest::lookup(value, db)?,
+        None => None,
+    };
+    let db_pr = match db_pr {
+        Some(db_pr) => db_pr,
+        None => return Ok(Some("This pull request is no longer tracked".to_string())),
+    };
+    let user = User::find_by_slack_id(slack_user_id, db)?;
+
+    match action.action_id.as_str() {
+        REVIEW_ACTION => {
+            if !user.map(|user| user.is_gh_authed()).unwrap_or(false) {
+                return Ok(Some(format!(
+                    "Connect your Github account at {} to review from Slack",
+                    state.app_url
+                )));
+            }
+
+            let title = if db_pr.title.is_empty() {
+                &db_pr.display_text
+            } else {
+                &db_pr.title
+            };
+            let opened = state
+                .slack
+                .open_view(trigger_id, &View::review(&db_pr.github_id, title))
+                .await?;
+            if let Some(view) = opened.view {
+                ReviewView::create(&view.id, slack_user_id, &db_pr, db)?;
+            }
+            Ok(None)
+        }
+        MERGE_ACTION => {
+            let token = match user.and_then(|user| user.github_access_token) {
+                Some(token) => token,
+                None => {
+                    return Ok(Some(format!(
+                        "Connect your Github account at {} to merge from Slack",
+                        state.app_url
+                    )))
+                }
+            };
+            merge(&db_pr, &token, state, db).await
+        }
+        CLAIM_REVIEW_ACTION => {
+            let user = match user {
+                Some(user) => user,
+                None => {
+                    return Ok(Some(format!(
+                        "Sign in at {} to claim reviews",
+                        state.app_url
+                    )))
+                }
+            };
+            let is_author = GithubUser::for_user(user.id, db)?
+                .iter()
+                .any(|github_user| github_user.github_id == db_pr.github_user_id);
+            if is_author {
+                return Ok(Some("You can't review your own pull request".to_string()));
+            }
+
+            ReviewClaim::toggle(&db_pr, &user, db)?;
+            refresh_message(&db_pr, user.github_access_token, state, db).await
+        }
+        NUDGE_REVIEWERS_ACTION => {
+            let reviewers: Vec<String> = ReviewClaim::slack_user_ids(&db_pr, db)?
+                .iter()
+                .map(|reviewer| format!("<@{}>", reviewer))
+                .collect();
+            let text = if reviewers.is_empty() {
+                format!(
+                    "<@{}> is looking for someone to review this pull request",
+                    slack_user_id
+                )
+            } else {
+                format!(
+                    "{}: <@{}> is waiting on your review",
+                    reviewers.join(", "),
+                    slack_user_id
+                )
+            };
+
+            state
+                .slack
+                .post_thread_reply(&db_pr.channel, &db_pr.slack_message_id, &text, None)
+                .await?;
+            Ok(None)
+        }
+        MUTE_ACTION => {
+            let db_pr = db_pr.set_muted(!db_pr.muted, db)?;
+            refresh_message(
+                &db_pr,
+                user.and_then(|user| user.github_access_token),
+                state,
+                db,
+            )
+            .await
+        }
+        _ => Err(Error::GuardError("Unknown action")),
+    }
+}
+
+/// Starts tracking an unfurled pull request by posting it in the channel the
+/// link was shared in
+async fn track(
+    action: &BlockAction,
+    slack_user_id: &str,
+    channel: Option<&InteractionChannel>,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<Option<String>> {
+    let token = match User::find_by_slack_id(slack_user_id, db)?
+        .and_then(|user| user.github_access_token)
+    {
+        Some(token) => token,
+        None => {
+            return Ok(Some(format!(
+                "Connect your Github account at {} to post pull requests for review",
+                state.app_url
+            )))
+        }
+    };
+    let channel = channel.ok_or(Error::GuardError("Action outside a channel"))?;
+    let link = action.value.as_deref().unwrap_or_default();
+
+    let result = match ReviewRequest::parse(link, None) {
+        Ok(review_request) => {
+            post_pull_request(state, db, &review_request, &channel.id, &token).await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match result {
+        Ok(warning) => Ok(warning),
+        Err(err) => Ok(Some(format!("Couldn't post {}: {}", link, err))),
+    }
+}
+
+/// Merges the pull request as the clicking user, once Github confirms they can
+/// push to the repository. The closed webhook updates the message afterwards.
+async fn merge(
+    db_pr: &PullRequest,
+    token: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<Option<String>> {
+    let review_request = db_pr.review_request().ok_or(Error::NotFoundError)?;
+
+    let repo = state.github.get_repo(&review_request, token).await?;
+    if !repo.permissions.push {
+        return Ok(Some(format!(
+            "You don't have permission to merge into {}",
+            repo.full_name
+        )));
+    }
+
+    let merge_method = RepoSetting::merge_method(&repo.full_name, db)?;
+    match state
+        .github
+        .merge(&review_request, merge_method, token)
+        .await
+    {
+        Ok(()) => Ok(None),
+        Err(Error::OutboundError { message, .. }) => Ok(Some(format!(
+            "Couldn't merge {}: {}",
+            db_pr.display_text, message
+        ))),
+        Err(err) => Err(err),
+    }
+}
+
+/// Re-renders the message with the clicking user's token, falling back to the
+/// author's
+async fn refresh_message(
+    db_pr: &PullRequest,
+    token: Option<String>,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<Option<String>> {
+    if refresh_pull_request_message(state, db, db_pr, token).await? {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "Connect your Github account at {} to update this message",
+            state.app_url
+        )))
+    }
+}
+
+/// Approves every dependency update in a digest still waiting for a review,
+/// as the user who clicked the button
+async fn approve_all(
+    action: &BlockAction,
+    slack_user_id: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<Option<String>> {
+    let digest_id = match action.value.as_ref().and_then(|value| value.parse().ok()) {
+        Some(digest_id) => digest_id,
+        None => return Ok(Some("This digest is no longer tracked".to_string())),
+    };
+    let token = match User::find_by_slack_id(slack_user_id, db)?
+        .and_then(|user| user.github_access_token)
+    {
+        Some(token) => token,
+        None => {
+            return Ok(Some(format!(
+                "Connect your Github account at {} to approve from Slack",
+                state.app_url
+            )))
+        }
+    };
+
+    dependency_digest::approve_all(digest_id, &token, state, db).await
+}
+
+/// Submits the review from the modal to Github as the user who filled it in,
+/// as long as the bot opened that modal for them on that pull request. The
+/// message itself is updated by the review webhook like any other review.
+async fn submit_review(
+    view: &SubmittedView,
+    slack_user_id: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    let review_view =
+        ReviewView::find_for_user(&view.id, slack_user_id, db)?.ok_or(Error::NotAuthedError)?;
+    let token = User::find_by_slack_id(slack_user_id, db)?
+        .and_then(|user| user.github_access_token)
+        .ok_or(Error::NotAuthedError)?;
+    let review_request = PullRequest::lookup(&view.private_metadata, db)?
+        .filter(|db_pr| db_pr.id == review_view.pull_request_id)
+        .and_then(|db_pr| db_pr.review_request())
+        .ok_or(Error::NotFoundError)?;
+
+    let event = view
+        .state
+        .value(REVIEW_VERDICT_BLOCK)
+        .and_then(|verdict| serde_json::from_value(serde_json::Value::String(verdict)).ok())
+        .unwrap_or(ReviewVerdict::Comment);
+    let body = view
+        .state
+        .value(REVIEW_COMMENT_BLOCK)
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if event != ReviewVerdict::Approve && body.is_empty() {
+        return view_errors(REVIEW_COMMENT_BLOCK, "Add a comment explaining your review");
+    }
+
+    let review = NewReview { event, body };
+    match state
+        .github
+        .create_review(&review_request, &review, &token)
+        .await
+    {
+        Ok(()) => {
+            review_view.delete(db)?;
+            Ok(prepare_response(""))
+        }
+        Err(err) => view_errors(REVIEW_VERDICT_BLOCK, &format!("{}", err)),
+    }
+}
+
+fn view_errors(block_id: &str, message: &str) -> Result<HttpResponse> {
+    let res = serde_json::to_string(&ViewErrors::new(block_id, message))?;
+    Ok(prepare_response(&res))
+}
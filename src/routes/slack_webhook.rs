@@ -1,22 +1,80 @@
+use std::future::Future;
+
 use actix_web::{
     web::{Data, Form, Json},
-    HttpResponse,
+    HttpRequest, HttpResponse,
 };
 
 use crate::db::DBExecutor;
-use crate::error::Result;
-use crate::models::{IconMapping, PullRequest as PullRequestModel, User};
-use crate::slack::{attachment, SlackRequest};
+use crate::error::{Error, Result};
+use crate::github::{github_id, NewComment, PRFiles, PRResult, ReviewRequest};
+use crate::models::{
+    AwayStatus, Calendar, ChannelSchedule, ChannelSetting, CommentMirror, GithubUser, IconMapping,
+    NewPullRequest, NotificationPreference, PullRequest as PullRequestModel, QueueQuery,
+    RepoSetting, Review, ReviewerCount, SlaBreach, User, Webhook,
+};
+use crate::slack::command::{
+    self, AwayCommand, Command, NotifyCommand, QueueFilter, SettingsCommand,
+};
+use crate::slack::views::View;
+use crate::slack::{attachment, mrkdwn, MessageContext, SlackRequest, Unfurl};
+use crate::utils::away::{away_until_date, back_on, flag_for_reassignment, status_change};
+use crate::utils::business_time::WorkingHours;
+use crate::utils::helpers::{message_context, queue_entries, ReviewQueue};
 use crate::utils::prepare_response;
 use crate::AppData;
 
-pub async fn review(
+pub async fn command(
+    form: Form<SlackRequest>,
+    state: AppData,
+    db: Data<DBExecutor>,
+) -> Result<HttpResponse> {
+    match Command::parse(&form.text) {
+        Ok(command) => dispatch(command, &form, &state, &db).await,
+        Err(err) => ephemeral(&state, format!("{}", err)),
+    }
+}
+
+pub async fn reviews(
     form: Form<SlackRequest>,
     state: AppData,
     db: Data<DBExecutor>,
+) -> Result<HttpResponse> {
+    match Command::parse(&format!("queue {}", form.text)) {
+        Ok(command) => dispatch(command, &form, &state, &db).await,
+        Err(err) => ephemeral(&state, format!("{}", err)),
+    }
+}
+
+async fn dispatch(
+    command: Command,
+    form: &SlackRequest,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    match command {
+        Command::Post(text) => post(form, &text, state, db).await,
+        Command::Queue(filters) => queue(form, &filters, state, db).await,
+        Command::Stats => stats(form, state, db),
+        Command::Snooze {
+            pull_request,
+            hours,
+        } => snooze(form, &pull_request, hours, state, db),
+        Command::Settings(setting) => settings(form, setting, state, db),
+        Command::Notify(notify) => notifications(form, notify, state, db),
+        Command::Away(away) => out_of_office(form, away, state, db).await,
+        Command::Help => ephemeral(state, command::usage()),
+    }
+}
+
+async fn post(
+    form: &SlackRequest,
+    text: &str,
+    state: &AppData,
+    db: &DBExecutor,
 ) -> Result<HttpResponse> {
     let access_token = if let Some(token) =
-        User::find_by_slack_id(&form.user_id, &db)?.and_then(|user| user.github_access_token)
+        User::find_by_slack_id(&form.user_id, db)?.and_then(|user| user.github_access_token)
     {
         token
     } else {
@@ -25,18 +83,42 @@ pub async fn review(
         )?;
         return Ok(prepare_response(&res));
     };
-    if form.text.trim().is_empty() {
-        let res = state.slack.immediate_response(
-                "Specify pull request For example: /code_review_bot http://github.com/facebook/react/pulls/123".to_string(),
-            )?;
-        return Ok(prepare_response(&res));
+    let default_repo = ChannelSetting::default_repo(&form.channel_id, db)?;
+    let mut notices = vec![];
+
+    for (text, pull_request) in ReviewRequest::parse_all(text, default_repo.as_deref()) {
+        let result = match pull_request {
+            Ok(pull_request) => {
+                post_pull_request(state, db, &pull_request, &form.channel_id, &access_token).await
+            }
+            Err(err) => Err(err.into()),
+        };
+
+        match result {
+            Ok(Some(warning)) => notices.push(warning),
+            Ok(None) => (),
+            Err(err) => notices.push(format!("Couldn't post {}: {}", text, err)),
+        }
+    }
+
+    if notices.is_empty() {
+        Ok(prepare_response(""))
+    } else {
+        ephemeral(state, notices.join("\n"))
     }
+}
 
-    let pull_request = form.text.to_lowercase().parse()?;
-    let pr_response = state.github.get_pr(&pull_request, &access_token).await?;
+pub(crate) async fn post_pull_request(
+    state: &AppData,
+    db: &DBExecutor,
+    pull_request: &ReviewRequest,
+    channel: &str,
+    access_token: &str,
+) -> Result<Option<String>> {
+    let pr_response = state.github.get_pr(pull_request, access_token).await?;
     let (filenames, extensions): (Vec<_>, Vec<_>) = state
         .github
-        .get_files(&pr_response, &access_token)
+        .get_files(&pr_response, access_token)
         .await
         .map(|files| {
             files
@@ -48,47 +130,479 @@ pub async fn review(
     let mut extensions: Vec<String> = extensions.into_iter().filter_map(|o| o).collect();
     extensions.dedup();
 
-    let mappings = IconMapping::from(filenames, extensions, &db)?;
+    let mappings = IconMapping::from(filenames, extensions, db)?;
 
-    state
+    let repo = &pr_response.base.repo;
+    let tracked = PullRequestModel::lookup(&github_id(&repo.full_name, pr_response.number), db)?;
+    let context = match &tracked {
+        Some(tracked) => message_context(tracked, db)?,
+        None => MessageContext {
+            author: GithubUser::slack_user_id(pr_response.user.id, db)?,
+            ..MessageContext::default()
+        },
+    };
+
+    let result = state
         .slack
         .post_message(
             &pr_response,
             mappings,
-            &form.channel_id,
+            channel,
             &state.app_url,
+            &context,
             None,
         )
         .await?;
+    let ts = result.ts.unwrap_or_else(|| "".to_string());
+    let posted_channel = result.channel.unwrap_or_else(|| channel.to_string());
 
-    Ok(prepare_response(""))
+    match tracked {
+        Some(tracked) => {
+            tracked.move_message(&ts, &posted_channel, db)?;
+        }
+        None => {
+            let author = GithubUser::find_or_create(&pr_response.user, db, None)?;
+            PullRequestModel::create(
+                &NewPullRequest::new(&pr_response, ts, posted_channel, author.github_id),
+                db,
+            )?;
+        }
+    }
+
+    if Webhook::find_by_repo(&repo.owner.login, &repo.name, db)?.is_none() {
+        return Ok(Some(format!(
+            "{} has no webhook installed, so this message won't be updated when the pull request is reviewed or closed. Install one at {}.",
+            repo.full_name, state.app_url
+        )));
+    }
+
+    Ok(None)
 }
 
-pub async fn reviews(
-    form: Form<SlackRequest>,
-    state: AppData,
-    db: Data<DBExecutor>,
+async fn queue(
+    form: &SlackRequest,
+    filters: &[QueueFilter],
+    state: &AppData,
+    db: &DBExecutor,
 ) -> Result<HttpResponse> {
-    let prs = PullRequestModel::by_state("open", &db)?;
+    let mut query = QueueQuery::default();
 
-    let open_prs: Vec<String> = if prs.is_empty() {
-        vec!["All PRs Reviewed! :partyparrot:".to_string()]
-    } else {
-        prs.iter().map(|pr| pr.display_text.to_string()).collect()
-    };
+    for filter in filters {
+        let authors = match filter {
+            QueueFilter::Repo(repo) => {
+                query.repos.push(repo.to_string());
+                continue;
+            }
+            QueueFilter::Label(label) => {
+                query.labels.push(label.to_string());
+                continue;
+            }
+            QueueFilter::Mine => github_ids_for_slack_user(&form.user_id, db)?,
+            QueueFilter::SlackUser(slack_id) => github_ids_for_slack_user(slack_id, db)?,
+            QueueFilter::GithubLogin(login) => match GithubUser::find_by_login(login, db)? {
+                Some(github_user) => vec![github_user.github_id],
+                None => return ephemeral(state, format!("No pull requests by {} found", login)),
+            },
+        };
+
+        query.authors = Some(match query.authors {
+            Some(existing) => existing
+                .into_iter()
+                .filter(|id| authors.contains(id))
+                .collect(),
+            None => authors,
+        });
+    }
+
+    let prs = PullRequestModel::waiting(&query, db)?;
+    let pr_ids: Vec<i32> = prs.iter().map(|pr| pr.id).collect();
+    let author_ids: Vec<i32> = prs.iter().map(|pr| pr.github_user_id).collect();
+    let reviews = Review::for_pull_requests(&pr_ids, db)?;
+    let authors = GithubUser::by_github_ids(&author_ids, db)?;
+    let slack_ids = GithubUser::slack_user_ids(&author_ids, db)?;
 
     state
         .slack
-        .reviews_response(&open_prs.join("\n"), &form.channel_id)
+        .reviews_response(
+            &queue_entries(&prs, &reviews, &authors, &slack_ids),
+            &form.channel_id,
+        )
         .await?;
     Ok(prepare_response(""))
 }
 
+fn github_ids_for_slack_user(slack_id: &str, db: &DBExecutor) -> Result<Vec<i32>> {
+    let github_ids = match User::find_by_slack_id(slack_id, db)? {
+        Some(user) => GithubUser::for_user(user.id, db)?
+            .iter()
+            .map(|github_user| github_user.github_id)
+            .collect(),
+        None => vec![],
+    };
+
+    Ok(github_ids)
+}
+
+fn stats(form: &SlackRequest, state: &AppData, db: &DBExecutor) -> Result<HttpResponse> {
+    let waiting = PullRequestModel::waiting(&QueueQuery::default(), db)?.len();
+    let approved = PullRequestModel::by_state("approved", db)?.len();
+    let reviewers = ReviewerCount::since(30, db)?;
+
+    let mut lines = vec![
+        format!("*Waiting for review:* {}", waiting),
+        format!("*Approved, not merged:* {}", approved),
+    ];
+    if let Some(turnaround) = review_turnaround(&form.channel_id, 30, db)? {
+        lines.push(format!(
+            "*Median time to first review:* {:.1} working hours",
+            turnaround.num_minutes() as f64 / 60.0
+        ));
+    }
+    lines.push("*Reviews in the last 30 days*".to_string());
+    if reviewers.is_empty() {
+        lines.push("No reviews yet".to_string());
+    }
+    lines.extend(
+        reviewers
+            .iter()
+            .map(|reviewer| format!("{}: {}", reviewer.login, reviewer.reviews)),
+    );
+
+    // Breaches are ordered by repository, so each one's are counted together
+    let mut breach_counts: Vec<(String, usize, usize)> = vec![];
+    for breach in SlaBreach::since(30, db)? {
+        let unreviewed = breach.reviewed_at.is_none() as usize;
+        match breach_counts.last_mut() {
+            Some((repo, total, open)) if *repo == breach.repo => {
+                *total += 1;
+                *open += unreviewed;
+            }
+            _ => breach_counts.push((breach.repo, 1, unreviewed)),
+        }
+    }
+    if !breach_counts.is_empty() {
+        lines.push("*Review SLA breaches in the last 30 days*".to_string());
+    }
+    lines.extend(breach_counts.iter().map(|(repo, total, unreviewed)| {
+        format!("{}: {} ({} still unreviewed)", repo, total, unreviewed)
+    }));
+
+    ephemeral(state, lines.join("\n"))
+}
+
+/// The median working time from a pull request being posted to its first
+/// review, over those posted in the last `days` days, counted in the channel's
+/// working hours
+fn review_turnaround(
+    channel: &str,
+    days: i64,
+    db: &DBExecutor,
+) -> Result<Option<chrono::Duration>> {
+    let working_hours = WorkingHours::for_channel(channel, db)?;
+    let prs = PullRequestModel::created_since(days, db)?;
+    let pr_ids: Vec<i32> = prs.iter().map(|pr| pr.id).collect();
+    let reviews = Review::for_pull_requests(&pr_ids, db)?;
+
+    let mut turnarounds: Vec<chrono::Duration> = prs
+        .iter()
+        .filter_map(|pr| {
+            let first_review = reviews
+                .iter()
+                .filter(|review| review.pull_request_id == pr.id)
+                .map(|review| review.created_at)
+                .min()?;
+            Some(working_hours.between(pr.created_at, first_review))
+        })
+        .collect();
+    turnarounds.sort();
+
+    Ok(turnarounds.get(turnarounds.len() / 2).copied())
+}
+
+fn snooze(
+    form: &SlackRequest,
+    pull_request: &str,
+    hours: i64,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    let default_repo = ChannelSetting::default_repo(&form.channel_id, db)?;
+    let review_request = match ReviewRequest::parse(pull_request, default_repo.as_deref()) {
+        Ok(review_request) => review_request,
+        Err(err) => return ephemeral(state, format!("Couldn't snooze {}: {}", pull_request, err)),
+    };
+
+    match PullRequestModel::lookup(&review_request.github_id(), db)? {
+        Some(tracked) => {
+            tracked.snooze(hours, db)?;
+            ephemeral(
+                state,
+                format!("Snoozed {} for {} hours", pull_request, hours),
+            )
+        }
+        None => ephemeral(state, format!("{} isn't being tracked", pull_request)),
+    }
+}
+
+fn settings(
+    form: &SlackRequest,
+    setting: SettingsCommand,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    match setting {
+        SettingsCommand::Show => (),
+        SettingsCommand::DefaultRepo(repo) => {
+            ChannelSetting::set_default_repo(&form.channel_id, repo.as_deref(), db)?;
+        }
+        SettingsCommand::ThreadActivity(enabled) => {
+            ChannelSetting::set_thread_activity(&form.channel_id, enabled, db)?;
+        }
+        SettingsCommand::MergeMethod { method, repo } => {
+            let repo = match repo.or(ChannelSetting::default_repo(&form.channel_id, db)?) {
+                Some(repo) => repo,
+                None => {
+                    return ephemeral(
+                        state,
+                        "Specify a repository, or set a default one for this channel".to_string(),
+                    )
+                }
+            };
+            RepoSetting::set_merge_method(&repo, method, db)?;
+
+            return ephemeral(state, format!("*Merge method for {}:* {}", repo, method));
+        }
+        SettingsCommand::ReviewSla {
+            hours,
+            escalation,
+            repo,
+        } => {
+            let repo = match repo.or(ChannelSetting::default_repo(&form.channel_id, db)?) {
+                Some(repo) => repo,
+                None => {
+                    return ephemeral(
+                        state,
+                        "Specify a repository, or set a default one for this channel".to_string(),
+                    )
+                }
+            };
+            let setting = RepoSetting::set_review_sla(&repo, hours, escalation.as_deref(), db)?;
+
+            return ephemeral(
+                state,
+                format!("*Review SLA for {}:* {}", repo, review_sla(&setting)),
+            );
+        }
+        SettingsCommand::DigestHour(hour) => {
+            ChannelSchedule::set_digest_hour(&form.channel_id, hour, db)?;
+        }
+        SettingsCommand::NudgeAfterHours(hours) => {
+            ChannelSchedule::set_nudge_after_hours(&form.channel_id, hours, db)?;
+        }
+        SettingsCommand::Timezone(zone) => {
+            ChannelSchedule::set_time_zone(&form.channel_id, zone, db)?;
+        }
+        SettingsCommand::Calendar(None) => {
+            ChannelSchedule::set_calendar(&form.channel_id, None, db)?;
+        }
+        SettingsCommand::Calendar(Some(name)) => match Calendar::find_by_name(&name, db)? {
+            Some(calendar) => {
+                ChannelSchedule::set_calendar(&form.channel_id, Some(calendar.id), db)?;
+            }
+            None => {
+                return ephemeral(
+                    state,
+                    format!(
+                        "There's no calendar called {}. Add one at {}/admin/calendars",
+                        name, state.app_url
+                    ),
+                )
+            }
+        },
+    };
+
+    let default_repo = ChannelSetting::default_repo(&form.channel_id, db)?;
+    let thread_activity = ChannelSetting::thread_activity(&form.channel_id, db)?;
+
+    let mut lines = vec![
+        format!(
+            "*Default repository:* {}",
+            default_repo.as_deref().unwrap_or("none")
+        ),
+        format!(
+            "*Thread activity:* {}",
+            if thread_activity { "on" } else { "off" }
+        ),
+    ];
+    if let Some(repo) = default_repo {
+        lines.push(format!(
+            "*Merge method:* {}",
+            RepoSetting::merge_method(&repo, db)?
+        ));
+        if let Some(setting) = RepoSetting::find(&repo, db)? {
+            lines.push(format!("*Review SLA:* {}", review_sla(&setting)));
+        }
+    }
+
+    let schedule = ChannelSchedule::find(&form.channel_id, db)?;
+    let digest_hour = schedule.as_ref().and_then(|schedule| schedule.digest_hour);
+    let nudge_after_hours = schedule
+        .as_ref()
+        .and_then(|schedule| schedule.nudge_after_hours);
+    lines.push(match digest_hour {
+        Some(hour) => format!("*Digest:* {:02}:00 on weekdays", hour),
+        None => "*Digest:* off".to_string(),
+    });
+    lines.push(match nudge_after_hours {
+        Some(hours) => format!("*Nudge after:* {} working hours without a review", hours),
+        None => "*Nudge after:* off".to_string(),
+    });
+    let calendar = match schedule.as_ref().and_then(|schedule| schedule.calendar_id) {
+        Some(calendar_id) => Calendar::find(calendar_id, db)?,
+        None => None,
+    };
+    match calendar {
+        Some(calendar) => lines.push(format!("*Calendar:* {}", calendar.name)),
+        None => lines.push(format!(
+            "*Time zone:* {}",
+            schedule
+                .map(|schedule| schedule.time_zone)
+                .unwrap_or_else(|| "UTC".to_string())
+        )),
+    }
+
+    ephemeral(state, lines.join("\n"))
+}
+
+fn review_sla(setting: &RepoSetting) -> String {
+    match (setting.review_sla_hours, &setting.escalation_mention) {
+        (Some(hours), Some(mention)) => format!(
+            "first review within {} working hours, escalated to {}",
+            hours, mention
+        ),
+        (Some(hours), None) => format!("first review within {} working hours", hours),
+        (None, _) => "off".to_string(),
+    }
+}
+
+fn notifications(
+    form: &SlackRequest,
+    notify: NotifyCommand,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    let user = match User::find_by_slack_id(&form.user_id, db)? {
+        Some(user) => user,
+        None => {
+            return ephemeral(
+                state,
+                format!("Sign in at {} to get direct messages", state.app_url),
+            )
+        }
+    };
+
+    let mut settings = NotificationPreference::settings(user.id, db)?;
+    match notify {
+        NotifyCommand::Show => (),
+        NotifyCommand::ReviewRequests(enabled) => settings.review_requests = enabled,
+        NotifyCommand::Reviews(enabled) => settings.reviews = enabled,
+        NotifyCommand::Merges(enabled) => settings.merges = enabled,
+        NotifyCommand::Digest(frequency) => settings.digest = frequency.to_string(),
+        NotifyCommand::QuietHours(hours) => {
+            settings.quiet_hours_start = hours.map(|(start, _)| start);
+            settings.quiet_hours_end = hours.map(|(_, end)| end);
+        }
+        NotifyCommand::Timezone(zone) => settings.time_zone = zone.name().to_string(),
+    };
+    if notify != NotifyCommand::Show {
+        NotificationPreference::save(&settings, db)?;
+    }
+
+    let on_off = |enabled| if enabled { "on" } else { "off" };
+    let quiet_hours = match (settings.quiet_hours_start, settings.quiet_hours_end) {
+        (Some(start), Some(end)) => format!(
+            "{:02}:00 to {:02}:00, direct messages then are dropped",
+            start, end
+        ),
+        _ => "off".to_string(),
+    };
+    let lines = [
+        format!("*Review requests:* {}", on_off(settings.review_requests)),
+        format!(
+            "*Reviews of your pull requests:* {}",
+            on_off(settings.reviews)
+        ),
+        format!(
+            "*Merges of your pull requests:* {}",
+            on_off(settings.merges)
+        ),
+        format!("*Digest:* {}", settings.digest),
+        format!("*Quiet hours:* {}", quiet_hours),
+        format!("*Time zone:* {}", settings.time_zone),
+        format!("Change these at {}/preferences", state.app_url),
+    ];
+
+    ephemeral(state, lines.join("\n"))
+}
+
+/// Marks the user away or back. Pull requests waiting on them are flagged
+/// when they go away so someone else can pick them up.
+async fn out_of_office(
+    form: &SlackRequest,
+    away: AwayCommand,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    let mut user = match User::find_by_slack_id(&form.user_id, db)? {
+        Some(user) => user,
+        None => {
+            return ephemeral(
+                state,
+                format!(
+                    "Sign in at {} to mark yourself out of office",
+                    state.app_url
+                ),
+            )
+        }
+    };
+    let now = chrono::Utc::now().naive_utc();
+
+    match away {
+        AwayCommand::Show => (),
+        AwayCommand::Back => user = user.set_away(&AwayStatus::back(), db)?,
+        AwayCommand::Until(date) => {
+            let settings = NotificationPreference::settings(user.id, db)?;
+            let status = away_until_date(date, settings.tz());
+            if !status.is_away(now) {
+                return ephemeral(state, format!("{} has already started", date));
+            }
+            let was_away = user.is_away(now);
+            user = user.set_away(&status, db)?;
+            if !was_away {
+                flag_for_reassignment(state, &user, db);
+            }
+        }
+    }
+
+    let text = match (user.is_away(now), back_on(&user, db)?) {
+        (true, Some(back_on)) => format!("You're out of office until {}", back_on),
+        (true, None) => "You're out of office while your Slack status says so".to_string(),
+        (false, _) => "You're not out of office".to_string(),
+    };
+    ephemeral(state, text)
+}
+
+fn ephemeral(state: &AppData, text: String) -> Result<HttpResponse> {
+    let res = state.slack.immediate_response(text)?;
+    Ok(prepare_response(&res))
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum SlackEventWrapper {
     UrlVerification { token: String, challenge: String },
+    EventCallback { event: SlackEvent },
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -99,10 +613,49 @@ pub enum SlackEvent {
         channel: String,
         user: Option<String>,
         subtype: Option<String>,
+        bot_id: Option<String>,
         attachments: Option<Vec<attachment::Attachment>>,
+        #[serde(default)]
         text: String,
         ts: String,
+        thread_ts: Option<String>,
+    },
+    AppHomeOpened {
+        user: String,
+        tab: Option<String>,
     },
+    LinkShared {
+        channel: String,
+        user: String,
+        message_ts: String,
+        links: Vec<SharedLink>,
+        source: Option<String>,
+    },
+    UserChange {
+        user: ChangedUser,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SharedLink {
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChangedUser {
+    pub id: String,
+    pub profile: ChangedProfile,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChangedProfile {
+    #[serde(default)]
+    pub status_emoji: String,
+    /// A Unix timestamp, 0 when the status doesn't expire
+    #[serde(default)]
+    pub status_expiration: i64,
 }
 
 #[derive(Serialize, Debug)]
@@ -110,11 +663,23 @@ pub struct UrlVerification {
     challenge: String,
 }
 
-pub async fn message(json: Json<SlackEventWrapper>) -> Result<HttpResponse> {
+pub async fn message(
+    req: HttpRequest,
+    json: Json<SlackEventWrapper>,
+    state: AppData,
+    db: Data<DBExecutor>,
+) -> Result<HttpResponse> {
     let Json(event_wrapper) = json;
 
+    // Events are answered before they're handled, so Slack only retries one
+    // when that answer was lost and the event may already have been handled
+    if req.headers().contains_key("X-Slack-Retry-Num") {
+        return Err(Error::GuardError("Retried event"));
+    }
+
     match event_wrapper {
         SlackEventWrapper::UrlVerification { challenge, .. } => handle_url_verification(challenge),
+        SlackEventWrapper::EventCallback { event } => handle_event(event, &state, &db).await,
     }
 }
 
@@ -123,3 +688,254 @@ fn handle_url_verification(challenge: String) -> Result<HttpResponse> {
 
     Ok(prepare_response(&res))
 }
+
+async fn handle_event(event: SlackEvent, state: &AppData, db: &DBExecutor) -> Result<HttpResponse> {
+    match event {
+        SlackEvent::Message {
+            subtype: None,
+            bot_id: None,
+            user: Some(user),
+            channel,
+            text,
+            ts,
+            thread_ts: Some(thread_ts),
+            ..
+        } if thread_ts != ts => {
+            let (state, db) = (state.background(), db.clone());
+            in_background("thread reply", async move {
+                handle_thread_reply(&channel, &user, &text, &ts, &thread_ts, &state, &db).await
+            })
+        }
+        // Links in a message still being written can't be unfurled by channel
+        // and timestamp
+        SlackEvent::LinkShared { source, .. } if source.as_deref() == Some("composer") => {
+            Err(Error::GuardError("Link shared in composer"))
+        }
+        SlackEvent::LinkShared {
+            channel,
+            user,
+            message_ts,
+            links,
+            ..
+        } => {
+            let (state, db) = (state.background(), db.clone());
+            in_background("shared link", async move {
+                handle_link_shared(&channel, &user, &message_ts, &links, &state, &db).await
+            })
+        }
+        SlackEvent::AppHomeOpened { user, tab } if tab.as_deref() != Some("messages") => {
+            publish_home(&user, state, db).await
+        }
+        SlackEvent::UserChange { user } => handle_user_change(&user, state, db).await,
+        _ => Err(Error::GuardError("Unhandled event")),
+    }
+}
+
+/// Answers Slack straight away and handles the event afterwards, since
+/// Github can take longer than the 3 seconds Slack waits before resending it
+fn in_background<F>(event: &'static str, handling: F) -> Result<HttpResponse>
+where
+    F: Future<Output = Result<()>> + 'static,
+{
+    actix_rt::spawn(async move {
+        match handling.await {
+            Ok(()) => {}
+            Err(Error::GuardError(reason)) => log::info!("Skipped {}: {}", event, reason),
+            Err(err) => log::warn!("Couldn't handle {}: {}", event, err),
+        }
+    });
+    Ok(prepare_response(""))
+}
+
+/// Posts a reply in a pull request's thread as a Github comment by the same
+/// person. Replies the bot mirrored from Github are recorded, which keeps
+/// them from going back.
+async fn handle_thread_reply(
+    channel: &str,
+    slack_user_id: &str,
+    text: &str,
+    ts: &str,
+    thread_ts: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<()> {
+    let db_pr = PullRequestModel::find_by_message(channel, thread_ts, db)?
+        .ok_or(Error::GuardError("Not a pull request thread"))?;
+    // Every pull request in a dependency digest shares its message
+    if db_pr.digest_id.is_some() {
+        return Err(Error::GuardError("Dependency digest thread"));
+    }
+    if CommentMirror::find_by_slack_ts(&db_pr, ts, db)?.is_some() {
+        return Err(Error::GuardError("Reply already on Github"));
+    }
+    // A comment being mirrored can be posted before its timestamp is saved
+    if CommentMirror::any_pending(&db_pr, db)? {
+        return Err(Error::GuardError("Reply may be a mirrored comment"));
+    }
+
+    let token = match User::find_by_slack_id(slack_user_id, db)?
+        .and_then(|user| user.github_access_token)
+    {
+        Some(token) => token,
+        None => {
+            state
+                .slack
+                .post_ephemeral(
+                    channel,
+                    slack_user_id,
+                    &format!(
+                        "Connect your Github account at {} to have replies here posted on the pull request",
+                        state.app_url
+                    ),
+                    Some(thread_ts),
+                )
+                .await?;
+            return Ok(());
+        }
+    };
+    let review_request = db_pr.review_request().ok_or(Error::NotFoundError)?;
+
+    let body = mrkdwn::to_markdown(text, |id| github_mention(id, db));
+    let comment = state
+        .github
+        .create_issue_comment(&review_request, &NewComment::from_slack(&body), &token)
+        .await?;
+    CommentMirror::create(&db_pr, comment.id, ts, db)?;
+
+    Ok(())
+}
+
+/// Marks the user away or back when they set or clear an out of office Slack
+/// status
+async fn handle_user_change(
+    changed: &ChangedUser,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    let user = User::find_by_slack_id(&changed.id, db)?
+        .ok_or(Error::GuardError("Status change for an unknown user"))?;
+    let now = chrono::Utc::now().naive_utc();
+    let status = status_change(
+        &user.away_status(),
+        &changed.profile.status_emoji,
+        changed.profile.status_expiration,
+        now,
+    )
+    .ok_or(Error::GuardError("Away status unchanged"))?;
+
+    let was_away = user.is_away(now);
+    let user = user.set_away(&status, db)?;
+    if !was_away && user.is_away(now) {
+        flag_for_reassignment(state, &user, db);
+    }
+
+    Ok(prepare_response(""))
+}
+
+/// Publishes the user's Home tab with the pull requests waiting on their
+/// review, claimed ones first, and the open pull requests they've authored
+async fn publish_home(
+    slack_user_id: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<HttpResponse> {
+    let user = match User::find_by_slack_id(slack_user_id, db)? {
+        Some(user) if user.is_gh_authed() => user,
+        _ => {
+            state
+                .slack
+                .publish_view(slack_user_id, &View::home_signed_out(&state.app_url))
+                .await?;
+            return Ok(prepare_response(""));
+        }
+    };
+    let queue = ReviewQueue::for_user(&user, db)?;
+
+    let view = View::home(
+        &queue.to_review_entries(),
+        &queue.authored_entries(),
+        &state.app_url,
+        chrono::Utc::now().naive_utc(),
+    );
+    state.slack.publish_view(slack_user_id, &view).await?;
+    Ok(prepare_response(""))
+}
+
+/// Unfurls pasted pull request links using the Github account of whoever
+/// pasted them. Links that can't be fetched keep Slack's default preview.
+async fn handle_link_shared(
+    channel: &str,
+    slack_user_id: &str,
+    message_ts: &str,
+    links: &[SharedLink],
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<()> {
+    let token = User::find_by_slack_id(slack_user_id, db)?
+        .and_then(|user| user.github_access_token)
+        .ok_or(Error::GuardError("Link shared by a user without Github"))?;
+
+    let mut pull_requests = vec![];
+    for link in links {
+        if let Ok(review_request) = ReviewRequest::parse(&link.url, None) {
+            match state.github.get_pr(&review_request, &token).await {
+                Ok(pull_request) if may_unfurl(&pull_request, db)? => {
+                    pull_requests.push((link.url.clone(), pull_request))
+                }
+                Ok(_) => log::info!("Not unfurling {} from a private repository", link.url),
+                Err(err) => log::warn!("Couldn't unfurl {}: {}", link.url, err),
+            }
+        }
+    }
+    if pull_requests.is_empty() {
+        return Err(Error::GuardError("No pull requests to unfurl"));
+    }
+
+    let mut unfurls = vec![];
+    for (link, pull_request) in &pull_requests {
+        let pr_files = PRFiles::new(pull_request, &state.github, Some(token.clone())).await;
+        let tracked = PullRequestModel::lookup(
+            &github_id(&pull_request.base.repo.full_name, pull_request.number),
+            db,
+        )?;
+        let context = match &tracked {
+            Some(tracked) => message_context(tracked, db)?,
+            None => MessageContext {
+                author: GithubUser::slack_user_id(pull_request.user.id, db)?,
+                ..MessageContext::default()
+            },
+        };
+
+        unfurls.push(Unfurl {
+            link: link.to_string(),
+            pull_request,
+            files: IconMapping::from(pr_files.filenames, pr_files.extensions, db)?,
+            context,
+            tracked: tracked.is_some(),
+        });
+    }
+
+    state
+        .slack
+        .unfurl(channel, message_ts, unfurls, &state.app_url)
+        .await?;
+    Ok(())
+}
+
+/// Private repositories are only unfurled once the bot posts their pull
+/// requests, so sharing a link can't show one to people without access
+fn may_unfurl(pull_request: &PRResult, db: &DBExecutor) -> Result<bool> {
+    let repo = &pull_request.base.repo;
+    Ok(!repo.private || PullRequestModel::repo_tracked(&repo.full_name, db)?)
+}
+
+/// The Github login of a mentioned Slack user, when they've linked one
+fn github_mention(slack_user_id: &str, db: &DBExecutor) -> Option<String> {
+    let user = User::find_by_slack_id(slack_user_id, db).ok()??;
+
+    GithubUser::for_user(user.id, db)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|github_user| format!("@{}", github_user.login))
+}
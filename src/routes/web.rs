@@ -9,25 +9,26 @@ use std::fmt;
 
 use crate::db::DBExecutor;
 use crate::error::{self, Result};
-use crate::models::{Config, User};
+use crate::models::{Config, NotificationPreference, NotificationSettings, User};
 use crate::utils::helpers::{get_current_user, sign_out_current_user};
+use crate::utils::notifications::{parse_time_zone, DigestFrequency};
 use crate::{AppConfig, AppData};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Flash {
-    message_type: FlashType,
-    message: String,
+    pub(crate) message_type: FlashType,
+    pub(crate) message: String,
 }
 
 impl Flash {
-    fn info(message: &str) -> Self {
+    pub(crate) fn info(message: &str) -> Self {
         Self {
             message_type: FlashType::Info,
             message: message.to_owned(),
         }
     }
 
-    fn err(message: &str) -> Self {
+    pub(crate) fn err(message: &str) -> Self {
         Self {
             message_type: FlashType::Error,
             message: message.to_owned(),
@@ -73,10 +74,10 @@ struct IndexTemplate<'a> {
     info: &'a Info<'a>,
 }
 
-struct Info<'a> {
-    client_id: &'a str,
-    gh_client_id: &'a str,
-    current_user: &'a Option<User>,
+pub(crate) struct Info<'a> {
+    pub(crate) client_id: &'a str,
+    pub(crate) gh_client_id: &'a str,
+    pub(crate) current_user: &'a Option<User>,
 }
 
 pub async fn root(
@@ -111,6 +112,150 @@ pub async fn root(
     Ok(build_response(rendered_template))
 }
 
+#[derive(Template)]
+#[template(path = "home/preferences.html")]
+struct PreferencesTemplate<'a> {
+    flash: &'a Option<Flash>,
+    info: &'a Info<'a>,
+    settings: &'a NotificationSettings,
+    digests: Vec<SelectOption>,
+    quiet_hours_starts: Vec<SelectOption>,
+    quiet_hours_ends: Vec<SelectOption>,
+}
+
+struct SelectOption {
+    value: String,
+    label: String,
+    selected: bool,
+}
+
+/// The hours of the day to pick quiet hours from, led by an option to have none
+fn hour_options(selected: Option<i32>) -> Vec<SelectOption> {
+    let mut options = vec![SelectOption {
+        value: "".to_string(),
+        label: "Off".to_string(),
+        selected: selected.is_none(),
+    }];
+    options.extend((0..24).map(|hour| SelectOption {
+        value: hour.to_string(),
+        label: format!("{:02}:00", hour),
+        selected: selected == Some(hour),
+    }));
+
+    options
+}
+
+pub async fn preferences(
+    state: AppData,
+    db: Data<DBExecutor>,
+    session: Session,
+    flash_message: Option<FlashMessage<Flash>>,
+) -> Result<HttpResponse> {
+    let flash = flash_message.map(|flash| flash.into_inner());
+    let current_user = get_current_user(&db, &session)?;
+    let user_id = current_user
+        .as_ref()
+        .map(|user| user.id)
+        .ok_or(error::Error::NotAuthedError)?;
+
+    let info = Info {
+        client_id: &state.slack.client_id,
+        gh_client_id: &state.github_oauth.client_id,
+        current_user: &current_user,
+    };
+    let settings = NotificationPreference::settings(user_id, &db)?;
+    let digests = [
+        DigestFrequency::Off,
+        DigestFrequency::Daily,
+        DigestFrequency::Weekly,
+    ]
+    .iter()
+    .map(|frequency| SelectOption {
+        value: frequency.to_string(),
+        label: frequency.to_string(),
+        selected: frequency.to_string() == settings.digest,
+    })
+    .collect();
+
+    Ok(build_response(
+        PreferencesTemplate {
+            flash: &flash,
+            info: &info,
+            settings: &settings,
+            digests,
+            quiet_hours_starts: hour_options(settings.quiet_hours_start),
+            quiet_hours_ends: hour_options(settings.quiet_hours_end),
+        }
+        .render()?,
+    ))
+}
+
+/// Unchecked checkboxes aren't submitted, so those fields are missing rather
+/// than false
+#[derive(Deserialize)]
+pub struct PreferencesData {
+    review_requests: Option<String>,
+    reviews: Option<String>,
+    merges: Option<String>,
+    digest: String,
+    quiet_hours_start: String,
+    quiet_hours_end: String,
+    time_zone: String,
+}
+
+pub async fn update_preferences(
+    form: Form<PreferencesData>,
+    db: Data<DBExecutor>,
+    session: Session,
+) -> Result<FlashResponse<HttpResponse, Flash>> {
+    let user = get_current_user(&db, &session)?.ok_or(error::Error::NotAuthedError)?;
+
+    let digest: DigestFrequency = match form.digest.parse() {
+        Ok(digest) => digest,
+        Err(err) => {
+            return Ok(FlashResponse::with_redirect(
+                Flash::err(&err),
+                "/preferences",
+            ))
+        }
+    };
+    let time_zone = match parse_time_zone(&form.time_zone) {
+        Some(zone) => zone,
+        None => {
+            return Ok(FlashResponse::with_redirect(
+                Flash::err(&format!(
+                    "`{}` isn't a time zone like America/New_York",
+                    form.time_zone
+                )),
+                "/preferences",
+            ))
+        }
+    };
+    // Quiet hours need both ends, so half a range turns them off
+    let (quiet_hours_start, quiet_hours_end) =
+        match (form.quiet_hours_start.parse(), form.quiet_hours_end.parse()) {
+            (Ok(start), Ok(end)) if start != end => (Some(start), Some(end)),
+            _ => (None, None),
+        };
+
+    let settings = NotificationSettings {
+        user_id: user.id,
+        review_requests: form.review_requests.is_some(),
+        reviews: form.reviews.is_some(),
+        merges: form.merges.is_some(),
+        digest: digest.to_string(),
+        quiet_hours_start,
+        quiet_hours_end,
+        time_zone: time_zone.name().to_string(),
+    };
+    let result = NotificationPreference::save(&settings, &db);
+
+    Ok(FlashResponse::with_redirect(
+        Flash::from_result(result, "Preferences saved"),
+        "/preferences",
+    ))
+}
+
 #[derive(Template)]
 #[template(path = "setup/new.html")]
 struct NewSetup;
@@ -123,6 +268,7 @@ pub async fn new_setup() -> Result<HttpResponse> {
 pub struct SetupData {
     slack_client_id: String,
     slack_client_secret: String,
+    slack_signing_secret: String,
     slack_token: String,
     slack_channel: String,
     github_client_id: String,
@@ -142,6 +288,7 @@ pub async fn create_setup(
         .slack(
             &form.slack_client_id,
             &form.slack_client_secret,
+            &form.slack_signing_secret,
             &form.slack_channel,
             &form.slack_token,
         )
@@ -152,6 +299,7 @@ pub async fn create_setup(
         &[
             Config::new("slack_client_id", &form.slack_client_id),
             Config::new("slack_client_secret", &form.slack_client_secret),
+            Config::new("slack_signing_secret", &form.slack_signing_secret),
             Config::new("slack_channel", &form.slack_channel),
             Config::new("slack_token", &form.slack_token),
             Config::new("github_client_id", &form.github_client_id),
@@ -186,6 +334,6 @@ pub async fn logout(
     Ok(FlashResponse::with_redirect(Flash::info("Signed Out"), "/"))
 }
 
-fn build_response(body: String) -> HttpResponse {
+pub(crate) fn build_response(body: String) -> HttpResponse {
     HttpResponse::Ok().content_type("text/html").body(body)
 }
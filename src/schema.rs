@@ -1,3 +1,50 @@
+table! {
+    calendars (id) {
+        id -> Int4,
+        name -> Varchar,
+        time_zone -> Varchar,
+        working_days -> Array<Int4>,
+        start_hour -> Int4,
+        end_hour -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    channel_schedules (channel_id) {
+        channel_id -> Varchar,
+        digest_hour -> Nullable<Int4>,
+        nudge_after_hours -> Nullable<Int4>,
+        time_zone -> Varchar,
+        last_digest_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        calendar_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    channel_settings (channel_id) {
+        channel_id -> Varchar,
+        default_repo -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        thread_activity -> Bool,
+    }
+}
+
+table! {
+    comment_mirrors (id) {
+        id -> Int4,
+        pull_request_id -> Int4,
+        github_comment_id -> Int8,
+        slack_ts -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     configs (key) {
         key -> Varchar,
@@ -5,6 +52,29 @@ table! {
     }
 }
 
+table! {
+    dependency_digests (id) {
+        id -> Int4,
+        repo -> Varchar,
+        channel -> Varchar,
+        slack_message_id -> Varchar,
+        open -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    event_logs (id) {
+        id -> Int4,
+        event -> Varchar,
+        pull_request_url -> Nullable<Varchar>,
+        message -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     file_extensions (id) {
         id -> Int4,
@@ -33,6 +103,17 @@ table! {
     }
 }
 
+table! {
+    holidays (id) {
+        id -> Int4,
+        calendar_id -> Int4,
+        date -> Date,
+        name -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     icon_mappings (id) {
         id -> Int4,
@@ -41,6 +122,33 @@ table! {
     }
 }
 
+table! {
+    ignore_rules (id) {
+        id -> Int4,
+        kind -> Varchar,
+        pattern -> Varchar,
+        repo -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    notification_preferences (user_id) {
+        user_id -> Int4,
+        review_requests -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        reviews -> Bool,
+        merges -> Bool,
+        digest -> Varchar,
+        quiet_hours_start -> Nullable<Int4>,
+        quiet_hours_end -> Nullable<Int4>,
+        time_zone -> Varchar,
+        last_digest_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     pull_requests (id) {
         id -> Int4,
@@ -52,6 +160,62 @@ table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         github_user_id -> Int4,
+        snoozed_until -> Nullable<Timestamp>,
+        title -> Varchar,
+        html_url -> Varchar,
+        additions -> Int4,
+        deletions -> Int4,
+        labels -> Array<Text>,
+        muted -> Bool,
+        last_nudged_at -> Nullable<Timestamp>,
+        digest_id -> Nullable<Int4>,
+        merged -> Bool,
+        host -> Varchar,
+        repo -> Varchar,
+    }
+}
+
+table! {
+    repo_settings (repo) {
+        repo -> Varchar,
+        merge_method -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        review_sla_hours -> Nullable<Int4>,
+        escalation_mention -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    review_claims (id) {
+        id -> Int4,
+        pull_request_id -> Int4,
+        user_id -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    review_request_messages (id) {
+        id -> Int4,
+        pull_request_github_id -> Varchar,
+        reviewer_github_id -> Int4,
+        channel -> Varchar,
+        slack_ts -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    review_views (id) {
+        id -> Int4,
+        view_id -> Varchar,
+        slack_user_id -> Varchar,
+        pull_request_id -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -66,6 +230,28 @@ table! {
     }
 }
 
+table! {
+    scheduler_locks (name) {
+        name -> Varchar,
+        holder -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    sla_breaches (id) {
+        id -> Int4,
+        pull_request_id -> Int4,
+        repo -> Varchar,
+        sla_hours -> Int4,
+        reviewed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -75,6 +261,9 @@ table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         github_access_token -> Nullable<Varchar>,
+        away_until -> Nullable<Timestamp>,
+        away_from_status -> Bool,
+        admin -> Bool,
     }
 }
 
@@ -87,17 +276,36 @@ table! {
     }
 }
 
+joinable!(channel_schedules -> calendars (calendar_id));
 joinable!(file_extensions -> icon_mappings (icon_mapping_id));
 joinable!(file_names -> icon_mappings (icon_mapping_id));
+joinable!(github_users -> users (user_id));
+joinable!(holidays -> calendars (calendar_id));
+joinable!(review_claims -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    calendars,
+    channel_schedules,
+    channel_settings,
+    comment_mirrors,
     configs,
+    dependency_digests,
+    event_logs,
     file_extensions,
     file_names,
     github_users,
+    holidays,
     icon_mappings,
+    ignore_rules,
+    notification_preferences,
     pull_requests,
+    repo_settings,
+    review_claims,
+    review_request_messages,
+    review_views,
     reviews,
+    scheduler_locks,
+    sla_breaches,
     users,
     webhooks,
 );
@@ -0,0 +1,471 @@
+use std::fmt;
+
+use super::{
+    Accessory, ActionElement, Actions, Block, Button, Context, Elements, Image, ImageBlock,
+    OptionObject, Overflow, Section, Text,
+};
+
+/// Slack rejects messages with more blocks than this
+pub const MAX_BLOCKS: usize = 50;
+const MAX_TEXT_LENGTH: usize = 3000;
+const MAX_FIELDS: usize = 10;
+const MAX_FIELD_LENGTH: usize = 2000;
+const MAX_HEADER_LENGTH: usize = 150;
+const MAX_LABEL_LENGTH: usize = 2000;
+const MAX_CONTEXT_ELEMENTS: usize = 10;
+const MAX_ACTION_ELEMENTS: usize = 25;
+const MAX_BUTTON_TEXT_LENGTH: usize = 75;
+const MAX_BUTTON_VALUE_LENGTH: usize = 2000;
+const MIN_OVERFLOW_OPTIONS: usize = 2;
+const MAX_OVERFLOW_OPTIONS: usize = 5;
+const MAX_OPTION_TEXT_LENGTH: usize = 75;
+const MAX_OPTION_VALUE_LENGTH: usize = 150;
+const MAX_ALT_TEXT_LENGTH: usize = 2000;
+const MAX_URL_LENGTH: usize = 3000;
+
+#[derive(Debug, PartialEq)]
+pub struct LimitError(String);
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Checks a message's blocks against Slack's limits, reporting the first one
+/// that would get the message rejected with `invalid_blocks`
+pub fn validate(blocks: &[Block]) -> Result<(), LimitError> {
+    check_count("message", "blocks", blocks.len(), 1, MAX_BLOCKS)?;
+
+    blocks.iter().try_for_each(Block::validate)
+}
+
+/// Trims whatever is over Slack's limits so the message is still accepted:
+/// text is shortened, extra elements are summarised or dropped, and blocks
+/// that can't be made valid are left out
+pub fn fit(blocks: Vec<Block>) -> Vec<Block> {
+    match validate(&blocks) {
+        Ok(()) => return blocks,
+        Err(err) => log::warn!("Fitting message to Slack's limits: {}", err),
+    }
+
+    let mut blocks: Vec<Block> = blocks.into_iter().filter_map(Block::fit).collect();
+    if blocks.len() > MAX_BLOCKS {
+        let hidden = blocks.len() - (MAX_BLOCKS - 1);
+        blocks.truncate(MAX_BLOCKS - 1);
+        blocks.push(Block::Context(Context::new(vec![Elements::Text(
+            Text::new(&format!("and {} more not shown", hidden)),
+        )])));
+    }
+
+    blocks
+}
+
+impl Block {
+    pub fn validate(&self) -> Result<(), LimitError> {
+        match self {
+            Block::Actions(actions) => actions.validate(),
+            Block::Context(context) => context.validate(),
+            Block::Divider | Block::RichText(_) => Ok(()),
+            Block::Header(header) => check_length("header", &header.text.text, MAX_HEADER_LENGTH),
+            Block::Image(image) => image.validate(),
+            Block::Input(input) => check_length("input label", &input.label.text, MAX_LABEL_LENGTH),
+            Block::Section(section) => section.validate(),
+        }
+    }
+
+    fn fit(self) -> Option<Block> {
+        match self {
+            Block::Actions(actions) => actions.fit().map(Block::Actions),
+            Block::Context(context) => context.fit().map(Block::Context),
+            Block::Header(mut header) => {
+                header.text.text = truncate(&header.text.text, MAX_HEADER_LENGTH);
+                Some(Block::Header(header))
+            }
+            Block::Image(image) => image.fit().map(Block::Image),
+            Block::Input(mut input) => {
+                input.label.text = truncate(&input.label.text, MAX_LABEL_LENGTH);
+                Some(Block::Input(input))
+            }
+            Block::Section(section) => section.fit().map(Block::Section),
+            block @ Block::Divider | block @ Block::RichText(_) => Some(block),
+        }
+    }
+}
+
+impl Section {
+    fn validate(&self) -> Result<(), LimitError> {
+        if self.text.is_none() && self.fields.is_empty() {
+            return Err(LimitError("section has no text or fields".to_string()));
+        }
+        if let Some(text) = &self.text {
+            check_length("section text", &text.text, MAX_TEXT_LENGTH)?;
+        }
+        check_count("section", "fields", self.fields.len(), 0, MAX_FIELDS)?;
+        for field in &self.fields {
+            check_length("section field", &field.text, MAX_FIELD_LENGTH)?;
+        }
+
+        match &self.accessory {
+            Some(Accessory::Image(image)) => image.validate(),
+            Some(Accessory::Button(button)) => button.validate(),
+            Some(Accessory::Overflow(overflow)) => overflow.validate(),
+            None => Ok(()),
+        }
+    }
+
+    fn fit(mut self) -> Option<Section> {
+        if let Some(text) = &mut self.text {
+            text.text = truncate(&text.text, MAX_TEXT_LENGTH);
+        }
+        self.fields.truncate(MAX_FIELDS);
+        for field in &mut self.fields {
+            field.text = truncate(&field.text, MAX_FIELD_LENGTH);
+        }
+        self.accessory = match self.accessory {
+            Some(Accessory::Image(image)) => image.fit().map(Accessory::Image),
+            Some(Accessory::Button(button)) => button.fit().map(Accessory::Button),
+            Some(Accessory::Overflow(overflow)) => overflow.fit().map(Accessory::Overflow),
+            None => None,
+        };
+
+        if self.text.is_none() && self.fields.is_empty() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+impl Context {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_count(
+            "context",
+            "elements",
+            self.elements.len(),
+            1,
+            MAX_CONTEXT_ELEMENTS,
+        )?;
+
+        self.elements.iter().try_for_each(|element| match element {
+            Elements::Text(text) => check_length("context text", &text.text, MAX_TEXT_LENGTH),
+            Elements::Image(image) => image.validate(),
+        })
+    }
+
+    /// Elements past the limit are replaced with a count of how many were left out
+    fn fit(self) -> Option<Context> {
+        let mut elements: Vec<Elements> = self
+            .elements
+            .into_iter()
+            .filter_map(|element| match element {
+                Elements::Text(mut text) => {
+                    text.text = truncate(&text.text, MAX_TEXT_LENGTH);
+                    Some(Elements::Text(text))
+                }
+                Elements::Image(image) => image.fit().map(Elements::Image),
+            })
+            .collect();
+
+        if elements.len() > MAX_CONTEXT_ELEMENTS {
+            let hidden = elements.len() - (MAX_CONTEXT_ELEMENTS - 1);
+            elements.truncate(MAX_CONTEXT_ELEMENTS - 1);
+            elements.push(Elements::Text(Text::new(&format!("+{} more", hidden))));
+        }
+
+        if elements.is_empty() {
+            None
+        } else {
+            Some(Context::new(elements))
+        }
+    }
+}
+
+impl Actions {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_count(
+            "actions",
+            "elements",
+            self.elements.len(),
+            1,
+            MAX_ACTION_ELEMENTS,
+        )?;
+
+        self.elements.iter().try_for_each(|element| match element {
+            ActionElement::Button(button) => button.validate(),
+            ActionElement::Overflow(overflow) => overflow.validate(),
+        })
+    }
+
+    fn fit(self) -> Option<Actions> {
+        let mut elements: Vec<ActionElement> = self
+            .elements
+            .into_iter()
+            .filter_map(|element| match element {
+                ActionElement::Button(button) => button.fit().map(ActionElement::Button),
+                ActionElement::Overflow(overflow) => overflow.fit().map(ActionElement::Overflow),
+            })
+            .collect();
+        elements.truncate(MAX_ACTION_ELEMENTS);
+
+        if elements.is_empty() {
+            None
+        } else {
+            Some(Actions::new(elements))
+        }
+    }
+}
+
+impl Button {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_length("button text", &self.text.text, MAX_BUTTON_TEXT_LENGTH)?;
+        if let Some(value) = &self.value {
+            check_length("button value", value, MAX_BUTTON_VALUE_LENGTH)?;
+        }
+        if let Some(url) = &self.url {
+            check_length("button url", url, MAX_URL_LENGTH)?;
+        }
+        Ok(())
+    }
+
+    /// A value or url that's too long can't be shortened without breaking the
+    /// button, so the button is dropped instead
+    fn fit(mut self) -> Option<Button> {
+        self.text.text = truncate(&self.text.text, MAX_BUTTON_TEXT_LENGTH);
+
+        let value_fits = self
+            .value
+            .as_ref()
+            .map(|value| value.chars().count() <= MAX_BUTTON_VALUE_LENGTH)
+            .unwrap_or(true);
+        let url_fits = self
+            .url
+            .as_ref()
+            .map(|url| url.chars().count() <= MAX_URL_LENGTH)
+            .unwrap_or(true);
+
+        if value_fits && url_fits {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl Overflow {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_count(
+            "overflow",
+            "options",
+            self.options.len(),
+            MIN_OVERFLOW_OPTIONS,
+            MAX_OVERFLOW_OPTIONS,
+        )?;
+
+        self.options.iter().try_for_each(OptionObject::validate)
+    }
+
+    fn fit(mut self) -> Option<Overflow> {
+        self.options = self
+            .options
+            .into_iter()
+            .filter_map(OptionObject::fit)
+            .take(MAX_OVERFLOW_OPTIONS)
+            .collect();
+
+        if self.options.len() < MIN_OVERFLOW_OPTIONS {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+impl OptionObject {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_length("option text", &self.text.text, MAX_OPTION_TEXT_LENGTH)?;
+        check_length("option value", &self.value, MAX_OPTION_VALUE_LENGTH)
+    }
+
+    fn fit(mut self) -> Option<OptionObject> {
+        if self.value.chars().count() > MAX_OPTION_VALUE_LENGTH {
+            return None;
+        }
+
+        self.text.text = truncate(&self.text.text, MAX_OPTION_TEXT_LENGTH);
+        Some(self)
+    }
+}
+
+impl Image {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_length("image alt text", &self.alt_text, MAX_ALT_TEXT_LENGTH)?;
+        check_length("image url", &self.image_url, MAX_URL_LENGTH)
+    }
+
+    fn fit(mut self) -> Option<Image> {
+        if self.image_url.chars().count() > MAX_URL_LENGTH {
+            return None;
+        }
+
+        self.alt_text = truncate(&self.alt_text, MAX_ALT_TEXT_LENGTH);
+        Some(self)
+    }
+}
+
+impl ImageBlock {
+    fn validate(&self) -> Result<(), LimitError> {
+        check_length("image alt text", &self.alt_text, MAX_ALT_TEXT_LENGTH)?;
+        check_length("image url", &self.image_url, MAX_URL_LENGTH)?;
+        if let Some(title) = &self.title {
+            check_length("image title", &title.text, MAX_ALT_TEXT_LENGTH)?;
+        }
+        Ok(())
+    }
+
+    fn fit(mut self) -> Option<ImageBlock> {
+        if self.image_url.chars().count() > MAX_URL_LENGTH {
+            return None;
+        }
+
+        self.alt_text = truncate(&self.alt_text, MAX_ALT_TEXT_LENGTH);
+        if let Some(title) = &mut self.title {
+            title.text = truncate(&title.text, MAX_ALT_TEXT_LENGTH);
+        }
+        Some(self)
+    }
+}
+
+fn check_length(what: &str, text: &str, max: usize) -> Result<(), LimitError> {
+    let length = text.chars().count();
+
+    if length > max {
+        Err(LimitError(format!(
+            "{} is {} characters, the limit is {}",
+            what, length, max
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_count(
+    what: &str,
+    items: &str,
+    count: usize,
+    min: usize,
+    max: usize,
+) -> Result<(), LimitError> {
+    if count < min || count > max {
+        Err(LimitError(format!(
+            "{} has {} {}, it needs between {} and {}",
+            what, count, items, min, max
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Shortens text to `max` characters, marking the cut with an ellipsis
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_owned()
+    } else {
+        let mut truncated: String = text.chars().take(max - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::slack::blocks::Header;
+
+    fn context_text(block: &Block) -> Vec<String> {
+        match block {
+            Block::Context(context) => context
+                .elements
+                .iter()
+                .filter_map(|element| match element {
+                    Elements::Text(text) => Some(text.text.clone()),
+                    Elements::Image(_) => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_long_header() {
+        let blocks = vec![Block::Header(Header::new(&"a".repeat(151)))];
+
+        assert_eq!(
+            validate(&blocks),
+            Err(LimitError(
+                "header is 151 characters, the limit is 150".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_too_many_fields() {
+        let fields: Vec<String> = (0..11).map(|i| i.to_string()).collect();
+        let fields: Vec<&str> = fields.iter().map(|field| field.as_str()).collect();
+
+        assert!(validate(&[Block::Section(Section::fields(&fields))]).is_err());
+        assert!(validate(&[Block::Section(Section::fields(&fields[..10]))]).is_ok());
+    }
+
+    #[test]
+    fn test_fit_truncates_text() {
+        let blocks = fit(vec![Block::Section(Section::new(&"a".repeat(3001)))]);
+
+        match &blocks[0] {
+            Block::Section(section) => {
+                let text = &section.text.as_ref().unwrap().text;
+                assert_eq!(text.chars().count(), 3000);
+                assert!(text.ends_with('…'));
+            }
+            _ => panic!("Expected a section"),
+        }
+        assert!(validate(&blocks).is_ok());
+    }
+
+    #[test]
+    fn test_fit_summarises_extra_context_elements() {
+        let elements = (0..12)
+            .map(|i| Elements::Image(Image::new("icon", &format!("https://example.com/{}", i))))
+            .collect();
+        let blocks = fit(vec![Block::Context(Context::new(elements))]);
+
+        assert!(validate(&blocks).is_ok());
+        assert_eq!(context_text(&blocks[0]), vec!["+3 more".to_string()]);
+    }
+
+    #[test]
+    fn test_fit_drops_overflow_without_enough_options() {
+        let overflow = Overflow::new("more", vec![OptionObject::new("Only", "only")]);
+        let blocks = fit(vec![
+            Block::Actions(Actions::new(vec![
+                Button::new("Review", "review", "1").into(),
+                overflow.into(),
+            ])),
+            Block::Actions(Actions::new(vec![Overflow::new("more", vec![]).into()])),
+        ]);
+
+        assert_eq!(blocks.len(), 1);
+        assert!(validate(&blocks).is_ok());
+    }
+
+    #[test]
+    fn test_fit_limits_block_count() {
+        let blocks = fit((0..60).map(|_| Block::Divider).collect());
+        assert!(validate(&blocks).is_ok());
+        assert_eq!(blocks.len(), MAX_BLOCKS);
+        assert_eq!(
+            context_text(&blocks[MAX_BLOCKS - 1]),
+            vec!["and 11 more not shown".to_string()]
+        );
+    }
+}
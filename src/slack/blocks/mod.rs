@@ -0,0 +1,1010 @@
+mod limits;
+
+use chrono::NaiveDateTime;
+
+use crate::github;
+use crate::models;
+use crate::slack::mrkdwn;
+
+pub use limits::{fit, MAX_BLOCKS};
+
+pub const CLAIM_REVIEW_ACTION: &str = "claim_review";
+pub const NUDGE_REVIEWERS_ACTION: &str = "nudge_reviewers";
+pub const MUTE_ACTION: &str = "mute";
+pub const REVIEW_ACTION: &str = "review";
+pub const MERGE_ACTION: &str = "merge";
+pub const TRACK_ACTION: &str = "track";
+pub const APPROVE_ALL_ACTION: &str = "approve_all";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "lowercase")]
+pub enum Block {
+    Actions(Actions),
+    Context(Context),
+    Divider,
+    Header(Header),
+    Image(ImageBlock),
+    Input(Input),
+    #[serde(rename = "rich_text")]
+    RichText(RichText),
+    Section(Section),
+}
+
+/// What's known about a tracked pull request beyond what Github reports
+#[derive(Debug, Default)]
+pub struct MessageContext {
+    /// Slack id of the author, when their Github account is linked
+    pub author: Option<String>,
+    /// Slack ids of the users who said they're reviewing it
+    pub reviewers: Vec<String>,
+    pub muted: bool,
+    /// Approved pull requests can be merged from the message
+    pub approved: bool,
+}
+
+/// A pull request waiting for review, along with what the queue shows about it
+pub struct QueueEntry<'a> {
+    pub pull_request: &'a models::PullRequest,
+    pub author: Option<&'a models::GithubUser>,
+    /// Slack id of the author, when their Github account is linked
+    pub author_slack_id: Option<&'a str>,
+    pub approvals: usize,
+    pub reviews: usize,
+}
+
+impl Block {
+    pub fn from_pull_request(
+        pull_request: &github::PRResult,
+        files: Vec<crate::models::IconMapping>,
+        additions: &str,
+        url: &str,
+        context: &MessageContext,
+    ) -> Vec<Block> {
+        let text = format!(
+            "<{}|{}> by {}",
+            pull_request.html_url,
+            pull_request.base.repo.full_name,
+            mention(context.author.as_deref(), &pull_request.user.login),
+        );
+
+        let mut elements = vec![
+            Elements::Image(Image::new(
+                "pull request status",
+                &format!("{}{}", url, pull_request.image_path()),
+            )),
+            Elements::Text(Text::new(additions)),
+        ];
+        elements.extend(files.into_iter().map(|icon| {
+            Elements::Image(Image::new(
+                &icon.file_type,
+                &format!("{}{}", url, icon.image_path()),
+            ))
+        }));
+
+        // Icons past the context block's element limit are summarised when
+        // the message is fitted to Slack's limits
+        let mut blocks = vec![
+            Block::Section(Section::new(&format!("*{}*\n{}", pull_request.title, text))),
+            Block::Context(Context::new(elements)),
+        ];
+
+        let mut status = vec![];
+        if !context.reviewers.is_empty() {
+            let reviewers: Vec<String> = context
+                .reviewers
+                .iter()
+                .map(|reviewer| format!("<@{}>", reviewer))
+                .collect();
+            status.push(format!(":eyes: Reviewing: {}", reviewers.join(", ")));
+        }
+        if context.muted {
+            status.push(":no_bell: Muted".to_string());
+        }
+        if !status.is_empty() {
+            blocks.push(Block::Context(Context::new(vec![Elements::Text(
+                Text::new(&status.join(" · ")),
+            )])));
+        }
+
+        if pull_request.open() {
+            let value = github::github_id(&pull_request.base.repo.full_name, pull_request.number);
+            let mute = if context.muted { "Unmute" } else { "Mute" };
+
+            let mut buttons = vec![Button::new("Review", REVIEW_ACTION, &value)];
+            if context.approved {
+                buttons.push(Button::new("Merge", MERGE_ACTION, &value).primary());
+                buttons.push(Button::new("I'm reviewing", CLAIM_REVIEW_ACTION, &value));
+            } else {
+                buttons.push(Button::new("I'm reviewing", CLAIM_REVIEW_ACTION, &value).primary());
+            }
+            buttons.push(Button::new(
+                "Nudge reviewers",
+                NUDGE_REVIEWERS_ACTION,
+                &value,
+            ));
+            buttons.push(Button::new(mute, MUTE_ACTION, &value));
+
+            blocks.push(Block::Actions(Actions::new(
+                buttons.into_iter().map(ActionElement::from).collect(),
+            )));
+        }
+
+        blocks
+    }
+
+    /// The pull request's message for a link pasted in Slack. Untracked pull
+    /// requests get a button to start tracking them in place of the usual
+    /// actions, which need a tracked pull request.
+    pub fn from_unfurl(
+        pull_request: &github::PRResult,
+        files: Vec<crate::models::IconMapping>,
+        url: &str,
+        context: &MessageContext,
+        tracked: bool,
+    ) -> Vec<Block> {
+        let additions = format!("(+{} -{})", pull_request.additions, pull_request.deletions);
+        let mut blocks = Block::from_pull_request(pull_request, files, &additions, url, context);
+
+        if !tracked {
+            blocks.retain(|block| !matches!(block, Block::Actions(_)));
+            blocks.push(Block::Actions(Actions::new(vec![ActionElement::from(
+                Button::new(
+                    "Track in this channel",
+                    TRACK_ACTION,
+                    &pull_request.html_url,
+                ),
+            )])));
+        }
+
+        blocks
+    }
+
+    /// Groups waiting pull requests under a header per repository, keeping the
+    /// order they're given in, and splits them into as many messages as
+    /// Slack's block limit requires
+    pub fn from_queue(entries: &[QueueEntry], now: NaiveDateTime) -> Vec<Vec<Block>> {
+        if entries.is_empty() {
+            return vec![vec![Block::Section(Section::new(
+                "All PRs Reviewed! :partyparrot:",
+            ))]];
+        }
+
+        let mut repos: Vec<(&str, Vec<&QueueEntry>)> = vec![];
+        for entry in entries {
+            let repo = entry.pull_request.repo.as_str();
+            match repos.iter_mut().find(|(name, _)| *name == repo) {
+                Some((_, group)) => group.push(entry),
+                None => repos.push((repo, vec![entry])),
+            }
+        }
+
+        let mut messages = vec![];
+        let mut current: Vec<Block> = vec![];
+        for (repo, group) in repos {
+            for (index, entry) in group.iter().enumerate() {
+                let needed = if index == 0 { 2 } else { 1 };
+                if current.len() + needed > MAX_BLOCKS {
+                    messages.push(current);
+                    current = vec![];
+                }
+
+                if index == 0 {
+                    current.push(Block::Header(Header::new(repo)));
+                } else if current.is_empty() {
+                    current.push(Block::Header(Header::new(&format!("{} (continued)", repo))));
+                }
+                current.push(Block::from_queue_entry(entry, now));
+            }
+        }
+        messages.push(current);
+
+        messages
+    }
+
+    /// A repository's dependency updates in one message, each with its status,
+    /// and a button approving every one that's still waiting
+    pub fn from_dependency_digest(
+        digest: &models::DependencyDigest,
+        pull_requests: &[models::PullRequest],
+    ) -> Vec<Block> {
+        let mut blocks = vec![Block::Header(Header::new(&format!(
+            "Dependency updates for {}",
+            digest.repo
+        )))];
+        blocks.extend(pull_requests.iter().map(|pull_request| {
+            let status = match (pull_request.state.as_str(), pull_request.merged) {
+                ("closed", true) => ":tada: Merged",
+                ("closed", false) => ":x: Closed",
+                ("approved", _) => ":white_check_mark: Approved",
+                _ => ":hourglass_flowing_sand: Waiting for review",
+            };
+            Block::Section(Section::new(&format!(
+                "*<{}|{}>*\n(+{} -{}) · {}",
+                pull_request.html_url,
+                mrkdwn::escape(&pull_request.title),
+                pull_request.additions,
+                pull_request.deletions,
+                status
+            )))
+        }));
+
+        let waiting = pull_requests
+            .iter()
+            .filter(|pull_request| pull_request.state == "open")
+            .count();
+        if waiting > 0 {
+            blocks.push(Block::Actions(Actions::new(vec![ActionElement::from(
+                Button::new(
+                    &format!("Approve all {}", waiting),
+                    APPROVE_ALL_ACTION,
+                    &digest.id.to_string(),
+                )
+                .primary(),
+            )])));
+        }
+
+        blocks
+    }
+
+    pub fn from_queue_entry(entry: &QueueEntry, now: NaiveDateTime) -> Block {
+        let pull_request = entry.pull_request;
+        let author = mention(
+            entry.author_slack_id,
+            entry
+                .author
+                .map(|author| author.login.as_str())
+                .unwrap_or("unknown"),
+        );
+
+        // Pull requests tracked before details were stored only have their display text
+        let title = if pull_request.title.is_empty() {
+            pull_request.display_text.clone()
+        } else {
+            format!(
+                "*<{}|{}>*\n(+{} -{})",
+                pull_request.html_url,
+                pull_request.title,
+                pull_request.additions,
+                pull_request.deletions
+            )
+        };
+
+        let mut details = vec![
+            format!(
+                "opened {} ago by {}",
+                age(pull_request.created_at, now),
+                author
+            ),
+            format!(
+                ":white_check_mark: {} approved, :speech_balloon: {} reviewed",
+                entry.approvals, entry.reviews
+            ),
+        ];
+        if !pull_request.labels.is_empty() {
+            let labels: Vec<String> = pull_request
+                .labels
+                .iter()
+                .map(|label| format!("`{}`", label))
+                .collect();
+            details.push(labels.join(" "));
+        }
+
+        let section = Section::new(&format!("{}\n{}", title, details.join(" · ")));
+        match entry.author.filter(|author| !author.avatar_url.is_empty()) {
+            Some(author) => {
+                Block::Section(section.accessory(Image::new(&author.login, &author.avatar_url)))
+            }
+            None => Block::Section(section),
+        }
+    }
+}
+
+/// A Slack mention when the person's Github account is linked, so they're
+/// notified, otherwise their Github login
+pub fn mention(slack_user_id: Option<&str>, login: &str) -> String {
+    match slack_user_id {
+        Some(slack_user_id) => format!("<@{}>", slack_user_id),
+        None => login.to_string(),
+    }
+}
+
+fn age(created_at: NaiveDateTime, now: NaiveDateTime) -> String {
+    let age = now - created_at;
+
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Elements {
+    Text(Text),
+    Image(Image),
+}
+
+/// Elements a section can show beside its text
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Accessory {
+    Image(Image),
+    Button(Button),
+    Overflow(Overflow),
+}
+
+/// Interactive elements an actions block can hold
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ActionElement {
+    Button(Button),
+    Overflow(Overflow),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Context {
+    elements: Vec<Elements>,
+}
+
+impl Context {
+    pub fn new(elements: Vec<Elements>) -> Self {
+        Self { elements }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Actions {
+    elements: Vec<ActionElement>,
+}
+
+impl Actions {
+    pub fn new(elements: Vec<ActionElement>) -> Self {
+        Self { elements }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Button {
+    #[serde(default = "button_type")]
+    #[serde(rename = "type")]
+    button_type: String,
+    text: PlainText,
+    action_id: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    style: Option<String>,
+}
+
+impl Button {
+    pub fn new(text: &str, action_id: &str, value: &str) -> Self {
+        Self {
+            button_type: button_type(),
+            text: PlainText::new(text),
+            action_id: action_id.to_owned(),
+            value: Some(value.to_owned()),
+            url: None,
+            style: None,
+        }
+    }
+
+    /// A button that opens `url` in the browser, Slack still sends the click
+    /// as an interaction
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn link(text: &str, action_id: &str, url: &str) -> Self {
+        Self {
+            button_type: button_type(),
+            text: PlainText::new(text),
+            action_id: action_id.to_owned(),
+            value: None,
+            url: Some(url.to_owned()),
+            style: None,
+        }
+    }
+
+    pub fn primary(mut self) -> Self {
+        self.style = Some("primary".to_owned());
+        self
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn danger(mut self) -> Self {
+        self.style = Some("danger".to_owned());
+        self
+    }
+}
+
+impl From<Button> for ActionElement {
+    fn from(button: Button) -> Self {
+        ActionElement::Button(button)
+    }
+}
+
+impl From<Button> for Accessory {
+    fn from(button: Button) -> Self {
+        Accessory::Button(button)
+    }
+}
+
+fn button_type() -> String {
+    "button".to_owned()
+}
+
+/// A `...` menu of options, each sent as an interaction when picked
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Overflow {
+    #[serde(default = "overflow_type")]
+    #[serde(rename = "type")]
+    overflow_type: String,
+    action_id: String,
+    options: Vec<OptionObject>,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl Overflow {
+    pub fn new(action_id: &str, options: Vec<OptionObject>) -> Self {
+        Self {
+            overflow_type: overflow_type(),
+            action_id: action_id.to_owned(),
+            options,
+        }
+    }
+}
+
+impl From<Overflow> for ActionElement {
+    fn from(overflow: Overflow) -> Self {
+        ActionElement::Overflow(overflow)
+    }
+}
+
+impl From<Overflow> for Accessory {
+    fn from(overflow: Overflow) -> Self {
+        Accessory::Overflow(overflow)
+    }
+}
+
+fn overflow_type() -> String {
+    "overflow".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Input {
+    block_id: String,
+    label: PlainText,
+    element: InputElement,
+    optional: bool,
+}
+
+impl Input {
+    /// An input whose element's `action_id` matches its `block_id`, so the
+    /// submitted value can be looked up by the block alone
+    pub fn new(block_id: &str, label: &str, element: InputElement) -> Self {
+        Self {
+            block_id: block_id.to_owned(),
+            label: PlainText::new(label),
+            element,
+            optional: false,
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum InputElement {
+    PlainTextInput {
+        action_id: String,
+        multiline: bool,
+    },
+    RadioButtons {
+        action_id: String,
+        options: Vec<OptionObject>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        initial_option: Option<OptionObject>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OptionObject {
+    text: PlainText,
+    value: String,
+}
+
+impl OptionObject {
+    pub fn new(text: &str, value: &str) -> Self {
+        Self {
+            text: PlainText::new(text),
+            value: value.to_owned(),
+        }
+    }
+}
+
+/// A section shows its text, its fields in two columns, or both
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Section {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Text>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<Text>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessory: Option<Accessory>,
+}
+
+impl Section {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: Some(Text::new(text)),
+            fields: vec![],
+            accessory: None,
+        }
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn fields(fields: &[&str]) -> Self {
+        Self {
+            text: None,
+            fields: fields.iter().map(|field| Text::new(field)).collect(),
+            accessory: None,
+        }
+    }
+
+    pub fn accessory<T: Into<Accessory>>(mut self, accessory: T) -> Self {
+        self.accessory = Some(accessory.into());
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Header {
+    text: PlainText,
+}
+
+impl Header {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: PlainText::new(text),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImageBlock {
+    image_url: String,
+    alt_text: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<PlainText>,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl ImageBlock {
+    pub fn new(image_url: &str, alt_text: &str) -> Self {
+        Self {
+            image_url: image_url.to_owned(),
+            alt_text: alt_text.to_owned(),
+            title: None,
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(PlainText::new(title));
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RichText {
+    elements: Vec<RichTextSection>,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl RichText {
+    pub fn new(elements: Vec<RichTextSection>) -> Self {
+        Self { elements }
+    }
+}
+
+/// A paragraph of rich text, built up one run of text, link or mention at a time
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RichTextSection {
+    #[serde(default = "rich_text_section_type")]
+    #[serde(rename = "type")]
+    section_type: String,
+    elements: Vec<RichTextElement>,
+}
+
+impl Default for RichTextSection {
+    fn default() -> Self {
+        Self {
+            section_type: rich_text_section_type(),
+            elements: vec![],
+        }
+    }
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl RichTextSection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(self, text: &str) -> Self {
+        self.push(RichTextElement::Text {
+            text: text.to_owned(),
+            style: None,
+        })
+    }
+
+    pub fn styled(self, text: &str, style: RichTextStyle) -> Self {
+        self.push(RichTextElement::Text {
+            text: text.to_owned(),
+            style: Some(style),
+        })
+    }
+
+    pub fn link(self, url: &str, text: &str) -> Self {
+        self.push(RichTextElement::Link {
+            url: url.to_owned(),
+            text: Some(text.to_owned()),
+        })
+    }
+
+    pub fn user(self, user_id: &str) -> Self {
+        self.push(RichTextElement::User {
+            user_id: user_id.to_owned(),
+        })
+    }
+
+    pub fn emoji(self, name: &str) -> Self {
+        self.push(RichTextElement::Emoji {
+            name: name.to_owned(),
+        })
+    }
+
+    fn push(mut self, element: RichTextElement) -> Self {
+        self.elements.push(element);
+        self
+    }
+}
+
+fn rich_text_section_type() -> String {
+    "rich_text_section".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum RichTextElement {
+    Text {
+        text: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        style: Option<RichTextStyle>,
+    },
+    Link {
+        url: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    User {
+        user_id: String,
+    },
+    Emoji {
+        name: String,
+    },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RichTextStyle {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub code: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub strike: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PlainText {
+    #[serde(default = "plain_text_type")]
+    #[serde(rename = "type")]
+    text_type: String,
+    text: String,
+}
+
+impl PlainText {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text_type: plain_text_type(),
+            text: text.to_owned(),
+        }
+    }
+}
+
+fn plain_text_type() -> String {
+    "plain_text".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Text {
+    #[serde(default = "text_type")]
+    #[serde(rename = "type")]
+    text_type: String,
+    text: String,
+}
+
+impl Text {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text_type: text_type(),
+            text: text.to_owned(),
+        }
+    }
+}
+
+fn text_type() -> String {
+    "mrkdwn".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Image {
+    #[serde(default = "image_type")]
+    #[serde(rename = "type")]
+    image_type: String,
+    image_url: String,
+    alt_text: String,
+}
+
+impl Image {
+    pub fn new(alt_text: &str, image_url: &str) -> Self {
+        Self {
+            image_type: image_type(),
+            image_url: image_url.to_owned(),
+            alt_text: alt_text.to_owned(),
+        }
+    }
+}
+
+impl From<Image> for Accessory {
+    fn from(image: Image) -> Self {
+        Accessory::Image(image)
+    }
+}
+
+fn image_type() -> String {
+    "image".to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::dependency_digest::MAX_DIGEST_PULL_REQUESTS;
+
+    fn pull_request(repo: &str, number: i32, state: &str) -> models::PullRequest {
+        let now = chrono::Utc::now().naive_utc();
+        models::PullRequest {
+            id: number,
+            github_id: format!("{}-{}", repo, number),
+            state: state.to_string(),
+            slack_message_id: "1.2".to_string(),
+            channel: "C1".to_string(),
+            display_text: String::new(),
+            created_at: now,
+            updated_at: now,
+            github_user_id: 1,
+            snoozed_until: None,
+            title: format!("Pull request {}", number),
+            html_url: format!("https://github.com/{}/pull/{}", repo, number),
+            additions: 1,
+            deletions: 1,
+            labels: vec![],
+            muted: false,
+            last_nudged_at: None,
+            digest_id: None,
+            merged: false,
+            host: "github.com".to_string(),
+            repo: repo.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_serialize_blocks() {
+        let blocks = vec![
+            Block::Divider,
+            Block::Section(Section::fields(&["*Repo*", "facebook/react"])),
+            Block::Image(ImageBlock::new("https://example.com/a.png", "a").title("A")),
+            Block::Actions(Actions::new(vec![
+                Button::new("Review", REVIEW_ACTION, "facebook/react-1")
+                    .primary()
+                    .into(),
+                Button::link("Open", "open", "https://github.com/facebook/react/pull/1")
+                    .danger()
+                    .into(),
+                Overflow::new(
+                    "more",
+                    vec![
+                        OptionObject::new("Mute", "mute"),
+                        OptionObject::new("Snooze", "snooze"),
+                    ],
+                )
+                .into(),
+            ])),
+            Block::RichText(RichText::new(vec![RichTextSection::new()
+                .user("U123")
+                .text(" commented on ")
+                .link("https://github.com/facebook/react/pull/1", "#1")
+                .styled(
+                    "LGTM",
+                    RichTextStyle {
+                        bold: true,
+                        ..RichTextStyle::default()
+                    },
+                )
+                .emoji("tada")])),
+        ];
+        let json = serde_json::to_value(&blocks).expect("Can't serialize blocks");
+
+        assert_eq!(json[0], serde_json::json!({ "type": "divider" }));
+        assert_eq!(json[1]["fields"][1]["text"], "facebook/react");
+        assert!(json[1].get("text").is_none());
+        assert_eq!(json[2]["type"], "image");
+        assert_eq!(json[2]["title"]["type"], "plain_text");
+        assert_eq!(json[3]["elements"][0]["style"], "primary");
+        assert_eq!(
+            json[3]["elements"][1]["url"],
+            "https://github.com/facebook/react/pull/1"
+        );
+        assert!(json[3]["elements"][1].get("value").is_none());
+        assert_eq!(json[3]["elements"][1]["style"], "danger");
+        assert_eq!(json[3]["elements"][2]["type"], "overflow");
+        assert_eq!(json[4]["type"], "rich_text");
+        assert_eq!(json[4]["elements"][0]["type"], "rich_text_section");
+        assert_eq!(
+            json[4]["elements"][0]["elements"][3],
+            serde_json::json!({ "type": "text", "text": "LGTM", "style": { "bold": true } })
+        );
+        assert_eq!(
+            json[4]["elements"][0]["elements"][4],
+            serde_json::json!({ "type": "emoji", "name": "tada" })
+        );
+    }
+
+    #[test]
+    fn test_from_queue_pagination() {
+        let now = chrono::Utc::now().naive_utc();
+        let pull_requests: Vec<models::PullRequest> = (1..=60)
+            .map(|number| pull_request("facebook/react", number, "open"))
+            .chain((61..=62).map(|number| pull_request("rust-lang/rust", number, "open")))
+            .collect();
+        let entries: Vec<QueueEntry> = pull_requests
+            .iter()
+            .map(|pull_request| QueueEntry {
+                pull_request,
+                author: None,
+                author_slack_id: None,
+                approvals: 0,
+                reviews: 0,
+            })
+            .collect();
+
+        let messages = Block::from_queue(&entries, now);
+        let headers = |message: &[Block]| -> Vec<String> {
+            message
+                .iter()
+                .filter_map(|block| match block {
+                    Block::Header(_) => serde_json::to_value(block)
+                        .ok()
+                        .map(|json| json["text"]["text"].as_str().unwrap().to_string()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|message| message.len() <= MAX_BLOCKS));
+        assert_eq!(messages[0].len(), MAX_BLOCKS);
+        assert_eq!(headers(&messages[0]), vec!["facebook/react"]);
+        assert_eq!(
+            headers(&messages[1]),
+            vec!["facebook/react (continued)", "rust-lang/rust"]
+        );
+        // Every entry is listed once, under one of the headers
+        let listed: usize = messages
+            .iter()
+            .map(|message| message.len() - headers(message).len())
+            .sum();
+        assert_eq!(listed, entries.len());
+    }
+
+    #[test]
+    fn test_from_dependency_digest() {
+        let now = chrono::Utc::now().naive_utc();
+        let digest = models::DependencyDigest {
+            id: 7,
+            repo: "facebook/react".to_string(),
+            channel: "C1".to_string(),
+            slack_message_id: "1.2".to_string(),
+            open: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let pull_request = |number: i32, state: &str, merged: bool| models::PullRequest {
+            digest_id: Some(7),
+            merged,
+            title: format!("Bump lodash to 4.17.{}", number),
+            ..pull_request("facebook/react", number, state)
+        };
+        let pull_requests = vec![
+            pull_request(1, "closed", true),
+            pull_request(2, "open", false),
+            pull_request(3, "approved", false),
+            pull_request(4, "open", false),
+            models::PullRequest {
+                title: "Bump <script> & friends".to_string(),
+                ..pull_request(5, "closed", true)
+            },
+        ];
+
+        let json = serde_json::to_value(Block::from_dependency_digest(&digest, &pull_requests))
+            .expect("Can't serialize blocks");
+
+        assert_eq!(
+            json[0]["text"]["text"],
+            "Dependency updates for facebook/react"
+        );
+        assert!(json[1]["text"]["text"]
+            .as_str()
+            .unwrap()
+            .ends_with(":tada: Merged"));
+        assert!(json[3]["text"]["text"]
+            .as_str()
+            .unwrap()
+            .ends_with(":white_check_mark: Approved"));
+        assert!(json[5]["text"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("|Bump &lt;script&gt; &amp; friends>*"));
+        assert_eq!(json[6]["elements"][0]["text"]["text"], "Approve all 2");
+        assert_eq!(json[6]["elements"][0]["value"], "7");
+
+        let closed = vec![pull_request(1, "closed", false)];
+        let json = serde_json::to_value(Block::from_dependency_digest(&digest, &closed))
+            .expect("Can't serialize blocks");
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        let full: Vec<_> = (1..=MAX_DIGEST_PULL_REQUESTS as i32)
+            .map(|number| pull_request(number, "open", false))
+            .collect();
+        let blocks = Block::from_dependency_digest(&digest, &full);
+        assert!(limits::validate(&blocks).is_ok());
+    }
+}
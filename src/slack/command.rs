@@ -0,0 +1,598 @@
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use std::fmt;
+
+use crate::github::MergeMethod;
+use crate::utils::notifications::{parse_time_zone, DigestFrequency};
+
+pub struct CommandDefinition {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+pub const COMMANDS: &[CommandDefinition] = &[
+    CommandDefinition {
+        name: "post",
+        args: "<url|owner/repo#123|#123>...",
+        description: "Post pull requests for review in this channel",
+    },
+    CommandDefinition {
+        name: "queue",
+        args: "[owner/repo|mine|@user|label:<name>]...",
+        description: "List pull requests waiting for review in any of the repositories given, matching every other filter",
+    },
+    CommandDefinition {
+        name: "stats",
+        args: "",
+        description: "Show review counts for the last 30 days",
+    },
+    CommandDefinition {
+        name: "snooze",
+        args: "<pull request> [hours]",
+        description: "Hide a pull request from the queue, for 24 hours by default and up to 720",
+    },
+    CommandDefinition {
+        name: "settings",
+        args:
+            "[repo <owner/repo|none> | threads <on|off> | merge <merge|squash|rebase> [owner/repo] | digest <hour|off> | nudge <hours|off> | timezone <Area/City> | calendar <name|none> | sla <hours|off> [@person|@team] [owner/repo]]",
+        description:
+            "Show or change this channel's settings and schedule, or how a repository is merged from Slack and who's mentioned when its pull requests miss their review SLA",
+    },
+    CommandDefinition {
+        name: "notify",
+        args: "[requests|reviews|merges <on|off> | digest <daily|weekly|off> | quiet <22-7|off> | timezone <Area/City>]",
+        description: "Show or change which direct messages the bot sends you, and when",
+    },
+    CommandDefinition {
+        name: "ooo",
+        args: "[until <YYYY-MM-DD> | off]",
+        description: "Mark yourself out of office, back on the given day, so you're not sent direct messages or reminders",
+    },
+    CommandDefinition {
+        name: "help",
+        args: "",
+        description: "Show this message",
+    },
+];
+
+const DEFAULT_SNOOZE_HOURS: i64 = 24;
+/// Longest a pull request can be snoozed for, 30 days
+const MAX_SNOOZE_HOURS: i64 = 720;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Post(String),
+    Queue(Vec<QueueFilter>),
+    Stats,
+    Snooze { pull_request: String, hours: i64 },
+    Settings(SettingsCommand),
+    Notify(NotifyCommand),
+    Away(AwayCommand),
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueueFilter {
+    Repo(String),
+    Mine,
+    SlackUser(String),
+    GithubLogin(String),
+    Label(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsCommand {
+    Show,
+    DefaultRepo(Option<String>),
+    ThreadActivity(bool),
+    MergeMethod {
+        method: MergeMethod,
+        repo: Option<String>,
+    },
+    DigestHour(Option<i32>),
+    NudgeAfterHours(Option<i32>),
+    Timezone(Tz),
+    Calendar(Option<String>),
+    ReviewSla {
+        hours: Option<i32>,
+        escalation: Option<String>,
+        repo: Option<String>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NotifyCommand {
+    Show,
+    ReviewRequests(bool),
+    Reviews(bool),
+    Merges(bool),
+    Digest(DigestFrequency),
+    QuietHours(Option<(i32, i32)>),
+    Timezone(Tz),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AwayCommand {
+    Show,
+    Until(NaiveDate),
+    Back,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CommandError(String);
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.0, usage())
+    }
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Result<Command, CommandError> {
+        let text = text.trim();
+        let mut words = text.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name.to_lowercase(),
+            None => return Ok(Command::Help),
+        };
+        let args: Vec<&str> = words.collect();
+
+        match name.as_str() {
+            "post" if args.is_empty() => {
+                Err(CommandError("Specify a pull request to post".to_string()))
+            }
+            "post" => Ok(Command::Post(args.join(" "))),
+            "queue" => parse_queue(&args),
+            "stats" => Ok(Command::Stats),
+            "snooze" => parse_snooze(&args),
+            "settings" => parse_settings(&args),
+            "notify" => parse_notify(&args),
+            "ooo" => parse_away(&args),
+            "help" => Ok(Command::Help),
+            // `/review <url>` predates subcommands, so anything that looks like a
+            // pull request is still posted
+            _ if looks_like_pull_request(&name) => Ok(Command::Post(text.to_string())),
+            _ => Err(CommandError(format!("Unknown command `{}`", name))),
+        }
+    }
+}
+
+pub fn usage() -> String {
+    let lines: Vec<String> = COMMANDS
+        .iter()
+        .map(|command| {
+            let invocation = format!("/review {} {}", command.name, command.args);
+            format!("`{}` {}", invocation.trim_end(), command.description)
+        })
+        .collect();
+
+    format!("*Usage*\n{}", lines.join("\n"))
+}
+
+fn parse_queue(args: &[&str]) -> Result<Command, CommandError> {
+    args.iter()
+        .map(|arg| parse_queue_filter(arg))
+        .collect::<Result<Vec<QueueFilter>, CommandError>>()
+        .map(Command::Queue)
+}
+
+fn parse_queue_filter(arg: &str) -> Result<QueueFilter, CommandError> {
+    let filter = match arg.splitn(2, ':').collect::<Vec<&str>>().as_slice() {
+        ["mine"] => QueueFilter::Mine,
+        ["repo", repo] if repo.contains('/') => QueueFilter::Repo(repo.to_string()),
+        ["author", login] if !login.is_empty() => {
+            QueueFilter::GithubLogin(login.trim_start_matches('@').to_string())
+        }
+        ["label", label] if !label.is_empty() => QueueFilter::Label(label.to_string()),
+        [user] if user.starts_with("<@") => QueueFilter::SlackUser(slack_user_id(user)),
+        [user] if user.starts_with('@') => QueueFilter::GithubLogin(user[1..].to_string()),
+        [repo] if repo.contains('/') => QueueFilter::Repo(repo.to_string()),
+        _ => return Err(CommandError(format!("Can't filter the queue by `{}`", arg))),
+    };
+
+    Ok(filter)
+}
+
+fn parse_snooze(args: &[&str]) -> Result<Command, CommandError> {
+    match args {
+        [pull_request] => Ok(Command::Snooze {
+            pull_request: pull_request.to_string(),
+            hours: DEFAULT_SNOOZE_HOURS,
+        }),
+        [pull_request, hours] => hours
+            .parse()
+            .ok()
+            .filter(|hours| (1..=MAX_SNOOZE_HOURS).contains(hours))
+            .map(|hours| Command::Snooze {
+                pull_request: pull_request.to_string(),
+                hours,
+            })
+            .ok_or_else(|| {
+                CommandError(format!(
+                    "`{}` isn't a number of hours between 1 and {}",
+                    hours, MAX_SNOOZE_HOURS
+                ))
+            }),
+        _ => Err(CommandError("Specify a pull request to snooze".to_string())),
+    }
+}
+
+fn parse_settings(args: &[&str]) -> Result<Command, CommandError> {
+    match args {
+        [] => Ok(Command::Settings(SettingsCommand::Show)),
+        ["repo", "none"] => Ok(Command::Settings(SettingsCommand::DefaultRepo(None))),
+        ["repo", repo] if repo.contains('/') => Ok(Command::Settings(
+            SettingsCommand::DefaultRepo(Some(repo.to_string())),
+        )),
+        ["threads", "on"] => Ok(Command::Settings(SettingsCommand::ThreadActivity(true))),
+        ["threads", "off"] => Ok(Command::Settings(SettingsCommand::ThreadActivity(false))),
+        ["merge", method] => Ok(Command::Settings(SettingsCommand::MergeMethod {
+            method: method.parse().map_err(CommandError)?,
+            repo: None,
+        })),
+        ["merge", method, repo] if repo.contains('/') => {
+            Ok(Command::Settings(SettingsCommand::MergeMethod {
+                method: method.parse().map_err(CommandError)?,
+                repo: Some(repo.to_string()),
+            }))
+        }
+        ["digest", "off"] => Ok(Command::Settings(SettingsCommand::DigestHour(None))),
+        ["digest", hour] => hour
+            .parse()
+            .ok()
+            .filter(|hour| (0..24).contains(hour))
+            .map(|hour| Command::Settings(SettingsCommand::DigestHour(Some(hour))))
+            .ok_or_else(|| CommandError(format!("`{}` isn't an hour of the day", hour))),
+        ["nudge", "off"] => Ok(Command::Settings(SettingsCommand::NudgeAfterHours(None))),
+        ["nudge", hours] => hours
+            .parse()
+            .ok()
+            .filter(|hours| *hours > 0)
+            .map(|hours| Command::Settings(SettingsCommand::NudgeAfterHours(Some(hours))))
+            .ok_or_else(|| CommandError(format!("`{}` isn't a number of hours", hours))),
+        ["calendar", "none"] => Ok(Command::Settings(SettingsCommand::Calendar(None))),
+        ["calendar", name @ ..] if !name.is_empty() => Ok(Command::Settings(
+            SettingsCommand::Calendar(Some(name.join(" "))),
+        )),
+        ["sla", "off"] => Ok(Command::Settings(SettingsCommand::ReviewSla {
+            hours: None,
+            escalation: None,
+            repo: None,
+        })),
+        ["sla", "off", repo] if repo.contains('/') => {
+            Ok(Command::Settings(SettingsCommand::ReviewSla {
+                hours: None,
+                escalation: None,
+                repo: Some(repo.to_string()),
+            }))
+        }
+        ["sla", hours, rest @ ..] if rest.len() <= 2 => parse_review_sla(hours, rest),
+        ["timezone", zone] => parse_time_zone(zone)
+            .map(|zone| Command::Settings(SettingsCommand::Timezone(zone)))
+            .ok_or_else(|| {
+                CommandError(format!(
+                    "`{}` isn't a time zone like America/New_York",
+                    zone
+                ))
+            }),
+        _ => Err(CommandError(format!(
+            "Unknown setting `{}`",
+            args.join(" ")
+        ))),
+    }
+}
+
+fn parse_notify(args: &[&str]) -> Result<Command, CommandError> {
+    match args {
+        [] => Ok(Command::Notify(NotifyCommand::Show)),
+        ["requests", setting] => parse_on_off(setting)
+            .map(|enabled| Command::Notify(NotifyCommand::ReviewRequests(enabled))),
+        ["reviews", setting] => {
+            parse_on_off(setting).map(|enabled| Command::Notify(NotifyCommand::Reviews(enabled)))
+        }
+        ["merges", setting] => {
+            parse_on_off(setting).map(|enabled| Command::Notify(NotifyCommand::Merges(enabled)))
+        }
+        ["digest", frequency] => frequency
+            .parse()
+            .map(|frequency| Command::Notify(NotifyCommand::Digest(frequency)))
+            .map_err(CommandError),
+        ["quiet", "off"] => Ok(Command::Notify(NotifyCommand::QuietHours(None))),
+        ["quiet", hours] => parse_quiet_hours(hours)
+            .map(|hours| Command::Notify(NotifyCommand::QuietHours(Some(hours))))
+            .ok_or_else(|| CommandError(format!("`{}` isn't a range of hours like 22-7", hours))),
+        ["timezone", zone] => parse_time_zone(zone)
+            .map(|zone| Command::Notify(NotifyCommand::Timezone(zone)))
+            .ok_or_else(|| {
+                CommandError(format!(
+                    "`{}` isn't a time zone like America/New_York",
+                    zone
+                ))
+            }),
+        _ => Err(CommandError(format!(
+            "Unknown notification `{}`",
+            args.join(" ")
+        ))),
+    }
+}
+
+fn parse_away(args: &[&str]) -> Result<Command, CommandError> {
+    match args {
+        [] => Ok(Command::Away(AwayCommand::Show)),
+        ["off"] => Ok(Command::Away(AwayCommand::Back)),
+        ["until", date] => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|date| Command::Away(AwayCommand::Until(date)))
+            .map_err(|_| CommandError(format!("`{}` isn't a date like 2026-11-02", date))),
+        _ => Err(CommandError(
+            "Say when you're back with `until <YYYY-MM-DD>`".to_string(),
+        )),
+    }
+}
+
+fn parse_on_off(setting: &str) -> Result<bool, CommandError> {
+    match setting {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(CommandError(format!("`{}` isn't on or off", setting))),
+    }
+}
+
+/// Quiet hours are given as `start-end` in whole hours of the day
+fn parse_quiet_hours(hours: &str) -> Option<(i32, i32)> {
+    let mut parts = hours.splitn(2, '-');
+    let start: i32 = parts.next()?.parse().ok()?;
+    let end: i32 = parts.next()?.parse().ok()?;
+
+    if (0..24).contains(&start) && (0..24).contains(&end) && start != end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+fn parse_review_sla(hours: &str, args: &[&str]) -> Result<Command, CommandError> {
+    let hours = hours
+        .parse()
+        .ok()
+        .filter(|hours| *hours > 0)
+        .ok_or_else(|| CommandError(format!("`{}` isn't a number of hours", hours)))?;
+    let mut escalation = None;
+    let mut repo = None;
+    for arg in args {
+        match slack_mention(arg) {
+            Some(mention) if escalation.is_none() => escalation = Some(mention),
+            None if repo.is_none() && arg.contains('/') => repo = Some(arg.to_string()),
+            _ => return Err(CommandError(format!("Unexpected `{}`", arg))),
+        }
+    }
+
+    Ok(Command::Settings(SettingsCommand::ReviewSla {
+        hours: Some(hours),
+        escalation,
+        repo,
+    }))
+}
+
+/// A user or user group mention without its label, which Slack still renders
+/// as a mention, e.g. `<!subteam^S1234|@team>` is `<!subteam^S1234>`
+fn slack_mention(arg: &str) -> Option<String> {
+    if arg.starts_with("<@") {
+        Some(format!("<@{}>", slack_user_id(arg)))
+    } else if arg.starts_with("<!subteam^") && arg.ends_with('>') {
+        let id = arg[1..arg.len() - 1].split('|').next().unwrap_or("");
+        Some(format!("<{}>", id))
+    } else {
+        None
+    }
+}
+
+/// Slack escapes mentions as `<@U1234|name>`
+fn slack_user_id(mention: &str) -> String {
+    mention
+        .trim_start_matches("<@")
+        .trim_end_matches('>')
+        .split('|')
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+fn looks_like_pull_request(word: &str) -> bool {
+    word.contains("://") || word.contains('#') || word.starts_with('<')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_legacy_url() {
+        let command = Command::parse("https://github.com/facebook/react/pull/1");
+        assert_eq!(
+            command,
+            Ok(Command::Post(
+                "https://github.com/facebook/react/pull/1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_queue_filters() {
+        assert_eq!(Command::parse("queue"), Ok(Command::Queue(vec![])));
+        assert_eq!(
+            Command::parse("queue mine"),
+            Ok(Command::Queue(vec![QueueFilter::Mine]))
+        );
+        assert_eq!(
+            Command::parse("queue <@U123|joe>"),
+            Ok(Command::Queue(vec![QueueFilter::SlackUser(
+                "U123".to_string()
+            )]))
+        );
+        assert_eq!(
+            Command::parse("queue @octocat"),
+            Ok(Command::Queue(vec![QueueFilter::GithubLogin(
+                "octocat".to_string()
+            )]))
+        );
+        assert_eq!(
+            Command::parse("queue facebook/react label:bug"),
+            Ok(Command::Queue(vec![
+                QueueFilter::Repo("facebook/react".to_string()),
+                QueueFilter::Label("bug".to_string())
+            ]))
+        );
+        assert!(Command::parse("queue label:").is_err());
+    }
+
+    #[test]
+    fn test_parse_snooze() {
+        assert_eq!(
+            Command::parse("snooze #12 4"),
+            Ok(Command::Snooze {
+                pull_request: "#12".to_string(),
+                hours: 4
+            })
+        );
+        assert!(Command::parse("snooze #12 soon").is_err());
+        assert!(Command::parse("snooze #12 0").is_err());
+        assert!(Command::parse("snooze #12 721").is_err());
+        assert!(Command::parse("snooze #12 9223372036854775807").is_err());
+    }
+
+    #[test]
+    fn test_parse_merge_method_setting() {
+        assert_eq!(
+            Command::parse("settings merge squash facebook/react"),
+            Ok(Command::Settings(SettingsCommand::MergeMethod {
+                method: MergeMethod::Squash,
+                repo: Some("facebook/react".to_string())
+            }))
+        );
+        assert!(Command::parse("settings merge fast-forward").is_err());
+    }
+
+    #[test]
+    fn test_parse_thread_activity_setting() {
+        assert_eq!(
+            Command::parse("settings threads off"),
+            Ok(Command::Settings(SettingsCommand::ThreadActivity(false)))
+        );
+        assert!(Command::parse("settings threads maybe").is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_settings() {
+        assert_eq!(
+            Command::parse("settings digest 9"),
+            Ok(Command::Settings(SettingsCommand::DigestHour(Some(9))))
+        );
+        assert_eq!(
+            Command::parse("settings nudge off"),
+            Ok(Command::Settings(SettingsCommand::NudgeAfterHours(None)))
+        );
+        assert_eq!(
+            Command::parse("settings timezone Europe/Paris"),
+            Ok(Command::Settings(SettingsCommand::Timezone(
+                Tz::Europe__Paris
+            )))
+        );
+        assert!(Command::parse("settings digest 25").is_err());
+        assert!(Command::parse("settings nudge 0").is_err());
+        assert_eq!(
+            Command::parse("settings calendar UK office"),
+            Ok(Command::Settings(SettingsCommand::Calendar(Some(
+                "UK office".to_string()
+            ))))
+        );
+    }
+
+    #[test]
+    fn test_parse_review_sla_setting() {
+        assert_eq!(
+            Command::parse("settings sla 4 <!subteam^S1|@reviewers> facebook/react"),
+            Ok(Command::Settings(SettingsCommand::ReviewSla {
+                hours: Some(4),
+                escalation: Some("<!subteam^S1>".to_string()),
+                repo: Some("facebook/react".to_string())
+            }))
+        );
+        assert_eq!(
+            Command::parse("settings sla 8 <@U1|alice>"),
+            Ok(Command::Settings(SettingsCommand::ReviewSla {
+                hours: Some(8),
+                escalation: Some("<@U1>".to_string()),
+                repo: None
+            }))
+        );
+        assert_eq!(
+            Command::parse("settings sla off"),
+            Ok(Command::Settings(SettingsCommand::ReviewSla {
+                hours: None,
+                escalation: None,
+                repo: None
+            }))
+        );
+        assert!(Command::parse("settings sla 0").is_err());
+        assert!(Command::parse("settings sla 4 someone").is_err());
+    }
+
+    #[test]
+    fn test_parse_away() {
+        assert_eq!(
+            Command::parse("ooo until 2026-11-02"),
+            Ok(Command::Away(AwayCommand::Until(NaiveDate::from_ymd(
+                2026, 11, 2
+            ))))
+        );
+        assert_eq!(
+            Command::parse("ooo off"),
+            Ok(Command::Away(AwayCommand::Back))
+        );
+        assert!(Command::parse("ooo until monday").is_err());
+        assert!(Command::parse("ooo 2026-11-02").is_err());
+    }
+
+    #[test]
+    fn test_parse_notify() {
+        assert_eq!(
+            Command::parse("notify requests off"),
+            Ok(Command::Notify(NotifyCommand::ReviewRequests(false)))
+        );
+        assert_eq!(
+            Command::parse("notify"),
+            Ok(Command::Notify(NotifyCommand::Show))
+        );
+        assert!(Command::parse("notify requests sometimes").is_err());
+        assert_eq!(
+            Command::parse("notify digest weekly"),
+            Ok(Command::Notify(NotifyCommand::Digest(
+                DigestFrequency::Weekly
+            )))
+        );
+        assert_eq!(
+            Command::parse("notify quiet 22-7"),
+            Ok(Command::Notify(NotifyCommand::QuietHours(Some((22, 7)))))
+        );
+        assert!(Command::parse("notify quiet 22-24").is_err());
+        assert_eq!(
+            Command::parse("notify timezone America/Chicago"),
+            Ok(Command::Notify(NotifyCommand::Timezone(
+                Tz::America__Chicago
+            )))
+        );
+        assert!(Command::parse("notify timezone -05:00").is_err());
+        assert_eq!(
+            Command::parse("notify sla 4"),
+            Err(CommandError("Unknown notification `sla 4`".to_string()))
+        );
+        assert!(Command::parse("notify sla off org/repo").is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown_command() {
+        assert!(Command::parse("dance").is_err());
+        assert_eq!(Command::parse(""), Ok(Command::Help));
+    }
+}
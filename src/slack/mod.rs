@@ -1,13 +1,25 @@
 pub mod attachment;
 mod blocks;
+pub mod command;
+pub mod mrkdwn;
+pub mod signature;
+pub mod views;
+
+pub use blocks::{
+    mention, MessageContext, QueueEntry, APPROVE_ALL_ACTION, CLAIM_REVIEW_ACTION, MERGE_ACTION,
+    MUTE_ACTION, NUDGE_REVIEWERS_ACTION, REVIEW_ACTION, TRACK_ACTION,
+};
 
 use base64::encode;
 use reqwest;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::fmt;
 
 use crate::error::{Error, Result};
 use crate::github;
 use crate::models;
+use crate::utils::outbound::{self, RetryPolicy};
 
 #[derive(Serialize, Debug)]
 pub struct SlackMessageResponse {
@@ -25,6 +37,45 @@ pub struct SlackMessagePost {
     blocks: Option<Vec<blocks::Block>>,
     username: Option<String>,
     icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_ts: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlackEphemeralPost {
+    channel: String,
+    user: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_ts: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlackUnfurl {
+    channel: String,
+    ts: String,
+    unfurls: HashMap<String, SlackUnfurlBlocks>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlackUnfurlBlocks {
+    blocks: Vec<blocks::Block>,
+}
+
+/// A pull request link to show a preview of
+pub struct Unfurl<'a> {
+    pub link: String,
+    pub pull_request: &'a github::PRResult,
+    pub files: Vec<models::IconMapping>,
+    pub context: MessageContext,
+    pub tracked: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlackActionResponse {
+    text: String,
+    response_type: String,
+    replace_original: bool,
 }
 
 #[derive(Serialize, Debug)]
@@ -35,6 +86,12 @@ pub struct SlackMessageUpdate {
     ts: String,
 }
 
+#[derive(Serialize, Debug)]
+pub struct SlackMessageDelete {
+    channel: String,
+    ts: String,
+}
+
 #[derive(Serialize, Debug)]
 pub struct SlackCreateComment {
     timestamp: String,
@@ -58,6 +115,49 @@ pub struct SlackMessageUpdateResponse {
     pub error: Option<String>,
 }
 
+#[derive(Serialize, Debug)]
+pub struct SlackOpenView<'a> {
+    trigger_id: String,
+    view: &'a views::View,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlackPublishView<'a> {
+    user_id: String,
+    view: &'a views::View,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlackLookupResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+    pub user: Option<SlackUserData>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlackViewResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+    pub view: Option<SlackOpenedView>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlackOpenedView {
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlackEphemeralResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlackUnfurlResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct SlackCreateCommentResponse {
     pub ok: bool,
@@ -79,12 +179,16 @@ pub struct SlackClient {
     pub channel: String,
     pub client_id: String,
     pub client_secret: String,
+    /// Checks requests really came from Slack
+    pub signing_secret: String,
     client: reqwest::Client,
+    policy: RetryPolicy,
 }
 
 pub enum Reaction {
     Comment,
     Approve,
+    SlaBreach,
 }
 
 trait SlackResponse {
@@ -104,6 +208,42 @@ impl SlackResponse for SlackCreateCommentResponse {
     }
 }
 
+impl SlackResponse for SlackEphemeralResponse {
+    fn ok(&self) -> bool {
+        self.ok
+    }
+
+    fn error(&self) -> String {
+        self.error
+            .clone()
+            .unwrap_or_else(|| "Unkown Error".to_string())
+    }
+}
+
+impl SlackResponse for SlackUnfurlResponse {
+    fn ok(&self) -> bool {
+        self.ok
+    }
+
+    fn error(&self) -> String {
+        self.error
+            .clone()
+            .unwrap_or_else(|| "Unkown Error".to_string())
+    }
+}
+
+impl SlackResponse for SlackViewResponse {
+    fn ok(&self) -> bool {
+        self.ok
+    }
+
+    fn error(&self) -> String {
+        self.error
+            .clone()
+            .unwrap_or_else(|| "Unkown Error".to_string())
+    }
+}
+
 impl SlackResponse for SlackMessageUpdateResponse {
     fn ok(&self) -> bool {
         self.ok
@@ -133,6 +273,7 @@ impl fmt::Display for Reaction {
         match *self {
             Reaction::Approve => write!(f, "white_check_mark"),
             Reaction::Comment => write!(f, "eyes"),
+            Reaction::SlaBreach => write!(f, "rotating_light"),
         }
     }
 }
@@ -144,6 +285,8 @@ impl SlackClient {
         channel: &str,
         client_id: &str,
         client_secret: &str,
+        signing_secret: &str,
+        policy: RetryPolicy,
     ) -> Result<SlackClient> {
         let url = "https://slack.com/api/".to_owned();
         let mut headers = reqwest::header::HeaderMap::new();
@@ -151,16 +294,16 @@ impl SlackClient {
             reqwest::header::AUTHORIZATION,
             reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
         );
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?;
+        let client = policy.client_builder().default_headers(headers).build()?;
 
         Ok(SlackClient {
             url,
             client,
+            policy,
             channel: channel.to_owned(),
             client_id: client_id.to_owned(),
             client_secret: client_secret.to_owned(),
+            signing_secret: signing_secret.to_owned(),
         })
     }
 
@@ -170,20 +313,23 @@ impl SlackClient {
         files: Vec<crate::models::IconMapping>,
         channel: &str,
         url: &str,
+        context: &MessageContext,
         user: Option<models::User>,
     ) -> Result<SlackMessagePostResponse> {
         let additions = format!("(+{} -{})", pull_request.additions, pull_request.deletions);
         let mut message = SlackMessagePost {
             text: None,
-            blocks: Some(blocks::Block::from_pull_request(
+            blocks: Some(blocks::fit(blocks::Block::from_pull_request(
                 pull_request,
                 files,
                 &additions,
                 url,
-            )),
+                context,
+            ))),
             channel: channel.to_string(),
             username: Some(pull_request.user.login.to_string()),
             icon_url: Some(pull_request.user.avatar_url.to_string()),
+            thread_ts: None,
         };
 
         let mut request = self
@@ -200,17 +346,11 @@ impl SlackClient {
         }
 
         let message = serde_json::to_string(&message)?;
-
-        request
+        let request = request
             .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(message)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<SlackMessagePostResponse>()
-            .await
-            .map_err(|e| e.into())
-            .and_then(handle_response)
+            .body(message);
+
+        self.call("chat.postMessage", request, false).await
     }
 
     pub async fn update_message(
@@ -220,18 +360,20 @@ impl SlackClient {
         ts: &str,
         channel: &str,
         url: &str,
+        context: &MessageContext,
         user: Option<models::User>,
     ) -> Result<SlackMessageUpdateResponse> {
         let additions = format!("(+{} -{})", pull_request.additions, pull_request.deletions);
 
         let message = serde_json::to_string(&SlackMessageUpdate {
             text: None,
-            blocks: Some(blocks::Block::from_pull_request(
+            blocks: Some(blocks::fit(blocks::Block::from_pull_request(
                 pull_request,
                 files,
                 &additions,
                 url,
-            )),
+                context,
+            ))),
             channel: channel.to_string(),
             ts: ts.to_string(),
         })?;
@@ -244,16 +386,320 @@ impl SlackClient {
             );
         }
 
-        request
+        let request = request
             .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(message)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<SlackMessageUpdateResponse>()
-            .await
-            .map_err(|e| e.into())
-            .and_then(handle_response)
+            .body(message);
+
+        self.call("chat.update", request, true).await
+    }
+
+    /// Sends the pull request's message to a user directly, under `text`
+    pub async fn post_direct_message(
+        &self,
+        slack_user_id: &str,
+        text: &str,
+        pull_request: &github::PRResult,
+        files: Vec<crate::models::IconMapping>,
+        url: &str,
+        context: &MessageContext,
+    ) -> Result<SlackMessagePostResponse> {
+        let additions = format!("(+{} -{})", pull_request.additions, pull_request.deletions);
+        let mut message_blocks = vec![blocks::Block::Section(blocks::Section::new(text))];
+        message_blocks.extend(blocks::Block::from_pull_request(
+            pull_request,
+            files,
+            &additions,
+            url,
+            context,
+        ));
+
+        let message = serde_json::to_string(&SlackMessagePost {
+            text: Some(text.to_string()),
+            channel: slack_user_id.to_string(),
+            blocks: Some(blocks::fit(message_blocks)),
+            username: None,
+            icon_url: None,
+            thread_ts: None,
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.postMessage"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.postMessage", request, false).await
+    }
+
+    /// Posts plain text as the bot, e.g. to a user's id to message them directly
+    pub async fn post_text(&self, channel: &str, text: &str) -> Result<SlackMessagePostResponse> {
+        let message = serde_json::to_string(&SlackMessagePost {
+            text: Some(text.to_string()),
+            channel: channel.to_string(),
+            blocks: None,
+            username: None,
+            icon_url: None,
+            thread_ts: None,
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.postMessage"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.postMessage", request, false).await
+    }
+
+    /// Replaces a dependency digest's message with its pull requests' statuses
+    pub async fn update_dependency_digest(
+        &self,
+        digest: &models::DependencyDigest,
+        pull_requests: &[models::PullRequest],
+    ) -> Result<SlackMessageUpdateResponse> {
+        let message = serde_json::to_string(&SlackMessageUpdate {
+            text: Some(format!("Dependency updates for {}", digest.repo)),
+            blocks: Some(blocks::fit(blocks::Block::from_dependency_digest(
+                digest,
+                pull_requests,
+            ))),
+            channel: digest.channel.to_string(),
+            ts: digest.slack_message_id.to_string(),
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.update"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.update", request, true).await
+    }
+
+    /// Replaces a message the bot posted with plain text
+    pub async fn update_text(
+        &self,
+        channel: &str,
+        ts: &str,
+        text: &str,
+    ) -> Result<SlackMessageUpdateResponse> {
+        let message = serde_json::to_string(&SlackMessageUpdate {
+            text: Some(text.to_string()),
+            blocks: Some(vec![]),
+            channel: channel.to_string(),
+            ts: ts.to_string(),
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.update"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.update", request, true).await
+    }
+
+    pub async fn delete_message(
+        &self,
+        channel: &str,
+        ts: &str,
+    ) -> Result<SlackMessageUpdateResponse> {
+        let message = serde_json::to_string(&SlackMessageDelete {
+            channel: channel.to_string(),
+            ts: ts.to_string(),
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.delete"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.delete", request, true).await
+    }
+
+    pub async fn post_thread_reply(
+        &self,
+        channel: &str,
+        thread_ts: &str,
+        text: &str,
+        user: Option<models::User>,
+    ) -> Result<SlackMessagePostResponse> {
+        let message = serde_json::to_string(&SlackMessagePost {
+            text: Some(text.to_string()),
+            channel: channel.to_string(),
+            blocks: None,
+            username: None,
+            icon_url: None,
+            thread_ts: Some(thread_ts.to_string()),
+        })?;
+
+        let mut request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.postMessage"));
+
+        if let Some(user) = user {
+            request = request.header(
+                reqwest::header::AUTHORIZATION,
+                &format!("Bearer {}", user.slack_access_token),
+            );
+        }
+
+        let request = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.postMessage", request, false).await
+    }
+
+    /// Shows `text` to just `user`, in the thread when one is given
+    pub async fn post_ephemeral(
+        &self,
+        channel: &str,
+        user: &str,
+        text: &str,
+        thread_ts: Option<&str>,
+    ) -> Result<SlackEphemeralResponse> {
+        let message = serde_json::to_string(&SlackEphemeralPost {
+            channel: channel.to_string(),
+            user: user.to_string(),
+            text: text.to_string(),
+            thread_ts: thread_ts.map(|ts| ts.to_string()),
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.postEphemeral"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.postEphemeral", request, false).await
+    }
+
+    /// Replaces the previews of pull request links in a message with the
+    /// pull requests' messages
+    pub async fn unfurl(
+        &self,
+        channel: &str,
+        ts: &str,
+        unfurls: Vec<Unfurl<'_>>,
+        url: &str,
+    ) -> Result<SlackUnfurlResponse> {
+        let unfurls = unfurls
+            .into_iter()
+            .map(|unfurl| {
+                let blocks = blocks::Block::from_unfurl(
+                    unfurl.pull_request,
+                    unfurl.files,
+                    url,
+                    &unfurl.context,
+                    unfurl.tracked,
+                );
+                (
+                    unfurl.link,
+                    SlackUnfurlBlocks {
+                        blocks: blocks::fit(blocks),
+                    },
+                )
+            })
+            .collect();
+
+        let message = serde_json::to_string(&SlackUnfurl {
+            channel: channel.to_string(),
+            ts: ts.to_string(),
+            unfurls,
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "chat.unfurl"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("chat.unfurl", request, false).await
+    }
+
+    pub async fn open_view(
+        &self,
+        trigger_id: &str,
+        view: &views::View,
+    ) -> Result<SlackViewResponse> {
+        let message = serde_json::to_string(&SlackOpenView {
+            trigger_id: trigger_id.to_string(),
+            view,
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "views.open"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("views.open", request, false).await
+    }
+
+    /// Replaces the user's Home tab
+    pub async fn publish_view(
+        &self,
+        user_id: &str,
+        view: &views::View,
+    ) -> Result<SlackViewResponse> {
+        let message = serde_json::to_string(&SlackPublishView {
+            user_id: user_id.to_string(),
+            view,
+        })?;
+
+        let request = self
+            .client
+            .post(&format!("{}/{}", self.url, "views.publish"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        self.call("views.publish", request, false).await
+    }
+
+    /// The Slack user with the email address, if there is one
+    pub async fn lookup_user_by_email(&self, email: &str) -> Result<Option<String>> {
+        let request = self
+            .client
+            .get(&format!("{}/{}", self.url, "users.lookupByEmail"))
+            .query(&[("email", email)]);
+
+        let res = self
+            .policy
+            .send("users.lookupByEmail", request, true)
+            .await?;
+        let body = outbound::error_for_status("users.lookupByEmail", res)?
+            .json::<SlackLookupResponse>()
+            .await?;
+
+        match body.error.as_deref() {
+            Some("users_not_found") => Ok(None),
+            _ if !body.ok => Err(Error::SlackError(format!(
+                "users.lookupByEmail: {}",
+                body.error.unwrap_or_else(|| "Unkown Error".to_string())
+            ))),
+            _ => Ok(body.user.map(|user| user.id)),
+        }
+    }
+
+    /// Replies only to the user who clicked a button, leaving the message as is
+    pub async fn respond(&self, response_url: &str, text: &str) -> Result<()> {
+        let message = serde_json::to_string(&SlackActionResponse {
+            text: text.to_string(),
+            response_type: "ephemeral".to_string(),
+            replace_original: false,
+        })?;
+
+        let request = self
+            .client
+            .post(response_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message);
+
+        let res = self.policy.send("response_url", request, false).await?;
+        outbound::error_for_status("response_url", res)?;
+        Ok(())
     }
 
     pub fn immediate_response(&self, text: String) -> Result<String> {
@@ -267,21 +713,31 @@ impl SlackClient {
         .map_err(|e| e.into())
     }
 
-    pub async fn reviews_response(&self, text: &str, channel_id: &str) -> Result<()> {
-        let response = serde_json::to_string(&SlackMessageResponse {
-            text: Some(text.to_string()),
-            blocks: None,
-            response_type: "in_channel".to_string(),
-            username: Some("Waiting for Review".to_string()),
-            channel: Some(channel_id.to_string()),
-        })?;
-
-        self.client
-            .post(&format!("{}/{}", self.url, "chat.postMessage"))
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(response)
-            .send()
-            .await?;
+    pub async fn reviews_response(
+        &self,
+        entries: &[QueueEntry<'_>],
+        channel_id: &str,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+
+        for blocks in blocks::Block::from_queue(entries, now) {
+            let response = serde_json::to_string(&SlackMessageResponse {
+                text: Some("Waiting for Review".to_string()),
+                blocks: Some(blocks::fit(blocks)),
+                response_type: "in_channel".to_string(),
+                username: Some("Waiting for Review".to_string()),
+                channel: Some(channel_id.to_string()),
+            })?;
+
+            let request = self
+                .client
+                .post(&format!("{}/{}", self.url, "chat.postMessage"))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(response);
+
+            self.call::<SlackMessagePostResponse>("chat.postMessage", request, false)
+                .await?;
+        }
 
         Ok(())
     }
@@ -310,16 +766,11 @@ impl SlackClient {
             );
         }
 
-        request
+        let request = request
             .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(message)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
-            .map_err(|e| e.into())
-            .and_then(handle_response)
+            .body(message);
+
+        self.call("reactions.add", request, true).await
     }
 
     pub async fn get_token(&self, code: &str) -> Result<SlackAuthResponse> {
@@ -330,18 +781,28 @@ impl SlackClient {
             reqwest::header::HeaderValue::from_str(&format!("Basic {}", auth_code)).unwrap(),
         );
 
-        let client = reqwest::Client::new();
-        client
+        let client = self.policy.client_builder().build()?;
+        let request = client
             .post(&format!("{}/{}", self.url, "oauth.v2.access"))
             .form(&[("code", code)])
-            .headers(headers)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
-            .map_err(|e| e.into())
-            .and_then(handle_response)
+            .headers(headers);
+
+        self.call("oauth.v2.access", request, false).await
+    }
+
+    async fn call<T>(
+        &self,
+        api: &str,
+        request: reqwest::RequestBuilder,
+        idempotent: bool,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned + SlackResponse,
+    {
+        let res = self.policy.send(api, request, idempotent).await?;
+        let body = outbound::error_for_status(api, res)?.json::<T>().await?;
+
+        handle_response(api, body)
     }
 }
 
@@ -378,10 +839,10 @@ impl SlackResponse for SlackAuthResponse {
     }
 }
 
-fn handle_response<T: SlackResponse>(resp: T) -> Result<T> {
+fn handle_response<T: SlackResponse>(api: &str, resp: T) -> Result<T> {
     if resp.ok() {
         Ok(resp)
     } else {
-        Err(Error::SlackError(resp.error()))
+        Err(Error::SlackError(format!("{}: {}", api, resp.error())))
     }
 }
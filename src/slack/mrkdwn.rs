@@ -0,0 +1,116 @@
+/// Slack treats these as control characters in message text
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The start of `text`, cut on a character boundary
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(max_chars - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Quotes every line of `text`, escaped
+pub fn quote(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| format!("> {}", escape(line)))
+        .collect();
+
+    lines.join("\n")
+}
+
+/// Converts Slack message text to Github markdown. Links become markdown
+/// links and user mentions are named by `mention`, falling back to the raw
+/// mention when it returns None.
+pub fn to_markdown<F>(text: &str, mention: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut markdown = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        markdown.push_str(&rest[..start]);
+
+        let inner = &rest[start + 1..end];
+        let mut parts = inner.splitn(2, '|');
+        let target = parts.next().unwrap_or("");
+        let label = parts.next();
+
+        let converted = if let Some(user_id) = target.strip_prefix('@') {
+            mention(user_id).unwrap_or_else(|| format!("@{}", label.unwrap_or(user_id)))
+        } else if let Some(channel) = target.strip_prefix('#') {
+            format!("#{}", label.unwrap_or(channel))
+        } else if let Some(special) = target.strip_prefix('!') {
+            format!("@{}", label.unwrap_or(special))
+        } else {
+            match label {
+                Some(label) => format!("[{}]({})", label, target),
+                None => target.to_string(),
+            }
+        };
+        markdown.push_str(&converted);
+
+        rest = &rest[end + 1..];
+    }
+    markdown.push_str(rest);
+
+    unescape(&markdown)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a < b && c > d"), "a &lt; b &amp;&amp; c &gt; d");
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("  short  ", 10), "short");
+        assert_eq!(excerpt("héllo wörld", 7), "héllo…");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let mention = |id: &str| {
+            if id == "U1" {
+                Some("@octocat".to_string())
+            } else {
+                None
+            }
+        };
+
+        assert_eq!(
+            to_markdown(
+                "<@U1> see <https://example.com|the docs> &amp; <https://example.com/b>",
+                mention
+            ),
+            "@octocat see [the docs](https://example.com) & https://example.com/b"
+        );
+        assert_eq!(
+            to_markdown("<@U2> in <#C1|general>, <!here>", mention),
+            "@U2 in #general, @here"
+        );
+        assert_eq!(to_markdown("if a &lt; b", mention), "if a < b");
+    }
+}
@@ -0,0 +1,131 @@
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+pub const SIGNATURE_HEADER: &str = "X-Slack-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Slack-Request-Timestamp";
+
+/// Requests signed longer ago than this are rejected, so captured ones can't
+/// be replayed
+const MAX_AGE_SECS: i64 = 5 * 60;
+
+/// Whether Slack signed `body` with the app's signing secret, as described at
+/// https://api.slack.com/authentication/verifying-requests-from-slack
+pub fn verify(
+    signing_secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    now: i64,
+) -> bool {
+    let signed_at: i64 = match timestamp.parse() {
+        Ok(signed_at) => signed_at,
+        Err(_) => return false,
+    };
+    if (now - signed_at).abs() > MAX_AGE_SECS {
+        return false;
+    }
+
+    let expected = match signature.strip_prefix("v0=").and_then(decode_hex) {
+        Some(expected) => expected,
+        None => return false,
+    };
+    let mut mac = match Hmac::<Sha256>::new_varkey(signing_secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+
+    mac.verify(&expected).is_ok()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The example from Slack's documentation
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: &str = "1531420618";
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    #[test]
+    fn test_verify() {
+        let now = 1531420618 + 60;
+
+        assert!(verify(SECRET, TIMESTAMP, BODY.as_bytes(), SIGNATURE, now));
+        assert!(!verify(
+            "other secret",
+            TIMESTAMP,
+            BODY.as_bytes(),
+            SIGNATURE,
+            now
+        ));
+        assert!(!verify(
+            SECRET,
+            TIMESTAMP,
+            b"user_id=U0ADMIN",
+            SIGNATURE,
+            now
+        ));
+        assert!(!verify(
+            SECRET,
+            "1531420619",
+            BODY.as_bytes(),
+            SIGNATURE,
+            now
+        ));
+        assert!(!verify(
+            SECRET,
+            TIMESTAMP,
+            BODY.as_bytes(),
+            "v0=nothex",
+            now
+        ));
+        assert!(!verify(
+            SECRET,
+            TIMESTAMP,
+            BODY.as_bytes(),
+            &SIGNATURE[3..],
+            now
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_old_requests() {
+        let signed_at = 1531420618;
+
+        assert!(verify(
+            SECRET,
+            TIMESTAMP,
+            BODY.as_bytes(),
+            SIGNATURE,
+            signed_at + MAX_AGE_SECS
+        ));
+        assert!(!verify(
+            SECRET,
+            TIMESTAMP,
+            BODY.as_bytes(),
+            SIGNATURE,
+            signed_at + MAX_AGE_SECS + 1
+        ));
+        assert!(!verify(
+            SECRET,
+            "not a time",
+            BODY.as_bytes(),
+            SIGNATURE,
+            signed_at
+        ));
+    }
+}
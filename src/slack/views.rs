@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+
+use super::blocks::{
+    Block, Context, Elements, Header, Input, InputElement, OptionObject, PlainText, QueueEntry,
+    Section, Text,
+};
+use crate::github::ReviewVerdict;
+
+pub const REVIEW_CALLBACK: &str = "submit_review";
+pub const REVIEW_VERDICT_BLOCK: &str = "verdict";
+pub const REVIEW_COMMENT_BLOCK: &str = "comment";
+
+/// Modal titles longer than this are rejected by Slack
+const MAX_TITLE_LENGTH: usize = 24;
+
+/// Pull requests listed in each section of the Home tab, which keeps it well
+/// under Slack's 100 block limit for views
+const MAX_HOME_ENTRIES: usize = 20;
+
+#[derive(Serialize, Debug)]
+pub struct View {
+    #[serde(rename = "type")]
+    view_type: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    callback_id: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    private_metadata: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<PlainText>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submit: Option<PlainText>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    close: Option<PlainText>,
+    blocks: Vec<Block>,
+}
+
+impl View {
+    fn modal(callback_id: &str, private_metadata: &str, title: &str, blocks: Vec<Block>) -> Self {
+        Self {
+            view_type: "modal".to_owned(),
+            callback_id: callback_id.to_owned(),
+            private_metadata: private_metadata.to_owned(),
+            title: Some(PlainText::new(
+                &title.chars().take(MAX_TITLE_LENGTH).collect::<String>(),
+            )),
+            submit: Some(PlainText::new("Submit")),
+            close: Some(PlainText::new("Cancel")),
+            blocks,
+        }
+    }
+
+    fn home_tab(blocks: Vec<Block>) -> Self {
+        Self {
+            view_type: "home".to_owned(),
+            callback_id: String::new(),
+            private_metadata: String::new(),
+            title: None,
+            submit: None,
+            close: None,
+            blocks,
+        }
+    }
+
+    /// A user's Home tab: pull requests waiting on their review, their own open
+    /// pull requests and where they stand, and links to the web app
+    pub fn home(
+        to_review: &[QueueEntry],
+        authored: &[QueueEntry],
+        app_url: &str,
+        now: NaiveDateTime,
+    ) -> Self {
+        let mut blocks = vec![];
+        blocks.extend(home_section(
+            "Waiting for your review",
+            "Nothing to review :tada:",
+            to_review,
+            now,
+        ));
+        blocks.push(Block::Divider);
+        blocks.extend(home_section(
+            "Your open pull requests",
+            "You have no open pull requests",
+            authored,
+            now,
+        ));
+        blocks.push(Block::Divider);
+        blocks.push(quick_links(app_url));
+
+        Self::home_tab(blocks)
+    }
+
+    /// The Home tab for someone who hasn't connected their Github account, so
+    /// there's nothing to list
+    pub fn home_signed_out(app_url: &str) -> Self {
+        Self::home_tab(vec![
+            Block::Section(Section::new(&format!(
+                "<{}|Sign in and connect your Github account> to see the pull requests waiting on you here.",
+                app_url
+            ))),
+            quick_links(app_url),
+        ])
+    }
+
+    /// Asks for a verdict and comment on the pull request tracked under
+    /// `github_id`, which comes back as the submission's `private_metadata`
+    pub fn review(github_id: &str, pull_request_title: &str) -> Self {
+        let verdicts = [
+            ("Approve", ReviewVerdict::Approve),
+            ("Request changes", ReviewVerdict::RequestChanges),
+            ("Comment", ReviewVerdict::Comment),
+        ];
+        let options: Vec<OptionObject> = verdicts
+            .iter()
+            .map(|(text, verdict)| OptionObject::new(text, &verdict.to_string()))
+            .collect();
+
+        Self::modal(
+            REVIEW_CALLBACK,
+            github_id,
+            "Review pull request",
+            vec![
+                Block::Section(Section::new(&format!("*{}*", pull_request_title))),
+                Block::Input(Input::new(
+                    REVIEW_VERDICT_BLOCK,
+                    "Review",
+                    InputElement::RadioButtons {
+                        action_id: REVIEW_VERDICT_BLOCK.to_owned(),
+                        initial_option: options.first().cloned(),
+                        options,
+                    },
+                )),
+                Block::Input(
+                    Input::new(
+                        REVIEW_COMMENT_BLOCK,
+                        "Comment",
+                        InputElement::PlainTextInput {
+                            action_id: REVIEW_COMMENT_BLOCK.to_owned(),
+                            multiline: true,
+                        },
+                    )
+                    .optional(),
+                ),
+            ],
+        )
+    }
+}
+
+fn home_section(
+    title: &str,
+    empty: &str,
+    entries: &[QueueEntry],
+    now: NaiveDateTime,
+) -> Vec<Block> {
+    let mut blocks = vec![Block::Header(Header::new(title))];
+    if entries.is_empty() {
+        blocks.push(Block::Section(Section::new(empty)));
+    }
+
+    blocks.extend(
+        entries
+            .iter()
+            .take(MAX_HOME_ENTRIES)
+            .map(|entry| Block::from_queue_entry(entry, now)),
+    );
+    if entries.len() > MAX_HOME_ENTRIES {
+        blocks.push(Block::Context(Context::new(vec![Elements::Text(
+            Text::new(&format!(
+                "and {} more, see `/review queue`",
+                entries.len() - MAX_HOME_ENTRIES
+            )),
+        )])));
+    }
+
+    blocks
+}
+
+fn quick_links(app_url: &str) -> Block {
+    Block::Context(Context::new(vec![Elements::Text(Text::new(&format!(
+        "<{url}|Your account> · <{url}/github/repos|Repository webhooks> · `/review help` for commands",
+        url = app_url.trim_end_matches('/')
+    )))]))
+}
+
+/// The reply to a `view_submission` that keeps the modal open with errors
+/// shown under the offending inputs
+#[derive(Serialize, Debug)]
+pub struct ViewErrors {
+    response_action: String,
+    errors: HashMap<String, String>,
+}
+
+impl ViewErrors {
+    pub fn new(block_id: &str, message: &str) -> Self {
+        let mut errors = HashMap::new();
+        errors.insert(block_id.to_owned(), message.to_owned());
+
+        Self {
+            response_action: "errors".to_owned(),
+            errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_review_modal_offers_github_verdicts() {
+        let view = serde_json::to_value(View::review("facebook/react-1", "Fix the thing"))
+            .expect("Can't serialize view");
+
+        assert_eq!(view["private_metadata"], "facebook/react-1");
+        let options = &view["blocks"][1]["element"]["options"];
+        assert_eq!(options[0]["value"], "APPROVE");
+        assert_eq!(options[1]["value"], "REQUEST_CHANGES");
+        assert_eq!(options[2]["value"], "COMMENT");
+        assert_eq!(
+            view["blocks"][1]["element"]["initial_option"]["value"],
+            "APPROVE"
+        );
+    }
+
+    #[test]
+    fn test_home_tab_has_no_modal_fields() {
+        let now = chrono::Utc::now().naive_utc();
+        let view = serde_json::to_value(View::home(&[], &[], "https://example.com", now))
+            .expect("Can't serialize view");
+
+        assert_eq!(view["type"], "home");
+        assert!(view.get("title").is_none());
+        assert!(view.get("callback_id").is_none());
+        assert_eq!(view["blocks"][0]["text"]["text"], "Waiting for your review");
+        assert_eq!(
+            view["blocks"][1]["text"]["text"],
+            "Nothing to review :tada:"
+        );
+    }
+}
@@ -8,6 +8,8 @@ use futures::TryFutureExt;
 
 use crate::github::{GithubClient, GithubOauthClient};
 use crate::slack::SlackClient;
+use crate::utils::notifications::ReviewBatches;
+use crate::utils::outbound::RetryPolicy;
 
 #[derive(Clone)]
 pub struct AppConfig {
@@ -30,17 +32,28 @@ pub struct AppData {
     pub github_oauth: GithubOauthClient,
     pub slack: SlackClient,
     pub app_url: String,
+    pub review_batches: ReviewBatches,
 }
 
 #[derive(Clone, Default)]
 pub struct AppDataBuilder {
     github: GithubClient,
+    http_policy: RetryPolicy,
     app_url: Option<String>,
     github_oauth: Option<GithubOauthClient>,
     slack: Option<SlackClient>,
+    review_batches: ReviewBatches,
+    /// GitHub Enterprise hosts and the token to use on each
+    enterprise_hosts: Vec<(String, String)>,
 }
 
 impl AppDataBuilder {
+    pub fn http_policy(mut self, policy: RetryPolicy) -> Self {
+        self.github = GithubClient::new(policy.clone());
+        self.http_policy = policy;
+        self
+    }
+
     pub fn github(mut self, client_id: &str, client_secret: &str) -> Self {
         self.github_oauth
             .replace(GithubOauthClient::new(client_id, client_secret));
@@ -51,27 +64,49 @@ impl AppDataBuilder {
         mut self,
         client_id: &str,
         client_secret: &str,
+        signing_secret: &str,
         channel: &str,
         token: &str,
     ) -> Self {
         self.slack.replace(
-            SlackClient::new(token, channel, client_id, client_secret)
-                .expect("Error creating slack client"),
+            SlackClient::new(
+                token,
+                channel,
+                client_id,
+                client_secret,
+                signing_secret,
+                self.http_policy.clone(),
+            )
+            .expect("Error creating slack client"),
         );
         self
     }
 
+    pub fn github_enterprise(mut self, host: &str, token: &str) -> Self {
+        self.enterprise_hosts
+            .push((host.to_owned(), token.to_owned()));
+        self
+    }
+
     pub fn app_url(mut self, url: &str) -> Self {
         self.app_url.replace(url.to_string());
         self
     }
 
     pub fn build(mut self) -> Option<AppData> {
+        let github = self
+            .enterprise_hosts
+            .iter()
+            .fold(self.github, |github, (host, token)| {
+                github.enterprise_host(host, token)
+            });
+
         Some(AppData {
-            github: self.github,
+            github,
             github_oauth: self.github_oauth.take()?,
             slack: self.slack.take()?,
             app_url: self.app_url.take()?,
+            review_batches: self.review_batches,
         })
     }
 
@@ -95,6 +130,15 @@ impl AppData {
     pub fn webhook_url(&self) -> String {
         format!("{}/github_event", self.app_url)
     }
+
+    /// A copy for work done after Slack or GitHub has been answered, which can
+    /// wait out GitHub's rate limits
+    pub fn background(&self) -> Self {
+        Self {
+            github: self.github.background(),
+            ..self.clone()
+        }
+    }
 }
 
 impl FromRequest for AppData {
@@ -0,0 +1,195 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::db::DBExecutor;
+use crate::error::Result;
+use crate::models::{
+    AwayStatus, GithubUser, NotificationPreference, PullRequest, QueueQuery, ReviewClaim,
+    ReviewRequestMessage, User,
+};
+use crate::AppData;
+
+/// Slack status emoji that mean someone's out of office
+pub const AWAY_STATUS_EMOJI: &[&str] = &[
+    ":palm_tree:",
+    ":airplane:",
+    ":beach_with_umbrella:",
+    ":face_with_thermometer:",
+];
+
+/// Away until the start of `date` in the user's time zone
+pub fn away_until_date(date: NaiveDate, time_zone: Tz) -> AwayStatus {
+    let start = |time: NaiveDateTime| time_zone.from_local_datetime(&time).earliest();
+    let midnight = date.and_hms(0, 0, 0);
+
+    AwayStatus {
+        // A clock change can skip midnight, and then the day starts an hour later
+        away_until: start(midnight)
+            .or_else(|| start(midnight + Duration::hours(1)))
+            .map(|start| start.naive_utc()),
+        away_from_status: false,
+    }
+}
+
+/// The day `user` is back, in their time zone, when they've said
+pub fn back_on(user: &User, db: &DBExecutor) -> Result<Option<String>> {
+    let settings = NotificationPreference::settings(user.id, db)?;
+
+    Ok(user.away_until.map(|until| {
+        settings
+            .tz()
+            .from_utc_datetime(&until)
+            .format("%a %-d %b")
+            .to_string()
+    }))
+}
+
+/// The status a user's new Slack status moves them to, or None to leave them
+/// as they are. Away set with `/review ooo` is left alone, and an
+/// `expiration` of 0 means the Slack status doesn't expire.
+pub fn status_change(
+    current: &AwayStatus,
+    emoji: &str,
+    expiration: i64,
+    now: NaiveDateTime,
+) -> Option<AwayStatus> {
+    let away_from_command = !current.away_from_status && current.is_away(now);
+
+    let next = if AWAY_STATUS_EMOJI.contains(&emoji) && !away_from_command {
+        AwayStatus {
+            away_until: Some(expiration)
+                .filter(|expiration| *expiration > 0)
+                .and_then(|expiration| NaiveDateTime::from_timestamp_opt(expiration, 0)),
+            away_from_status: true,
+        }
+    } else if !AWAY_STATUS_EMOJI.contains(&emoji) && current.away_from_status {
+        AwayStatus::back()
+    } else {
+        return None;
+    };
+
+    Some(next).filter(|next| next != current)
+}
+
+/// Posts in the thread of each pull request still waiting on `user` that it
+/// needs another reviewer, which are those they've claimed or had a review
+/// requested on. It's done in the background, since there can be more posts
+/// than fit in the time Slack gives a command or event to be answered.
+pub fn flag_for_reassignment(state: &AppData, user: &User, db: &DBExecutor) {
+    let (state, user, db) = (state.clone(), user.clone(), db.clone());
+    actix_rt::spawn(async move {
+        if let Err(err) = flag_waiting_pull_requests(&state, &user, &db).await {
+            log::warn!(
+                "Couldn't flag pull requests waiting on {}: {}",
+                user.slack_user_id,
+                err
+            );
+        }
+    });
+}
+
+async fn flag_waiting_pull_requests(state: &AppData, user: &User, db: &DBExecutor) -> Result<()> {
+    let github_ids: Vec<i32> = GithubUser::for_user(user.id, db)?
+        .iter()
+        .map(|github_user| github_user.github_id)
+        .collect();
+    let requested: Vec<String> = ReviewRequestMessage::for_reviewers(&github_ids, db)?
+        .into_iter()
+        .map(|message| message.pull_request_github_id)
+        .collect();
+    let claimed = ReviewClaim::pull_request_ids(user, db)?;
+
+    for pr in PullRequest::waiting(&QueueQuery::default(), db)? {
+        if claimed.contains(&pr.id) || requested.contains(&pr.github_id) {
+            flag_pull_request(state, user, &pr, db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Posts in the pull request's thread that `user` is away and it needs
+/// another reviewer
+pub async fn flag_pull_request(
+    state: &AppData,
+    user: &User,
+    pull_request: &PullRequest,
+    db: &DBExecutor,
+) -> Result<()> {
+    let text = match back_on(user, db)? {
+        Some(back_on) => format!(
+            ":palm_tree: <@{}> is out of office until {}, so this pull request needs another reviewer",
+            user.slack_user_id, back_on
+        ),
+        None => format!(
+            ":palm_tree: <@{}> is out of office, so this pull request needs another reviewer",
+            user.slack_user_id
+        ),
+    };
+
+    state
+        .slack
+        .post_thread_reply(
+            &pull_request.channel,
+            &pull_request.slack_message_id,
+            &text,
+            None,
+        )
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2026, 10, day).and_hms(hour, 0, 0)
+    }
+
+    #[test]
+    fn test_away_until_date() {
+        assert_eq!(
+            away_until_date(NaiveDate::from_ymd(2026, 11, 2), Tz::Europe__Paris).away_until,
+            Some(NaiveDate::from_ymd(2026, 11, 1).and_hms(23, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_status_change() {
+        let now = at(19, 12);
+        let back = AwayStatus::back();
+        let from_status = AwayStatus {
+            away_until: None,
+            away_from_status: true,
+        };
+
+        assert_eq!(
+            status_change(&back, ":palm_tree:", 0, now),
+            Some(from_status.clone())
+        );
+        assert_eq!(
+            status_change(&back, ":airplane:", at(23, 17).timestamp(), now),
+            Some(AwayStatus {
+                away_until: Some(at(23, 17)),
+                away_from_status: true,
+            })
+        );
+        // An expiration chrono can't represent is taken as no expiration
+        assert_eq!(
+            status_change(&back, ":palm_tree:", i64::MAX, now),
+            Some(from_status.clone())
+        );
+        assert_eq!(status_change(&back, ":coffee:", 0, now), None);
+        assert_eq!(status_change(&from_status, ":palm_tree:", 0, now), None);
+        assert_eq!(
+            status_change(&from_status, "", 0, now),
+            Some(AwayStatus::back())
+        );
+
+        // Away with `/review ooo` isn't changed by the Slack status
+        let ooo = away_until_date(NaiveDate::from_ymd(2026, 11, 2), Tz::UTC);
+        assert_eq!(status_change(&ooo, ":palm_tree:", 0, now), None);
+        assert_eq!(status_change(&ooo, "", 0, now), None);
+    }
+}
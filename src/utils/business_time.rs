@@ -0,0 +1,171 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::db::DBExecutor;
+use crate::error::Result;
+use crate::models::{Calendar, ChannelSchedule, Holiday};
+
+/// When a team is working, in their time zone. Without a calendar that's 9 to
+/// 5, Monday to Friday, in UTC.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkingHours {
+    pub time_zone: Tz,
+    /// ISO weekday numbers, Monday is 1
+    pub working_days: Vec<u32>,
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            time_zone: Tz::UTC,
+            working_days: vec![1, 2, 3, 4, 5],
+            start_hour: 9,
+            end_hour: 17,
+            holidays: vec![],
+        }
+    }
+}
+
+impl WorkingHours {
+    pub fn from_calendar(calendar: &Calendar, holidays: &[Holiday]) -> Self {
+        Self {
+            time_zone: zone(&calendar.time_zone),
+            working_days: calendar
+                .working_days
+                .iter()
+                .map(|day| *day as u32)
+                .collect(),
+            start_hour: calendar.start_hour as u32,
+            end_hour: calendar.end_hour as u32,
+            holidays: holidays.iter().map(|holiday| holiday.date).collect(),
+        }
+    }
+
+    /// The default hours in `time_zone`
+    pub fn in_zone(time_zone: Tz) -> Self {
+        Self {
+            time_zone,
+            ..Self::default()
+        }
+    }
+
+    /// The channel's calendar when it has one, otherwise the default hours in
+    /// the channel's time zone
+    pub fn for_channel(channel: &str, db: &DBExecutor) -> Result<WorkingHours> {
+        let schedule = ChannelSchedule::find(channel, db)?;
+        let calendar = match schedule.as_ref().and_then(|schedule| schedule.calendar_id) {
+            Some(calendar_id) => Calendar::find(calendar_id, db)?,
+            None => None,
+        };
+
+        match (calendar, schedule) {
+            (Some(calendar), _) => Ok(Self::from_calendar(
+                &calendar,
+                &Holiday::for_calendar(calendar.id, db)?,
+            )),
+            (None, Some(schedule)) => Ok(Self::in_zone(zone(&schedule.time_zone))),
+            (None, None) => Ok(Self::default()),
+        }
+    }
+
+    /// `time` in UTC, moved into the team's time zone
+    pub fn local(&self, time: NaiveDateTime) -> NaiveDateTime {
+        self.time_zone.from_utc_datetime(&time).naive_local()
+    }
+
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        self.working_days
+            .contains(&date.weekday().number_from_monday())
+            && !self.holidays.contains(&date)
+    }
+
+    /// The working time between two UTC times
+    pub fn between(&self, start: NaiveDateTime, end: NaiveDateTime) -> Duration {
+        let (start, end) = (self.local(start), self.local(end));
+        let mut total = Duration::zero();
+
+        let mut date = start.date();
+        while date <= end.date() {
+            if self.is_working_day(date) {
+                // Counted from midnight so a day can end at 24:00
+                let midnight = date.and_hms(0, 0, 0);
+                let from = start.max(midnight + Duration::hours(self.start_hour.into()));
+                let to = end.min(midnight + Duration::hours(self.end_hour.into()));
+                if to > from {
+                    total = total + (to - from);
+                }
+            }
+            date = date.succ();
+        }
+
+        total
+    }
+}
+
+/// A stored time zone name, UTC if it's no longer one chrono-tz knows
+fn zone(name: &str) -> Tz {
+    name.parse().unwrap_or(Tz::UTC)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        // October 2026 starts on a Thursday
+        NaiveDate::from_ymd(2026, 10, day).and_hms(hour, 0, 0)
+    }
+
+    #[test]
+    fn test_between() {
+        let hours = WorkingHours::default();
+
+        assert_eq!(hours.between(at(19, 10), at(19, 12)), Duration::hours(2));
+        assert_eq!(hours.between(at(19, 6), at(19, 20)), Duration::hours(8));
+        // Friday afternoon to Monday morning skips the weekend
+        assert_eq!(hours.between(at(16, 16), at(19, 10)), Duration::hours(2));
+        assert_eq!(hours.between(at(17, 10), at(18, 16)), Duration::zero());
+        assert_eq!(hours.between(at(20, 10), at(19, 10)), Duration::zero());
+    }
+
+    #[test]
+    fn test_between_in_zone() {
+        // 7:00 to 9:00 UTC is 9:00 to 11:00 in Berlin's summer time
+        assert_eq!(
+            WorkingHours::in_zone(Tz::Europe__Berlin).between(at(19, 7), at(19, 9)),
+            Duration::hours(2)
+        );
+        // and 8:00 to 10:00 once it ends, an hour of which is working time
+        assert_eq!(
+            WorkingHours::in_zone(Tz::Europe__Berlin).between(at(26, 7), at(26, 9)),
+            Duration::hours(1)
+        );
+        assert_eq!(
+            WorkingHours::default().between(at(19, 7), at(19, 9)),
+            Duration::zero()
+        );
+    }
+
+    #[test]
+    fn test_between_with_calendar() {
+        // Sunday to Thursday, 8 to 4, with Tuesday off
+        let hours = WorkingHours {
+            working_days: vec![7, 1, 2, 3, 4],
+            start_hour: 8,
+            end_hour: 16,
+            holidays: vec![NaiveDate::from_ymd(2026, 10, 20)],
+            ..WorkingHours::default()
+        };
+
+        assert!(hours.is_working_day(NaiveDate::from_ymd(2026, 10, 18)));
+        assert!(!hours.is_working_day(NaiveDate::from_ymd(2026, 10, 16)));
+        assert!(!hours.is_working_day(NaiveDate::from_ymd(2026, 10, 20)));
+        // Friday and Saturday are off, so Thursday 15:00 to Sunday 9:00 is two hours
+        assert_eq!(hours.between(at(15, 15), at(18, 9)), Duration::hours(2));
+        // Monday 15:00 to Wednesday 9:00 skips the holiday
+        assert_eq!(hours.between(at(19, 15), at(21, 9)), Duration::hours(2));
+    }
+}
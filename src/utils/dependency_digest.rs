@@ -0,0 +1,139 @@
+use crate::db::DBExecutor;
+use crate::error::{Error, Result};
+use crate::github::{NewReview, PRResult, ReviewVerdict};
+use crate::models::{DependencyDigest, NewPullRequest, PullRequest};
+use crate::slack::mrkdwn;
+use crate::AppData;
+
+/// Authors whose pull requests are batched into their repository's digest
+pub const DEPENDENCY_BOTS: &[&str] = &[
+    "dependabot[bot]",
+    "dependabot-preview[bot]",
+    "renovate[bot]",
+];
+
+/// Most pull requests a digest lists before the next update starts a new one,
+/// keeping the message well under Slack's block limit
+pub const MAX_DIGEST_PULL_REQUESTS: usize = 20;
+
+pub fn is_dependency_update(pull_request: &PRResult) -> bool {
+    DEPENDENCY_BOTS.contains(&pull_request.user.login.as_str())
+}
+
+/// Tracks a dependency update in its repository's open digest, posting a new
+/// digest when there isn't one
+pub async fn post_dependency_update(
+    state: &AppData,
+    db: &DBExecutor,
+    pull_request: &PRResult,
+    github_user_id: i32,
+) -> Result<()> {
+    let full_name = &pull_request.base.repo.full_name;
+    let digest = match DependencyDigest::open_for_repo(full_name, db)? {
+        Some(digest) => digest,
+        None => {
+            let result = state
+                .slack
+                .post_text(
+                    &state.slack.channel,
+                    &format!("Dependency updates for {}", full_name),
+                )
+                .await?;
+            DependencyDigest::create(
+                full_name,
+                &result.channel.unwrap_or_else(|| "".to_string()),
+                &result.ts.unwrap_or_else(|| "".to_string()),
+                db,
+            )?
+        }
+    };
+
+    PullRequest::create(
+        &NewPullRequest {
+            digest_id: Some(digest.id),
+            ..NewPullRequest::new(
+                pull_request,
+                digest.slack_message_id.clone(),
+                digest.channel.clone(),
+                github_user_id,
+            )
+        },
+        db,
+    )?;
+
+    refresh_digest(state, db, digest.id).await
+}
+
+/// Re-renders a digest's message, closing the digest once all of its pull
+/// requests are, or once it's full, so the next update starts a new one
+pub async fn refresh_digest(state: &AppData, db: &DBExecutor, digest_id: i32) -> Result<()> {
+    let digest = DependencyDigest::find(digest_id, db)?.ok_or(Error::NotFoundError)?;
+    let pull_requests = PullRequest::in_digest(digest.id, db)?;
+
+    state
+        .slack
+        .update_dependency_digest(&digest, &pull_requests)
+        .await?;
+
+    if digest.open
+        && (pull_requests.len() >= MAX_DIGEST_PULL_REQUESTS
+            || pull_requests
+                .iter()
+                .all(|pull_request| pull_request.state == "closed"))
+    {
+        digest.close(db)?;
+    }
+    Ok(())
+}
+
+/// Approves every pull request in the digest still waiting for a review with
+/// `token`, returning a notice of how it went. The message is updated by the
+/// review webhooks like any other review.
+pub async fn approve_all(
+    digest_id: i32,
+    token: &str,
+    state: &AppData,
+    db: &DBExecutor,
+) -> Result<Option<String>> {
+    let mut approved = 0;
+    let mut failed = vec![];
+
+    for pull_request in PullRequest::in_digest(digest_id, db)? {
+        if pull_request.state != "open" {
+            continue;
+        }
+        let review_request = match pull_request.review_request() {
+            Some(review_request) => review_request,
+            None => continue,
+        };
+
+        let review = NewReview {
+            event: ReviewVerdict::Approve,
+            body: String::new(),
+        };
+        match state
+            .github
+            .create_review(&review_request, &review, token)
+            .await
+        {
+            Ok(()) => approved += 1,
+            Err(err) => failed.push(format!(
+                "<{}|{}>: {}",
+                pull_request.html_url,
+                mrkdwn::escape(&pull_request.title),
+                err
+            )),
+        }
+    }
+
+    let notice = match (approved, failed.is_empty()) {
+        (0, true) => "There's nothing left to approve".to_string(),
+        (_, true) => format!("Approved {} dependency updates", approved),
+        _ => format!(
+            "Approved {} dependency updates, but couldn't approve:\n{}",
+            approved,
+            failed.join("\n")
+        ),
+    };
+    Ok(Some(notice))
+}
@@ -1,8 +1,15 @@
 use actix_session::Session;
+use std::collections::HashMap;
 
 use crate::db::DBExecutor;
-use crate::error::Result;
-use crate::models::User;
+use crate::error::{Error, Result};
+use crate::github::{PRFiles, PRResult};
+use crate::models::{
+    ChannelSetting, GithubUser, IconMapping, PullRequest, QueueQuery, Review, ReviewClaim, User,
+};
+use crate::slack::{MessageContext, QueueEntry};
+use crate::utils::dependency_digest::refresh_digest;
+use crate::AppData;
 
 pub fn get_current_user(db: &DBExecutor, session: &Session) -> Result<Option<User>> {
     if let Some(id) = session.get("id")? {
@@ -12,6 +19,249 @@ pub fn get_current_user(db: &DBExecutor, session: &Session) -> Result<Option<Use
     }
 }
 
+/// The signed in user, when they're allowed on the admin pages
+pub fn require_admin(db: &DBExecutor, session: &Session) -> Result<User> {
+    get_current_user(db, session)?
+        .filter(|user| user.admin)
+        .ok_or(Error::NotAuthedError)
+}
+
 pub fn sign_out_current_user(session: &Session) {
     session.clear()
 }
+
+pub fn message_context(pull_request: &PullRequest, db: &DBExecutor) -> Result<MessageContext> {
+    Ok(MessageContext {
+        author: GithubUser::slack_user_id(pull_request.github_user_id, db)?,
+        reviewers: ReviewClaim::slack_user_ids(pull_request, db)?,
+        muted: pull_request.muted,
+        approved: pull_request.state == "approved",
+    })
+}
+
+pub fn queue_entries<'a>(
+    prs: &'a [PullRequest],
+    reviews: &[Review],
+    authors: &'a [GithubUser],
+    slack_ids: &'a HashMap<i32, String>,
+) -> Vec<QueueEntry<'a>> {
+    prs.iter()
+        .map(|pr| {
+            let pr_reviews: Vec<&Review> = reviews
+                .iter()
+                .filter(|review| review.pull_request_id == pr.id)
+                .collect();
+
+            QueueEntry {
+                pull_request: pr,
+                author: authors
+                    .iter()
+                    .find(|author| author.github_id == pr.github_user_id),
+                author_slack_id: slack_ids.get(&pr.github_user_id).map(|id| id.as_str()),
+                approvals: pr_reviews
+                    .iter()
+                    .filter(|review| review.state == "approved")
+                    .count(),
+                reviews: pr_reviews.len(),
+            }
+        })
+        .collect()
+}
+
+/// A user's side of the review queue: what's waiting on their review, claimed
+/// first, and what they've opened themselves
+pub struct ReviewQueue {
+    pub to_review: Vec<PullRequest>,
+    pub authored: Vec<PullRequest>,
+    reviews: Vec<Review>,
+    authors: Vec<GithubUser>,
+    slack_ids: HashMap<i32, String>,
+}
+
+impl ReviewQueue {
+    pub fn for_user(user: &User, db: &DBExecutor) -> Result<ReviewQueue> {
+        let github_users = GithubUser::for_user(user.id, db)?;
+        let github_ids: Vec<i32> = github_users
+            .iter()
+            .map(|github_user| github_user.github_id)
+            .collect();
+        let claimed = ReviewClaim::pull_request_ids(user, db)?;
+
+        let mut to_review = PullRequest::waiting(&QueueQuery::default(), db)?;
+        to_review.retain(|pr| !github_ids.contains(&pr.github_user_id));
+        let authored = PullRequest::open_by_authors(&github_ids, db)?;
+
+        let prs = || to_review.iter().chain(authored.iter());
+        let pr_ids: Vec<i32> = prs().map(|pr| pr.id).collect();
+        let author_ids: Vec<i32> = prs().map(|pr| pr.github_user_id).collect();
+        let reviews = Review::for_pull_requests(&pr_ids, db)?;
+        let authors = GithubUser::by_github_ids(&author_ids, db)?;
+        let slack_ids = GithubUser::slack_user_ids(&author_ids, db)?;
+
+        // Reviews are stored against the reviewer's github_users row
+        to_review.retain(|pr| {
+            !reviews.iter().any(|review| {
+                review.pull_request_id == pr.id
+                    && github_users
+                        .iter()
+                        .any(|github_user| github_user.id == review.github_user_id)
+            })
+        });
+        to_review.sort_by_key(|pr| !claimed.contains(&pr.id));
+
+        Ok(ReviewQueue {
+            to_review,
+            authored,
+            reviews,
+            authors,
+            slack_ids,
+        })
+    }
+
+    pub fn to_review_entries(&self) -> Vec<QueueEntry<'_>> {
+        queue_entries(
+            &self.to_review,
+            &self.reviews,
+            &self.authors,
+            &self.slack_ids,
+        )
+    }
+
+    pub fn authored_entries(&self) -> Vec<QueueEntry<'_>> {
+        queue_entries(
+            &self.authored,
+            &self.reviews,
+            &self.authors,
+            &self.slack_ids,
+        )
+    }
+}
+
+/// Re-renders the Slack message for a tracked pull request, posting as its
+/// author when they've signed in since that's who the message was posted as.
+/// Dependency updates re-render their digest instead.
+pub async fn update_pull_request_message(
+    state: &AppData,
+    db: &DBExecutor,
+    db_pr: &PullRequest,
+    pull_request: &PRResult,
+) -> Result<()> {
+    if let Some(digest_id) = db_pr.digest_id {
+        return refresh_digest(state, db, digest_id).await;
+    }
+
+    let user = db_pr.user(db)?;
+
+    let pr_files = PRFiles::new(
+        pull_request,
+        &state.github,
+        user.clone().and_then(|u| u.github_access_token),
+    )
+    .await;
+    let mappings = IconMapping::from(pr_files.filenames, pr_files.extensions, db)?;
+
+    state
+        .slack
+        .update_message(
+            pull_request,
+            mappings,
+            &db_pr.slack_message_id,
+            &db_pr.channel,
+            &state.app_url,
+            &message_context(db_pr, db)?,
+            user,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Fetches the pull request from Github with `token`, or its author's token
+/// when none is given, and re-renders its message. Returns false when there's
+/// no token to fetch it with.
+pub async fn refresh_pull_request_message(
+    state: &AppData,
+    db: &DBExecutor,
+    db_pr: &PullRequest,
+    token: Option<String>,
+) -> Result<bool> {
+    // Digests render from what's tracked, so there's nothing to fetch
+    if let Some(digest_id) = db_pr.digest_id {
+        refresh_digest(state, db, digest_id).await?;
+        return Ok(true);
+    }
+
+    let token = match token.or_else(|| {
+        db_pr
+            .user(db)
+            .ok()
+            .flatten()
+            .and_then(|author| author.github_access_token)
+    }) {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+    let review_request = db_pr.review_request().ok_or(Error::NotFoundError)?;
+
+    let pull_request = state.github.get_pr(&review_request, &token).await?;
+    update_pull_request_message(state, db, db_pr, &pull_request).await?;
+    Ok(true)
+}
+
+/// Adds an entry to the pull request's timeline in its message thread, posted
+/// as `user` when given, unless the channel has turned thread activity off.
+/// Returns the reply's timestamp when it was posted.
+pub async fn post_thread_activity(
+    state: &AppData,
+    db: &DBExecutor,
+    db_pr: &PullRequest,
+    text: &str,
+    user: Option<User>,
+) -> Result<Option<String>> {
+    if !ChannelSetting::thread_activity(&db_pr.channel, db)? {
+        return Ok(None);
+    }
+
+    // A digest's thread is shared, so say which update it's about
+    let text = match db_pr.digest_id {
+        Some(_) => format!("<{}|{}>: {}", db_pr.html_url, db_pr.title, text),
+        None => text.to_string(),
+    };
+    let result = state
+        .slack
+        .post_thread_reply(&db_pr.channel, &db_pr.slack_message_id, &text, user)
+        .await?;
+    Ok(result.ts)
+}
+
+/// Links each Github account nobody has connected yet to the signed in Slack
+/// user with the same email, looking the emails up with `token`. Returns how
+/// many were linked.
+pub async fn match_github_users_by_email(
+    state: &AppData,
+    db: &DBExecutor,
+    token: &str,
+) -> Result<usize> {
+    let mut linked = 0;
+
+    for github_user in GithubUser::unlinked(db)? {
+        let profile = state
+            .github
+            .get_user_by_login(&github_user.login, token)
+            .await?;
+        let email = match profile.email {
+            Some(email) if !email.is_empty() => email,
+            _ => continue,
+        };
+
+        let user = match state.slack.lookup_user_by_email(&email).await? {
+            Some(slack_user_id) => User::find_by_slack_id(&slack_user_id, db)?,
+            None => None,
+        };
+        if let Some(user) = user {
+            github_user.link(user.id, db)?;
+            linked += 1;
+        }
+    }
+
+    Ok(linked)
+}
@@ -0,0 +1,169 @@
+use chrono::NaiveDate;
+
+/// Longer events are skipped rather than taken as a month or more off, since
+/// they're more likely a mistake or a term than holidays
+const MAX_EVENT_DAYS: i64 = 31;
+
+/// Reads the events in an iCalendar file as holidays, one for each day an
+/// event covers. Times are ignored, so an event counts for the whole day.
+/// Events over `MAX_EVENT_DAYS` long are skipped.
+pub fn holidays(ics: &str) -> Vec<(NaiveDate, String)> {
+    let mut holidays = vec![];
+    let mut event: Option<Event> = None;
+
+    for line in unfold(ics) {
+        let (name, value) = match property(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+
+        match (name.as_str(), event.as_mut()) {
+            ("BEGIN", _) if value == "VEVENT" => event = Some(Event::default()),
+            ("END", Some(current)) if value == "VEVENT" => {
+                holidays.extend(current.days());
+                event = None;
+            }
+            ("DTSTART", Some(current)) => current.start = date(value),
+            ("DTEND", Some(current)) => current.end = date(value),
+            ("SUMMARY", Some(current)) => current.summary = unescape(value),
+            _ => (),
+        }
+    }
+
+    holidays.sort();
+    holidays
+}
+
+#[derive(Default)]
+struct Event {
+    start: Option<NaiveDate>,
+    /// The day after the event, which all-day events end on
+    end: Option<NaiveDate>,
+    summary: String,
+}
+
+impl Event {
+    fn days(&self) -> Vec<(NaiveDate, String)> {
+        let start = match self.start {
+            Some(start) => start,
+            None => return vec![],
+        };
+        let end = self
+            .end
+            .filter(|end| *end > start)
+            .unwrap_or_else(|| start.succ());
+        if (end - start).num_days() > MAX_EVENT_DAYS {
+            return vec![];
+        }
+
+        let mut days = vec![];
+        let mut day = start;
+        while day < end {
+            days.push((day, self.summary.clone()));
+            day = day.succ();
+        }
+
+        days
+    }
+}
+
+/// Long lines are folded onto following lines that start with whitespace
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match lines.last_mut() {
+            Some(last) if line.starts_with(' ') || line.starts_with('\t') => {
+                last.push_str(&line[1..])
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Splits `NAME;PARAM=VALUE:value` into the name and value
+fn property(line: &str) -> Option<(String, &str)> {
+    let colon = line.find(':')?;
+    let name = line[..colon].split(';').next()?;
+
+    Some((name.to_uppercase(), &line[colon + 1..]))
+}
+
+/// Dates are `20261225`, or `20261225T090000Z` with a time
+fn date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_holidays() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   VERSION:2.0\r\n\
+                   BEGIN:VEVENT\r\n\
+                   DTSTART;VALUE=DATE:20261225\r\n\
+                   DTEND;VALUE=DATE:20261227\r\n\
+                   SUMMARY:Christmas Day\\, and Boxing\r\n  Day\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Thanksgiving\r\n\
+                   DTSTART:20261126T000000Z\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:No date\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+
+        assert_eq!(
+            holidays(ics),
+            vec![
+                (
+                    NaiveDate::from_ymd(2026, 11, 26),
+                    "Thanksgiving".to_string()
+                ),
+                (
+                    NaiveDate::from_ymd(2026, 12, 25),
+                    "Christmas Day, and Boxing Day".to_string()
+                ),
+                (
+                    NaiveDate::from_ymd(2026, 12, 26),
+                    "Christmas Day, and Boxing Day".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_holidays_skips_long_events() {
+        let event = |end: &str| {
+            format!(
+                "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20260101\nDTEND;VALUE=DATE:{}\nSUMMARY:Term\nEND:VEVENT\n",
+                end
+            )
+        };
+
+        assert_eq!(holidays(&event("20260201")).len(), 31);
+        assert!(holidays(&event("20260202")).is_empty());
+        assert!(holidays(&event("99991231")).is_empty());
+    }
+
+    #[test]
+    fn test_holidays_ignores_other_components() {
+        let ics = "BEGIN:VTODO\nDTSTART:20261225\nSUMMARY:Shop\nEND:VTODO\n";
+        assert!(holidays(ics).is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::github::PRResult;
+use crate::models::IgnoreRule;
+
+/// What an ignore rule's pattern is matched against
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IgnoreRuleKind {
+    /// A glob on the author's login
+    Author,
+    /// Any author Github marks as a bot, the pattern isn't used
+    Bot,
+    /// A glob on the branch being merged
+    HeadBranch,
+    /// A glob on the branch being merged into
+    BaseBranch,
+    /// A glob on the label names
+    Label,
+    /// A regex on the title
+    Title,
+    /// A glob every changed file matches, like `docs/*`
+    Paths,
+}
+
+pub const IGNORE_RULE_KINDS: [IgnoreRuleKind; 7] = [
+    IgnoreRuleKind::Author,
+    IgnoreRuleKind::Bot,
+    IgnoreRuleKind::HeadBranch,
+    IgnoreRuleKind::BaseBranch,
+    IgnoreRuleKind::Label,
+    IgnoreRuleKind::Title,
+    IgnoreRuleKind::Paths,
+];
+
+impl fmt::Display for IgnoreRuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IgnoreRuleKind::Author => write!(f, "author"),
+            IgnoreRuleKind::Bot => write!(f, "bot"),
+            IgnoreRuleKind::HeadBranch => write!(f, "head_branch"),
+            IgnoreRuleKind::BaseBranch => write!(f, "base_branch"),
+            IgnoreRuleKind::Label => write!(f, "label"),
+            IgnoreRuleKind::Title => write!(f, "title"),
+            IgnoreRuleKind::Paths => write!(f, "paths"),
+        }
+    }
+}
+
+impl FromStr for IgnoreRuleKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        IGNORE_RULE_KINDS
+            .iter()
+            .find(|kind| kind.to_string() == s)
+            .copied()
+            .ok_or_else(|| format!("`{}` isn't a kind of ignore rule", s))
+    }
+}
+
+/// Why the pull request shouldn't be posted, from the first of `rules` it
+/// matches. `filenames` are the changed files, which are empty when they
+/// couldn't be fetched. Path rules only match when every changed file was
+/// fetched.
+pub fn ignore_reason(
+    rules: &[IgnoreRule],
+    pull_request: &PRResult,
+    filenames: &[String],
+) -> Option<String> {
+    rules
+        .iter()
+        .find_map(|rule| rule_reason(rule, pull_request, filenames))
+}
+
+fn rule_reason(rule: &IgnoreRule, pull_request: &PRResult, filenames: &[String]) -> Option<String> {
+    let pattern = rule.pattern.as_str();
+
+    match rule.kind.parse().ok()? {
+        IgnoreRuleKind::Author if glob_matches(pattern, &pull_request.user.login) => Some(format!(
+            "author {} matches `{}`",
+            pull_request.user.login, pattern
+        )),
+        IgnoreRuleKind::Bot if pull_request.user.account_type == "Bot" => {
+            Some(format!("author {} is a bot", pull_request.user.login))
+        }
+        IgnoreRuleKind::HeadBranch if glob_matches(pattern, &pull_request.head.ref_name) => {
+            Some(format!(
+                "branch {} matches `{}`",
+                pull_request.head.ref_name, pattern
+            ))
+        }
+        IgnoreRuleKind::BaseBranch if glob_matches(pattern, &pull_request.base.ref_name) => {
+            Some(format!(
+                "base branch {} matches `{}`",
+                pull_request.base.ref_name, pattern
+            ))
+        }
+        IgnoreRuleKind::Label => pull_request
+            .labels
+            .iter()
+            .find(|label| glob_matches(pattern, &label.name))
+            .map(|label| format!("label {} matches `{}`", label.name, pattern)),
+        IgnoreRuleKind::Title
+            if Regex::new(pattern)
+                .map(|regex| regex.is_match(&pull_request.title))
+                .unwrap_or(false) =>
+        {
+            Some(format!("title matches `{}`", pattern))
+        }
+        IgnoreRuleKind::Paths
+            if !filenames.is_empty()
+                && filenames.len() >= pull_request.changed_files as usize
+                && filenames
+                    .iter()
+                    .all(|filename| glob_matches(pattern, filename)) =>
+        {
+            Some(format!("every changed file matches `{}`", pattern))
+        }
+        _ => None,
+    }
+}
+
+/// Matches `*` to any run of characters, slashes included, and `?` to any
+/// one character
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Where to resume from after the last `*`, in the pattern and the text
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    fn rule(kind: IgnoreRuleKind, pattern: &str) -> IgnoreRule {
+        let now = Utc::now().naive_utc();
+        IgnoreRule {
+            id: 1,
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            repo: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn pull_request() -> PRResult {
+        serde_json::from_value(serde_json::json!({
+            "url": "https://api.github.com/repos/facebook/react/pulls/1",
+            "html_url": "https://github.com/facebook/react/pull/1",
+            "title": "WIP: Bump lodash from 4.17.15 to 4.17.19",
+            "body": "",
+            "state": "open",
+            "merged": false,
+            "review_comments": 0,
+            "additions": 1,
+            "deletions": 1,
+            "number": 1,
+            "labels": [{ "name": "dependencies" }],
+            "user": {
+                "id": 1,
+                "login": "dependabot[bot]",
+                "avatar_url": "",
+                "html_url": "",
+                "type": "Bot"
+            },
+            "head": { "ref": "dependabot/npm_and_yarn/lodash-4.17.19" },
+            "base": {
+                "ref": "main",
+                "repo": {
+                    "id": 1,
+                    "name": "react",
+                    "full_name": "facebook/react",
+                    "private": false,
+                    "owner": { "id": 2, "login": "facebook", "avatar_url": "", "html_url": "" }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches(
+            "dependabot/*",
+            "dependabot/npm_and_yarn/lodash"
+        ));
+        assert!(glob_matches("*.md", "docs/README.md"));
+        assert!(glob_matches("release-?.?", "release-1.2"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "aXbYbc"));
+        assert!(!glob_matches("docs/*", "src/docs/index.md"));
+        assert!(!glob_matches("main", "main-2"));
+    }
+
+    #[test]
+    fn test_ignore_reason() {
+        let pr = pull_request();
+        let files = vec!["docs/index.md".to_string(), "docs/api.md".to_string()];
+        let reason = |kind, pattern| ignore_reason(&[rule(kind, pattern)], &pr, &files);
+
+        assert_eq!(
+            reason(IgnoreRuleKind::Author, "dependabot*"),
+            Some("author dependabot[bot] matches `dependabot*`".to_string())
+        );
+        assert!(reason(IgnoreRuleKind::Bot, "").is_some());
+        assert!(reason(IgnoreRuleKind::HeadBranch, "dependabot/*").is_some());
+        assert!(reason(IgnoreRuleKind::BaseBranch, "release/*").is_none());
+        assert!(reason(IgnoreRuleKind::Label, "dependenc*").is_some());
+        assert_eq!(
+            reason(IgnoreRuleKind::Title, "(?i)^wip\\b"),
+            Some("title matches `(?i)^wip\\b`".to_string())
+        );
+        assert!(reason(IgnoreRuleKind::Title, "(unclosed").is_none());
+        assert!(reason(IgnoreRuleKind::Paths, "docs/*").is_some());
+        assert!(reason(IgnoreRuleKind::Paths, "*.rs").is_none());
+        assert!(ignore_reason(&[rule(IgnoreRuleKind::Paths, "*")], &pr, &[]).is_none());
+
+        let truncated = PRResult {
+            changed_files: 3,
+            ..pr
+        };
+        assert!(
+            ignore_reason(&[rule(IgnoreRuleKind::Paths, "docs/*")], &truncated, &files).is_none()
+        );
+    }
+
+    #[test]
+    fn test_ignore_rule_kind_round_trip() {
+        for kind in IGNORE_RULE_KINDS.iter() {
+            assert_eq!(kind.to_string().parse(), Ok(*kind));
+        }
+        assert!("branch".parse::<IgnoreRuleKind>().is_err());
+    }
+}
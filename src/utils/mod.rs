@@ -1,9 +1,17 @@
 extern crate serde_yaml;
 
 pub mod app_config;
+pub mod away;
+pub mod business_time;
 pub mod db;
+pub mod dependency_digest;
 pub mod helpers;
+pub mod ical;
+pub mod ignore_rules;
+pub mod notifications;
+pub mod outbound;
 pub mod paginated_resource;
+pub mod scheduler;
 
 use actix_web::HttpResponse;
 
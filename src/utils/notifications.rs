@@ -0,0 +1,203 @@
+use chrono::{NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db::DBExecutor;
+use crate::error::Result;
+use crate::models::{NotificationPreference, NotificationSettings, PullRequest, User};
+use crate::AppData;
+
+/// How long reviews of a pull request are collected before its author is sent
+/// them together
+const REVIEW_BATCH_WINDOW: Duration = Duration::from_secs(120);
+
+/// The direct messages users can turn off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Notification {
+    ReviewRequest,
+    Review,
+    Merge,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DigestFrequency {
+    #[default]
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl fmt::Display for DigestFrequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestFrequency::Off => write!(f, "off"),
+            DigestFrequency::Daily => write!(f, "daily"),
+            DigestFrequency::Weekly => write!(f, "weekly"),
+        }
+    }
+}
+
+impl FromStr for DigestFrequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(DigestFrequency::Off),
+            "daily" => Ok(DigestFrequency::Daily),
+            "weekly" => Ok(DigestFrequency::Weekly),
+            _ => Err(format!("`{}` isn't daily, weekly or off", s)),
+        }
+    }
+}
+
+/// Whether `user` wants `notification` sent right now. Anything arriving in
+/// their quiet hours or while they're away is dropped, since the Home tab
+/// still lists it.
+pub fn wants(user: &User, notification: Notification, db: &DBExecutor) -> Result<bool> {
+    let settings = NotificationPreference::settings(user.id, db)?;
+    let enabled = match notification {
+        Notification::ReviewRequest => settings.review_requests,
+        Notification::Review => settings.reviews,
+        Notification::Merge => settings.merges,
+    };
+
+    let now = Utc::now().naive_utc();
+
+    Ok(enabled && !in_quiet_hours(&settings, now) && !user.is_away(now))
+}
+
+/// Whether `now`, in UTC, falls in the user's quiet hours. The hours can wrap
+/// past midnight, like 22 to 7.
+pub fn in_quiet_hours(settings: &NotificationSettings, now: NaiveDateTime) -> bool {
+    let (start, end) = match (settings.quiet_hours_start, settings.quiet_hours_end) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return false,
+    };
+    let hour = settings.tz().from_utc_datetime(&now).hour() as i32;
+
+    if start <= end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+/// Parses an IANA time zone name like `America/New_York`
+pub fn parse_time_zone(text: &str) -> Option<Tz> {
+    text.trim().parse().ok()
+}
+
+/// An author's Slack id and the pull request they're being sent reviews of
+type BatchKey = (String, String);
+
+/// Reviews waiting to be sent to their authors
+#[derive(Clone, Default)]
+pub struct ReviewBatches {
+    pending: Arc<Mutex<HashMap<BatchKey, Vec<String>>>>,
+}
+
+impl ReviewBatches {
+    /// Adds a review to its batch, returning true when that started the batch
+    fn add(&self, key: &BatchKey, review: String) -> bool {
+        let mut pending = self.pending.lock().expect("Lock failed");
+        let batch = pending.entry(key.clone()).or_default();
+        batch.push(review);
+
+        batch.len() == 1
+    }
+
+    fn take(&self, key: &BatchKey) -> Vec<String> {
+        let mut pending = self.pending.lock().expect("Lock failed");
+        pending.remove(key).unwrap_or_default()
+    }
+}
+
+/// Sends the author a direct message about a review of their pull request.
+/// Reviews arriving within a couple of minutes of each other share a message.
+pub fn notify_author_of_review(
+    state: &AppData,
+    author: &User,
+    pull_request: &PullRequest,
+    review: String,
+) {
+    let key = (author.slack_user_id.clone(), pull_request.github_id.clone());
+    if !state.review_batches.add(&key, review) {
+        return;
+    }
+
+    let state = state.clone();
+    let heading = format!(
+        "*Your pull request <{}|{}> was reviewed*",
+        pull_request.html_url,
+        if pull_request.title.is_empty() {
+            &pull_request.display_text
+        } else {
+            &pull_request.title
+        }
+    );
+    actix_rt::spawn(async move {
+        actix_rt::time::delay_for(REVIEW_BATCH_WINDOW).await;
+
+        let reviews = state.review_batches.take(&key);
+        let text = format!("{}\n{}", heading, reviews.join("\n"));
+        if let Err(err) = state.slack.post_text(&key.0, &text).await {
+            log::warn!("Couldn't notify {} of reviews: {}", key.0, err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_review_batches() {
+        let batches = ReviewBatches::default();
+        let key = ("U1".to_string(), "facebook/react-1".to_string());
+
+        assert!(batches.add(&key, "approved".to_string()));
+        assert!(!batches.add(&key, "commented".to_string()));
+        assert_eq!(batches.take(&key), vec!["approved", "commented"]);
+        assert!(batches.add(&key, "approved again".to_string()));
+    }
+
+    #[test]
+    fn test_in_quiet_hours() {
+        let at = |hour| chrono::NaiveDate::from_ymd(2026, 10, 19).and_hms(hour, 30, 0);
+        let mut settings = NotificationSettings::new(1);
+        assert!(!in_quiet_hours(&settings, at(3)));
+
+        settings.quiet_hours_start = Some(22);
+        settings.quiet_hours_end = Some(7);
+        assert!(in_quiet_hours(&settings, at(23)));
+        assert!(in_quiet_hours(&settings, at(3)));
+        assert!(!in_quiet_hours(&settings, at(12)));
+
+        // 20:30 UTC is 22:30 in Berlin's summer time, but 21:30 once it ends
+        settings.time_zone = "Europe/Berlin".to_string();
+        assert!(in_quiet_hours(&settings, at(20)));
+        assert!(!in_quiet_hours(&settings, at(5)));
+        let winter = chrono::NaiveDate::from_ymd(2026, 12, 1).and_hms(20, 30, 0);
+        assert!(!in_quiet_hours(&settings, winter));
+
+        settings.quiet_hours_start = Some(12);
+        settings.quiet_hours_end = Some(13);
+        assert!(in_quiet_hours(&settings, at(10)));
+        assert!(!in_quiet_hours(&settings, at(11)));
+    }
+
+    #[test]
+    fn test_parse_time_zone() {
+        assert_eq!(
+            parse_time_zone(" America/New_York "),
+            Some(Tz::America__New_York)
+        );
+        assert_eq!(parse_time_zone("UTC"), Some(Tz::UTC));
+        assert_eq!(parse_time_zone("+02:00"), None);
+        assert_eq!(parse_time_zone("pacific"), None);
+    }
+}
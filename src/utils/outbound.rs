@@ -0,0 +1,259 @@
+use rand::Rng;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+/// A `Retry-After` longer than this is treated as a failure rather than waited out
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Timeouts and retry behaviour shared by every outbound Slack and Github call
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(5),
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout = Duration::from_secs(secs);
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+    }
+
+    /// Sends a request, retrying rate limited responses and, for idempotent
+    /// calls, timeouts and server errors. Any other response, or the last one
+    /// once retries run out, is returned as is for the caller to classify.
+    pub async fn send(
+        &self,
+        api: &str,
+        request: reqwest::RequestBuilder,
+        idempotent: bool,
+    ) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+
+        loop {
+            let retry_request = if attempt < self.max_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let current = match retry_request {
+                Some(retry_request) => retry_request,
+                None => return request.send().await.map_err(|e| request_error(api, &e)),
+            };
+
+            let delay = match current.send().await {
+                Ok(res) if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    match retry_after(&res) {
+                        Some(wait) if wait > MAX_RETRY_AFTER => return Ok(res),
+                        Some(wait) => wait,
+                        None => self.backoff(attempt),
+                    }
+                }
+                Ok(res) if res.status().is_server_error() && idempotent => self.backoff(attempt),
+                Ok(res) => return Ok(res),
+                Err(err) if idempotent && (err.is_timeout() || err.is_connect()) => {
+                    self.backoff(attempt)
+                }
+                Err(err) => return Err(request_error(api, &err)),
+            };
+
+            log::warn!(
+                "{} failed, retrying in {}ms (attempt {})",
+                api,
+                delay.as_millis(),
+                attempt + 1
+            );
+            actix_rt::time::delay_for(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Exponential backoff with jitter: somewhere between half and all of
+    /// `base_delay * 2^attempt`, capped at `max_delay`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let millis = exponential.as_millis() as u64;
+        let jitter = rand::thread_rng().gen_range(0, millis / 2 + 1);
+
+        Duration::from_millis(millis / 2 + jitter)
+    }
+}
+
+/// Turns an unsuccessful status into an error recording which call failed
+pub fn error_for_status(api: &str, res: reqwest::Response) -> Result<reqwest::Response> {
+    let status = res.status();
+
+    if status.is_client_error() || status.is_server_error() {
+        Err(outbound_error(
+            api,
+            Some(status),
+            status.canonical_reason().unwrap_or("Request failed"),
+        ))
+    } else {
+        Ok(res)
+    }
+}
+
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    res.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+fn request_error(api: &str, err: &reqwest::Error) -> Error {
+    let message = if err.is_timeout() {
+        "Timed out".to_string()
+    } else {
+        format!("{}", err)
+    };
+
+    outbound_error(api, err.status(), &message)
+}
+
+fn outbound_error(api: &str, status: Option<reqwest::StatusCode>, message: &str) -> Error {
+    Error::OutboundError {
+        api: api.to_owned(),
+        status: status.map(|status| status.as_u16()),
+        message: message.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            ..RetryPolicy::default()
+        }
+        .max_retries(max_retries)
+    }
+
+    /// Answers every request with `status`, counting the requests
+    fn serve(status: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buffer = [0; 1024];
+                let _ = stream.read(&mut buffer);
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[test]
+    fn test_backoff_bounds() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 0..40 {
+            let exponential =
+                (policy.base_delay * 2u32.saturating_pow(attempt.min(31))).min(policy.max_delay);
+            let delay = policy.backoff(attempt);
+
+            assert!(
+                delay >= exponential / 2,
+                "attempt {} waited {:?}",
+                attempt,
+                delay
+            );
+            assert!(
+                delay <= exponential,
+                "attempt {} waited {:?}",
+                attempt,
+                delay
+            );
+        }
+        assert!(policy.backoff(100) <= policy.max_delay);
+    }
+
+    #[actix_rt::test]
+    async fn test_retries_server_errors_when_idempotent() {
+        let (url, requests) = serve("503 Service Unavailable");
+        let client = reqwest::Client::new();
+
+        let res = policy(2)
+            .send("test", client.get(&url), true)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_rt::test]
+    async fn test_doesnt_retry_server_errors_otherwise() {
+        let (url, requests) = serve("503 Service Unavailable");
+        let client = reqwest::Client::new();
+
+        policy(2)
+            .send("test", client.post(&url), false)
+            .await
+            .unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_returns_last_rate_limited_response() {
+        let (url, requests) = serve("429 Too Many Requests");
+        let client = reqwest::Client::new();
+
+        let res = policy(1)
+            .send("test", client.post(&url), false)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
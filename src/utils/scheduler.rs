@@ -0,0 +1,370 @@
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike, Utc, Weekday};
+use rand::Rng;
+
+use crate::db::DBExecutor;
+use crate::error::Result;
+use crate::models::{
+    ChannelSchedule, GithubUser, NotificationPreference, NotificationSettings, PullRequest,
+    QueueQuery, RepoSetting, Review, ReviewClaim, SchedulerLock, SlaBreach, User,
+};
+use crate::slack::Reaction;
+use crate::utils::business_time::WorkingHours;
+use crate::utils::helpers::{queue_entries, ReviewQueue};
+use crate::utils::notifications::{in_quiet_hours, DigestFrequency};
+use crate::{AppConfig, AppData};
+
+const TICK: std::time::Duration = std::time::Duration::from_secs(60);
+const LOCK_NAME: &str = "scheduler";
+/// Longer than a single Slack or Github call can wait on rate limits, which is
+/// up to five minutes, and renewed between channels so a slow run keeps it.
+/// Short enough that another instance takes over soon after the holder stops.
+const LOCK_TTL_MINUTES: i64 = 10;
+/// Personal digests go out at the start of the user's working day
+const USER_DIGEST_HOUR: u32 = 9;
+
+/// Starts sending digests and nudges every minute in the background. Every
+/// instance runs the loop but only the one holding the lock sends anything.
+pub fn start(config: AppConfig, db: DBExecutor) {
+    let holder = format!("{}-{}", std::process::id(), rand::thread_rng().gen::<u32>());
+
+    actix_rt::spawn(async move {
+        loop {
+            actix_rt::time::delay_for(TICK).await;
+
+            // Nothing can be sent until setup is complete
+            let state = match &*config.data.lock().expect("Lock failed") {
+                Some(state) => state.clone(),
+                None => continue,
+            };
+
+            if renew_lock(&holder, &db) {
+                run(&state, &db, &holder).await;
+            }
+        }
+    });
+}
+
+/// Takes or extends the lock for `holder`, returning whether they hold it
+fn renew_lock(holder: &str, db: &DBExecutor) -> bool {
+    match SchedulerLock::acquire(LOCK_NAME, holder, Duration::minutes(LOCK_TTL_MINUTES), db) {
+        Ok(held) => held,
+        Err(err) => {
+            log::error!("Couldn't take the scheduler lock: {}", err);
+            false
+        }
+    }
+}
+
+/// Sends what's due, stopping early if the lock is lost partway through so
+/// two instances never send at once
+async fn run(state: &AppData, db: &DBExecutor, holder: &str) {
+    let now = Utc::now().naive_utc();
+    let lost_lock = || {
+        let lost = !renew_lock(holder, db);
+        if lost {
+            log::warn!("Lost the scheduler lock, stopping this run");
+        }
+        lost
+    };
+
+    let schedules = match ChannelSchedule::all(db) {
+        Ok(schedules) => schedules,
+        Err(err) => {
+            log::error!("Couldn't load channel schedules: {}", err);
+            return;
+        }
+    };
+    for schedule in schedules {
+        if lost_lock() {
+            return;
+        }
+        if let Err(err) = run_channel(state, db, &schedule, now).await {
+            log::error!(
+                "Scheduled messages for {} failed: {}",
+                schedule.channel_id,
+                err
+            );
+        }
+    }
+
+    if lost_lock() {
+        return;
+    }
+    if let Err(err) = check_review_slas(state, db, now).await {
+        log::error!("Checking review SLAs failed: {}", err);
+    }
+
+    if lost_lock() {
+        return;
+    }
+    if let Err(err) = send_user_digests(state, db, now).await {
+        log::error!("Sending digests failed: {}", err);
+    }
+}
+
+async fn run_channel(
+    state: &AppData,
+    db: &DBExecutor,
+    schedule: &ChannelSchedule,
+    now: NaiveDateTime,
+) -> Result<()> {
+    let working_hours = WorkingHours::for_channel(&schedule.channel_id, db)?;
+
+    if let Some(hour) = schedule.digest_hour {
+        // Marked sent first, so a failed or repeated run can't post it twice
+        if digest_due(hour as u32, &working_hours, schedule.last_digest_at, now) {
+            schedule.digest_sent(now, db)?;
+            post_channel_digest(state, db, schedule).await?;
+        }
+    }
+
+    if let Some(hours) = schedule.nudge_after_hours {
+        nudge_stale_pull_requests(state, db, schedule, &working_hours, hours.into(), now).await?;
+    }
+
+    Ok(())
+}
+
+/// Posts the pull requests in the channel that are waiting for review
+async fn post_channel_digest(
+    state: &AppData,
+    db: &DBExecutor,
+    schedule: &ChannelSchedule,
+) -> Result<()> {
+    let query = QueueQuery {
+        channel: Some(schedule.channel_id.clone()),
+        ..QueueQuery::default()
+    };
+    let prs = PullRequest::waiting(&query, db)?;
+    let pr_ids: Vec<i32> = prs.iter().map(|pr| pr.id).collect();
+    let author_ids: Vec<i32> = prs.iter().map(|pr| pr.github_user_id).collect();
+    let reviews = Review::for_pull_requests(&pr_ids, db)?;
+    let authors = GithubUser::by_github_ids(&author_ids, db)?;
+    let slack_ids = GithubUser::slack_user_ids(&author_ids, db)?;
+
+    state
+        .slack
+        .reviews_response(
+            &queue_entries(&prs, &reviews, &authors, &slack_ids),
+            &schedule.channel_id,
+        )
+        .await
+}
+
+/// Reminds the thread of each pull request nobody has reviewed in
+/// `after_hours` working hours, and again every `after_hours` after that
+async fn nudge_stale_pull_requests(
+    state: &AppData,
+    db: &DBExecutor,
+    schedule: &ChannelSchedule,
+    working_hours: &WorkingHours,
+    after_hours: i64,
+    now: NaiveDateTime,
+) -> Result<()> {
+    let query = QueueQuery {
+        channel: Some(schedule.channel_id.clone()),
+        ..QueueQuery::default()
+    };
+    let prs = PullRequest::waiting(&query, db)?;
+    let pr_ids: Vec<i32> = prs.iter().map(|pr| pr.id).collect();
+    let reviews = Review::for_pull_requests(&pr_ids, db)?;
+
+    for pr in prs {
+        if reviews.iter().any(|review| review.pull_request_id == pr.id) {
+            continue;
+        }
+        let since = pr.last_nudged_at.unwrap_or(pr.created_at);
+        if working_hours.between(since, now) < Duration::hours(after_hours) {
+            continue;
+        }
+
+        let reviewers: Vec<String> = ReviewClaim::users(&pr, db)?
+            .iter()
+            .filter(|reviewer| !reviewer.is_away(now))
+            .map(|reviewer| format!("<@{}>", reviewer.slack_user_id))
+            .collect();
+        let text = if reviewers.is_empty() {
+            format!(
+                ":hourglass: This pull request has been waiting over {} working hours for a review",
+                after_hours
+            )
+        } else {
+            format!(
+                ":hourglass: {}: this pull request has been waiting over {} working hours for your review",
+                reviewers.join(", "),
+                after_hours
+            )
+        };
+
+        pr.nudged(now, db)?;
+        state
+            .slack
+            .post_thread_reply(&pr.channel, &pr.slack_message_id, &text, None)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Escalates each pull request that's gone unreviewed for longer than its
+/// repository's SLA, in the working hours of the channel it was posted in.
+/// A pull request is only escalated once.
+async fn check_review_slas(state: &AppData, db: &DBExecutor, now: NaiveDateTime) -> Result<()> {
+    for setting in RepoSetting::with_review_sla(db)? {
+        let sla_hours = match setting.review_sla_hours {
+            Some(hours) => hours,
+            None => continue,
+        };
+        let query = QueueQuery {
+            repos: vec![setting.repo.clone()],
+            ..QueueQuery::default()
+        };
+        let prs = PullRequest::waiting(&query, db)?;
+        let pr_ids: Vec<i32> = prs.iter().map(|pr| pr.id).collect();
+        let reviews = Review::for_pull_requests(&pr_ids, db)?;
+        let breached = SlaBreach::pull_request_ids(&pr_ids, db)?;
+
+        for pr in prs {
+            if breached.contains(&pr.id)
+                || reviews.iter().any(|review| review.pull_request_id == pr.id)
+            {
+                continue;
+            }
+            let working_hours = WorkingHours::for_channel(&pr.channel, db)?;
+            if working_hours.between(pr.created_at, now) < Duration::hours(sla_hours.into()) {
+                continue;
+            }
+
+            let text = match &setting.escalation_mention {
+                Some(mention) => format!(
+                    ":rotating_light: {}: this pull request has gone {} working hours without a review, past the SLA for {}",
+                    mention, sla_hours, setting.repo
+                ),
+                None => format!(
+                    ":rotating_light: This pull request has gone {} working hours without a review, past the SLA for {}",
+                    sla_hours, setting.repo
+                ),
+            };
+
+            // Recorded first, so a failed post isn't escalated again next tick
+            SlaBreach::create(&pr, sla_hours, db)?;
+            state
+                .slack
+                .post_thread_reply(&pr.channel, &pr.slack_message_id, &text, None)
+                .await?;
+            if pr.digest_id.is_none() {
+                state
+                    .slack
+                    .add_reaction(
+                        &Reaction::SlaBreach,
+                        &pr.slack_message_id,
+                        &pr.channel,
+                        None,
+                    )
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the users who asked for one a digest of the pull requests waiting on
+/// their review, skipping anyone with nothing waiting
+async fn send_user_digests(state: &AppData, db: &DBExecutor, now: NaiveDateTime) -> Result<()> {
+    for preference in NotificationPreference::with_digest(db)? {
+        let settings = NotificationSettings::from(preference.clone());
+        let working_hours = WorkingHours::in_zone(settings.tz());
+        let local_today = working_hours.local(now).date();
+        let on_today = match preference.digest.parse().unwrap_or_default() {
+            DigestFrequency::Off => false,
+            DigestFrequency::Daily => true,
+            DigestFrequency::Weekly => local_today.weekday() == Weekday::Mon,
+        };
+        let due = digest_due(
+            USER_DIGEST_HOUR,
+            &working_hours,
+            preference.last_digest_at,
+            now,
+        );
+        if !on_today || !due || in_quiet_hours(&settings, now) {
+            continue;
+        }
+
+        let user = match User::find(preference.user_id, db)? {
+            Some(user) if !user.is_away(now) => user,
+            _ => continue,
+        };
+        preference.digest_sent(now, db)?;
+        let queue = ReviewQueue::for_user(&user, db)?;
+        if !queue.to_review.is_empty() {
+            state
+                .slack
+                .reviews_response(&queue.to_review_entries(), &user.slack_user_id)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a digest sent at `hour` local time on working days is due. It's
+/// due from that hour until it's been sent that day, so a missed tick is
+/// caught up on the next one.
+fn digest_due(
+    hour: u32,
+    working_hours: &WorkingHours,
+    last_sent: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+) -> bool {
+    let local_now = working_hours.local(now);
+    let sent_today = last_sent
+        .map(|sent| working_hours.local(sent).date() == local_now.date())
+        .unwrap_or(false);
+
+    working_hours.is_working_day(local_now.date()) && local_now.hour() >= hour && !sent_today
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_digest_due() {
+        // A Monday
+        let at = |hour| NaiveDate::from_ymd(2026, 10, 19).and_hms(hour, 0, 0);
+        let utc = WorkingHours::default();
+
+        assert!(!digest_due(9, &utc, None, at(8)));
+        assert!(digest_due(9, &utc, None, at(9)));
+        assert!(digest_due(9, &utc, None, at(15)));
+        assert!(!digest_due(9, &utc, Some(at(9)), at(15)));
+        assert!(digest_due(
+            9,
+            &utc,
+            Some(NaiveDate::from_ymd(2026, 10, 16).and_hms(9, 0, 0)),
+            at(9)
+        ));
+        // 8:00 UTC is 10:00 in Berlin's summer time
+        assert!(digest_due(
+            9,
+            &WorkingHours::in_zone(chrono_tz::Tz::Europe__Berlin),
+            None,
+            at(8)
+        ));
+        // Sunday
+        assert!(!digest_due(
+            9,
+            &utc,
+            None,
+            NaiveDate::from_ymd(2026, 10, 18).and_hms(10, 0, 0)
+        ));
+        // A holiday
+        let holiday = WorkingHours {
+            holidays: vec![NaiveDate::from_ymd(2026, 10, 19)],
+            ..WorkingHours::default()
+        };
+        assert!(!digest_due(9, &holiday, None, at(10)));
+    }
+}